@@ -0,0 +1,12 @@
+-- Short-lived codes that let members and freshmen check themselves in to a
+-- technical seminar or directorship meeting.
+CREATE TABLE attendance_checkin_sessions (
+    id serial PRIMARY KEY,
+    code varchar(8) NOT NULL UNIQUE,
+    seminar_id integer REFERENCES technical_seminars(id) ON DELETE CASCADE,
+    meeting_id integer REFERENCES committee_meetings(id) ON DELETE CASCADE,
+    host varchar(32) NOT NULL,
+    expires timestamp NOT NULL,
+    closed boolean NOT NULL DEFAULT false,
+    CHECK ((seminar_id IS NULL) != (meeting_id IS NULL))
+);
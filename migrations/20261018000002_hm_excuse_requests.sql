@@ -0,0 +1,14 @@
+-- Excuse requests submitted by members and freshmen for a house meeting,
+-- reviewed by eboard.
+CREATE TYPE excuse_status_enum AS ENUM ('Pending', 'Accepted', 'Denied');
+
+CREATE TABLE hm_excuse_requests (
+    id serial PRIMARY KEY,
+    meeting_id integer NOT NULL REFERENCES house_meetings(id) ON DELETE CASCADE,
+    uid varchar(32),
+    fid integer REFERENCES freshman_accounts(id) ON DELETE CASCADE,
+    excuse text NOT NULL,
+    status excuse_status_enum NOT NULL DEFAULT 'Pending',
+    submitted timestamp NOT NULL,
+    CHECK ((uid IS NULL) != (fid IS NULL))
+);
@@ -0,0 +1,10 @@
+-- How packet completion is computed in each operating year. An operating
+-- year uses the most recent row starting on or before it, or the defaults if
+-- there is none.
+CREATE TYPE packet_rounding_enum AS ENUM ('Floor', 'Nearest', 'Ceiling');
+
+CREATE TABLE packet_settings (
+    year_start date PRIMARY KEY,
+    misc_cap integer NOT NULL DEFAULT 10 CHECK (misc_cap >= 0),
+    rounding packet_rounding_enum NOT NULL DEFAULT 'Floor'
+);
@@ -0,0 +1,30 @@
+-- Every freshman converted to a member, with what their freshman account
+-- held and where its rows were moved, so a mistaken conversion can be
+-- undone. Attendance, excuse request and batch ids are of the member rows
+-- they were moved to.
+CREATE TABLE freshman_conversions (
+    id serial PRIMARY KEY,
+    fid integer NOT NULL,
+    uid varchar(32) NOT NULL,
+    converted timestamp NOT NULL DEFAULT now(),
+    undone timestamp,
+    name varchar(64) NOT NULL,
+    eval_date date NOT NULL,
+    onfloor_status boolean,
+    room_number varchar,
+    signatures_missed integer,
+    rit_username varchar(10),
+    committee_attendance integer[] NOT NULL DEFAULT '{}',
+    hm_attendance integer[] NOT NULL DEFAULT '{}',
+    seminar_attendance integer[] NOT NULL DEFAULT '{}',
+    excuse_requests integer[] NOT NULL DEFAULT '{}',
+    batch_users integer[] NOT NULL DEFAULT '{}',
+    -- The freshman's batch pull, if they had one, and the member batch pull
+    -- it became. A pull isn't moved if the member already had one.
+    batch_pull_approved boolean,
+    batch_pull_reason varchar,
+    batch_pull_puller varchar,
+    batch_pull integer
+);
+
+CREATE INDEX freshman_conversions_fid_idx ON freshman_conversions (fid);
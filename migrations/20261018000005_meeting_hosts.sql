@@ -0,0 +1,4 @@
+-- Who created each seminar and directorship meeting, so only they and eboard
+-- can open check-in sessions for it. Meetings from before this have no host.
+ALTER TABLE technical_seminars ADD COLUMN host varchar(32);
+ALTER TABLE committee_meetings ADD COLUMN host varchar(32);
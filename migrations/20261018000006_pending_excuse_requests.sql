@@ -0,0 +1,5 @@
+-- Only one excuse request per person can be pending for a meeting at a time.
+CREATE UNIQUE INDEX hm_excuse_requests_pending_uid_idx ON hm_excuse_requests (meeting_id, uid)
+    WHERE status = 'Pending' AND uid IS NOT NULL;
+CREATE UNIQUE INDEX hm_excuse_requests_pending_fid_idx ON hm_excuse_requests (meeting_id, fid)
+    WHERE status = 'Pending' AND fid IS NOT NULL;
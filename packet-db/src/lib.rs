@@ -0,0 +1,84 @@
+//! Queries on the packet app's database.
+//!
+//! The query macros check every query in a crate against one database, so
+//! these live apart from the conditional queries. This crate's `.env` keeps
+//! the macros on the query data in `.sqlx`, which is generated against a
+//! packet database.
+
+use chrono::NaiveDateTime;
+use sqlx::{query_as, query_scalar, Pool, Postgres};
+
+/// A packet as stored in the packet database, before it's been validated
+#[derive(Clone, Debug)]
+pub struct PacketRow {
+    pub id: i32,
+    pub username: Option<String>,
+    pub name: Option<String>,
+    pub start: Option<NaiveDateTime>,
+    pub upper_signatures: i64,
+    pub max_upper: i64,
+    pub misc_signatures: i64,
+}
+
+/// An upperclassman's signature on a packet
+pub struct UpperSignature {
+    pub member: String,
+    pub signed: bool,
+}
+
+/// Every packet, or only those belonging to `rit_username`, newest first
+pub async fn get_packets(
+    db: &Pool<Postgres>,
+    rit_username: Option<&str>,
+) -> Result<Vec<PacketRow>, sqlx::Error> {
+    query_as!(
+        PacketRow,
+        "SELECT p.id AS \"id!\",
+            p.freshman_username AS \"username?\",
+            fm.name AS \"name?\",
+            p.start AS \"start?\",
+            (SELECT count(*) FROM signature_upper su
+                WHERE su.packet_id = p.id AND su.signed) AS \"upper_signatures!\",
+            (SELECT count(*) FROM signature_upper su
+                WHERE su.packet_id = p.id) AS \"max_upper!\",
+            (SELECT count(*) FROM signature_misc sm
+                WHERE sm.packet_id = p.id) AS \"misc_signatures!\"
+        FROM packet p
+        LEFT JOIN freshman fm ON fm.rit_username = p.freshman_username
+        WHERE $1::varchar IS NULL OR trim(p.freshman_username) = $1
+        ORDER BY p.id DESC",
+        rit_username
+    )
+    .fetch_all(db)
+    .await
+}
+
+/// Upperclassmen on packet `packet_id`, and whether they've signed it
+pub async fn get_upper_signatures(
+    db: &Pool<Postgres>,
+    packet_id: i32,
+) -> Result<Vec<UpperSignature>, sqlx::Error> {
+    query_as!(
+        UpperSignature,
+        "SELECT member AS \"member!\", coalesce(signed, false) AS \"signed!\"
+        FROM signature_upper WHERE packet_id = $1 AND member IS NOT NULL
+        ORDER BY member",
+        packet_id
+    )
+    .fetch_all(db)
+    .await
+}
+
+/// Members who've signed packet `packet_id` as misc signatures
+pub async fn get_misc_signatures(
+    db: &Pool<Postgres>,
+    packet_id: i32,
+) -> Result<Vec<String>, sqlx::Error> {
+    query_scalar!(
+        "SELECT member AS \"member!\" FROM signature_misc
+        WHERE packet_id = $1 AND member IS NOT NULL ORDER BY member",
+        packet_id
+    )
+    .fetch_all(db)
+    .await
+}
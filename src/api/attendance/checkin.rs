@@ -0,0 +1,189 @@
+use crate::api::token_person;
+use crate::app::AppState;
+use crate::auth::{CSHAuth, User};
+use crate::error::AppError;
+use crate::repo::attendance::checkin;
+use crate::schema::api::{CheckInCode, CheckInSubmission};
+use crate::transaction::UnitOfWork;
+use actix_web::{
+    post, put,
+    web::{Data, Json, Path},
+    HttpResponse,
+};
+use chrono::{Duration, NaiveDateTime, Utc};
+use lazy_static::lazy_static;
+use log::{log, Level};
+use rand::{distributions::Alphanumeric, Rng};
+use std::env;
+
+const CODE_LENGTH: usize = 6;
+
+lazy_static! {
+    static ref CHECKIN_CODE_MINUTES: i64 = env::var("CHECKIN_CODE_MINUTES")
+        .map(|x| x.parse::<i64>().unwrap_or(15))
+        .unwrap_or(15);
+}
+
+fn generate_code() -> String {
+    rand::thread_rng()
+        .sample_iter(&Alphanumeric)
+        .take(CODE_LENGTH)
+        .map(|c| char::from(c).to_ascii_uppercase())
+        .collect()
+}
+
+fn qr_url(code: &str) -> String {
+    format!(
+        "{}/attendance/checkin/{}",
+        env::var("DOMAIN").unwrap_or("localhost".to_string()),
+        code
+    )
+}
+
+/// Opens a check-in session for either a seminar or a directorship, with a
+/// new code. Only the meeting's host and eboard can open one.
+async fn open_checkin_session(
+    mut uow: UnitOfWork,
+    seminar_id: Option<i32>,
+    meeting_id: Option<i32>,
+    user: &User,
+) -> Result<HttpResponse, AppError> {
+    let host = checkin::get_meeting_host(&mut uow, seminar_id, meeting_id).await?;
+    if !user.eboard() && host.as_deref() != Some(user.preferred_username.as_str()) {
+        log!(
+            Level::Warn,
+            "{} tried to open check-in for a meeting they don't host",
+            user.preferred_username
+        );
+        return Err(AppError::Unauthorized);
+    }
+
+    let code = generate_code();
+    let expires = Utc::now().naive_utc() + Duration::minutes(*CHECKIN_CODE_MINUTES);
+    checkin::open_session(
+        &mut uow,
+        seminar_id,
+        meeting_id,
+        &code,
+        &user.preferred_username,
+        &expires,
+    )
+    .await?;
+
+    uow.commit().await?;
+    Ok(HttpResponse::Created().json(CheckInCode {
+        qr_url: qr_url(&code),
+        code,
+        expires,
+    }))
+}
+
+#[utoipa::path(
+    context_path="/api/attendance",
+    responses(
+        (status = 201, description = "Open a check-in session for a seminar", body = CheckInCode),
+        (status = 400, description = "Invalid id"),
+        (status = 401, description = "Not eboard or the seminar's host"),
+        (status = 404, description = "Seminar not found"),
+        (status = 500, description = "Error created by Query"),
+        )
+    )]
+#[post("/checkin/seminar/{id}", wrap = "CSHAuth::enabled()")]
+pub async fn open_seminar_checkin(
+    path: Path<(String,)>,
+    uow: UnitOfWork,
+    user: User,
+) -> Result<HttpResponse, AppError> {
+    let (id,) = path.into_inner();
+    log!(Level::Info, "POST /attendance/checkin/seminar/{id}");
+    let id = match id.parse::<i32>() {
+        Ok(id) => id,
+        Err(_e) => {
+            log!(Level::Warn, "Invalid id");
+            return Err(AppError::BadRequest(String::from("Invalid id")));
+        }
+    };
+    open_checkin_session(uow, Some(id), None, &user).await
+}
+
+#[utoipa::path(
+    context_path="/api/attendance",
+    responses(
+        (status = 201, description = "Open a check-in session for a directorship", body = CheckInCode),
+        (status = 400, description = "Invalid id"),
+        (status = 401, description = "Not eboard or the directorship's host"),
+        (status = 404, description = "Directorship not found"),
+        (status = 500, description = "Error created by Query"),
+        )
+    )]
+#[post("/checkin/directorship/{id}", wrap = "CSHAuth::enabled()")]
+pub async fn open_directorship_checkin(
+    path: Path<(String,)>,
+    uow: UnitOfWork,
+    user: User,
+) -> Result<HttpResponse, AppError> {
+    let (id,) = path.into_inner();
+    log!(Level::Info, "POST /attendance/checkin/directorship/{id}");
+    let id = match id.parse::<i32>() {
+        Ok(id) => id,
+        Err(_e) => {
+            log!(Level::Warn, "Invalid id");
+            return Err(AppError::BadRequest(String::from("Invalid id")));
+        }
+    };
+    open_checkin_session(uow, None, Some(id), &user).await
+}
+
+#[utoipa::path(
+    context_path="/api/attendance",
+    responses(
+        (status = 200, description = "Close a check-in session"),
+        (status = 404, description = "No open session with this code was opened by the user"),
+        (status = 500, description = "Error created by Query"),
+        )
+    )]
+#[put("/checkin/{code}/close", wrap = "CSHAuth::enabled()")]
+pub async fn close_checkin(
+    path: Path<(String,)>,
+    mut uow: UnitOfWork,
+    user: User,
+) -> Result<HttpResponse, AppError> {
+    let (code,) = path.into_inner();
+    log!(Level::Info, "PUT /attendance/checkin/{code}/close");
+
+    checkin::close_session(&mut uow, &code, &user.preferred_username).await?;
+
+    uow.commit().await?;
+    Ok(HttpResponse::Ok().body(""))
+}
+
+#[utoipa::path(
+    context_path="/api/attendance",
+    request_body = CheckInSubmission,
+    responses(
+        (status = 200, description = "Check in to the meeting the code belongs to"),
+        (status = 401, description = "No valid token, or the freshman account isn't the user's"),
+        (status = 404, description = "Code is invalid, expired, or closed, or no such freshman account"),
+        (status = 500, description = "Error created by Query or LDAP"),
+        )
+    )]
+#[post("/checkin/{code}", wrap = "CSHAuth::enabled()")]
+pub async fn submit_checkin(
+    path: Path<(String,)>,
+    state: Data<AppState>,
+    mut uow: UnitOfWork,
+    user: User,
+    body: Json<CheckInSubmission>,
+) -> Result<HttpResponse, AppError> {
+    let (code,) = path.into_inner();
+    log!(Level::Info, "POST /attendance/checkin/{code}");
+    let now: NaiveDateTime = Utc::now().naive_utc();
+
+    let session = checkin::get_open_session(&mut uow, &code, &now).await?;
+    let person = token_person(&state, &mut uow, &user, body.fid).await?;
+
+    checkin::check_in(&mut uow, &session, &person).await?;
+
+    uow.commit().await?;
+    Ok(HttpResponse::Ok().body(""))
+}
@@ -1,81 +1,65 @@
+use crate::error::{ldap_error, AppError};
+use crate::transaction::UnitOfWork;
 use actix_web::{
-    get, post, put,
+    delete, get, post, put,
     web::{Data, Json, Path},
     HttpResponse, Responder,
 };
-use chrono::NaiveDate;
 use log::{log, Level};
-use sqlx::{query, query_as};
 
 use crate::{
-    api::{log_query, log_query_as, open_transaction},
+    api::{parse_person, token_person},
     app::AppState,
-    schema::{api::*, db::AttendanceStatus},
+    auth::{CSHAuth, User},
+    ldap::{group::Group, search::SearchAttrs},
+    repo::attendance::house,
+    schema::{api::*, db::ExcuseStatus},
 };
 
 #[utoipa::path(context_path="/attendance", responses((status = 200, description = "Submit new house meeting attendance"),(status = 500, description = "Error created by Query"),))]
 #[post("/house")]
 pub async fn submit_hm_attendance(
-    state: Data<AppState>,
+    mut uow: UnitOfWork,
     body: Json<HouseAttendance>,
-) -> impl Responder {
+) -> Result<HttpResponse, AppError> {
     log!(Level::Info, "POST /attendance/house");
-    let mut transaction = match open_transaction(&state.db).await {
-        Ok(t) => t,
-        Err(res) => return res,
-    };
+    house::record_house_meeting(&mut uow, &body).await?;
+
+    // Commit transaction
+    uow.commit().await?;
+    Ok(HttpResponse::Ok().body(""))
+}
 
-    let id: i32;
-    match log_query_as(
-        query_as!(
-            ID,
-            "INSERT INTO house_meetings(date, active) VALUES ($1, true) RETURNING id",
-            body.date
+#[utoipa::path(
+    context_path="/api/attendance",
+    request_body = Date,
+    responses(
+        (status = 201, description = "Start a house meeting with every active member and freshman marked absent", body = HouseMeeting),
+        (status = 500, description = "Error created by Query or LDAP"),
+        (status = 503, description = "LDAP unavailable"),
         )
-        .fetch_all(&state.db)
-        .await,
-        Some(transaction),
-    )
-    .await
+    )]
+#[post("/house/start", wrap = "CSHAuth::eboard_only()")]
+pub async fn start_house_meeting(
+    state: Data<AppState>,
+    mut uow: UnitOfWork,
+    body: Json<Date>,
+) -> Result<HttpResponse, AppError> {
+    log!(Level::Info, "POST /attendance/house/start");
+    let members: Vec<String> = match state
+        .ldap
+        .get_group_members(Group::Active, &SearchAttrs::minimal())
+        .await
     {
-        Ok((tx, i)) => {
-            transaction = tx.unwrap();
-            id = i[0].id;
-        }
-        Err(res) => return res,
-    }
-    log!(Level::Trace, "created new house meeting");
-
-    let frosh_id = vec![id; body.frosh.len()];
-    let member_id = vec![id; body.members.len()];
-    let frosh_names: Vec<i32> = body.frosh.iter().map(|a| a.fid).collect();
-    let frosh_statuses: Vec<AttendanceStatus> = body.frosh.iter().map(|a| a.att_status).collect();
-    let member_names: Vec<i32> = body.frosh.iter().map(|a| a.fid).collect();
-    let member_statuses: Vec<AttendanceStatus> = body.frosh.iter().map(|a| a.att_status).collect();
-
-    match log_query(
-        query!("INSERT INTO freshman_hm_attendance (fid, meeting_id, attendance_status) SELECT fid, meeting_id, attendance_status as \"attendance_status: AttendanceStatus\" FROM UNNEST($1::int4[], $2::int4[], $3::attendance_enum[]) as a(fid, meeting_id, attendance_status)", frosh_names.as_slice(), frosh_id.as_slice(), frosh_statuses.as_slice() as &[AttendanceStatus])
-        .execute(&state.db).await.map(|_| ()), Some(transaction)).await {
-        Ok(tx) => transaction = tx.unwrap(),
-        Err(res) => return res,
-    }
+        Ok(members) => members.into_iter().map(|m| m.uid).collect(),
+        Err(e) => return Err(ldap_error(e)),
+    };
 
-    match log_query(
-        query!("INSERT INTO member_hm_attendance (uid, meeting_id, attendance_status) SELECT uid, meeting_id, attendance_status as \"attendance_status: AttendanceStatus\" FROM UNNEST($1::int4[], $2::int4[], $3::attendance_enum[]) as a(uid, meeting_id, attendance_status)", member_names.as_slice(), member_id.as_slice(), member_statuses.as_slice() as &[AttendanceStatus])
-        .execute(&state.db).await.map(|_| ()), Some(transaction)).await {
-        Ok(tx) => transaction = tx.unwrap(),
-        Err(res) => return res,
-    }
-    log!(Level::Trace, "added attendance to house meeting");
+    let meeting =
+        house::start_house_meeting(&mut uow, &body.date, &members, &state.year_start).await?;
 
-    // Commit transaction
-    match transaction.commit().await {
-        Ok(_) => HttpResponse::Ok().body(""),
-        Err(e) => {
-            log!(Level::Error, "Transaction failed to commit");
-            HttpResponse::InternalServerError().body(e.to_string())
-        }
-    }
+    uow.commit().await?;
+    Ok(HttpResponse::Created().json(meeting))
 }
 
 #[utoipa::path(context_path="/attendance", responses((status = 200, description = "Get house meetings missed for a given user", body = [NaiveDate]),(status = 400, description = "Invalid user"),(status = 500, description = "Error created by Query"),))]
@@ -86,24 +70,13 @@ pub async fn get_hm_absences_by_user(
 ) -> impl Responder {
     let (user,) = path.into_inner();
     log!(Level::Info, "GET /attendance/house/{user}");
-
-    if user.chars().next().unwrap().is_numeric() {
-        let user: i32 = match user.parse() {
-            Ok(user) => user,
-            Err(_) => {
-                log!(Level::Warn, "Invalid id");
-                return HttpResponse::BadRequest().body("Invalid id");
-            }
-        };
-        match log_query_as(query_as!(Date, "SELECT date FROM house_meetings WHERE date > $1 AND id IN (SELECT meeting_id FROM freshman_hm_attendance WHERE fid = $2 AND attendance_status = 'Absent')", &NaiveDate::from(state.year_start), user).fetch_all(&state.db).await, None).await {
-            Ok((_, hms)) => HttpResponse::Ok().json(hms),
-            Err(e) => return e,
-        }
-    } else {
-        match log_query_as(query_as!(Date, "SELECT date FROM house_meetings WHERE date > $1 AND id IN (SELECT meeting_id FROM member_hm_attendance WHERE uid = $2 AND attendance_status = 'Absent')", &NaiveDate::from(state.year_start), user).fetch_all(&state.db).await, None).await {
-            Ok((_, hms)) => HttpResponse::Ok().json(hms),
-            Err(e) => return e,
-        }
+    let person = match parse_person(user) {
+        Ok(person) => person,
+        Err(e) => return e.into(),
+    };
+    match house::get_absences(&state.db, &person, &state.year_start).await {
+        Ok(hms) => HttpResponse::Ok().json(hms),
+        Err(e) => e.into(),
     }
 }
 
@@ -115,24 +88,13 @@ pub async fn get_hm_attendance_by_user_evals(
 ) -> impl Responder {
     let (user,) = path.into_inner();
     log!(Level::Info, "GET /attendance/house/evals/{user}");
-
-    if user.chars().next().unwrap().is_numeric() {
-        let user: i32 = match user.parse() {
-            Ok(user) => user,
-            Err(_) => {
-                log!(Level::Warn, "Invalid id");
-                return HttpResponse::BadRequest().body("Invalid id");
-            }
-        };
-        match log_query_as(query_as!(EvalsHmAtt, "select attendance_status as \"attendance_status:_\", excuse, date from (select * from freshman_hm_attendance where fid = $2) as mha left join house_meetings on mha.meeting_id = house_meetings.id where date > $1 and attendance_status != 'Attended'", NaiveDate::from(state.year_start), user).fetch_all(&state.db).await, None).await {
-            Ok((_, hms)) => HttpResponse::Ok().json(hms),
-            Err(e) => return e,
-        }
-    } else {
-        match log_query_as(query_as!(EvalsHmAtt, "select attendance_status as \"attendance_status:_\", excuse, date from (select * from member_hm_attendance where uid = $2) as mha left join house_meetings on mha.meeting_id = house_meetings.id where date > $1 and attendance_status != 'Attended'", NaiveDate::from(state.year_start), user).fetch_all(&state.db).await, None).await {
-            Ok((_, hms)) => HttpResponse::Ok().json(hms),
-            Err(e) => return e,
-        }
+    let person = match parse_person(user) {
+        Ok(person) => person,
+        Err(e) => return e.into(),
+    };
+    match house::get_missed_house_meetings(&state.db, &person, &state.year_start).await {
+        Ok(hms) => HttpResponse::Ok().json(hms),
+        Err(e) => e.into(),
     }
 }
 
@@ -140,45 +102,251 @@ pub async fn get_hm_attendance_by_user_evals(
 #[put("/house/{user}")]
 pub async fn modify_hm_attendance(
     path: Path<(String,)>,
-    state: Data<AppState>,
+    mut uow: UnitOfWork,
     body: Json<EvalsHmAtt>,
-) -> impl Responder {
+) -> Result<HttpResponse, AppError> {
     let (user,) = path.into_inner();
     log!(Level::Info, "PUT /attendance/house/{user}");
-    let body = body.into_inner();
-    let new_status = body.attendance_status;
-    let excuse = body.excuse;
-    let date = body.date;
-
-    let mut transaction = match open_transaction(&state.db).await {
-        Ok(t) => t,
-        Err(res) => return res,
-    };
+    let person = parse_person(user)?;
 
-    if user.chars().next().unwrap().is_numeric() {
-        let user: i32 = match user.parse() {
-            Ok(user) => user,
-            Err(_) => {
-                log!(Level::Warn, "Invalid id");
-                return HttpResponse::BadRequest().body("Invalid id");
-            }
-        };
-        match log_query(query!("UPDATE freshman_hm_attendance SET attendance_status = $1, excuse = $2 WHERE fid = $3 AND id IN (SELECT id FROM house_meetings WHERE date > $4)", new_status as AttendanceStatus, excuse, user, date).execute(&state.db).await.map(|_| ()), Some(transaction)).await {
-            Ok(tx) => transaction = tx.unwrap(),
-            Err(res) => return res,
-        }
-    } else {
-        match log_query(query!("UPDATE member_hm_attendance SET attendance_status = $1, excuse = $2 WHERE uid = $3 AND id IN (SELECT id FROM house_meetings WHERE date > $4)", new_status as AttendanceStatus, excuse, user, date).execute(&state.db).await.map(|_| ()), Some(transaction)).await {
-            Ok(tx) => transaction = tx.unwrap(),
-            Err(res) => return res,
-        }
+    house::set_attendance_on(&mut uow, &person, &body).await?;
+
+    uow.commit().await?;
+    Ok(HttpResponse::Ok().body(""))
+}
+
+fn parse_id(id: &str) -> Result<i32, AppError> {
+    id.parse::<i32>().map_err(|_| {
+        log!(Level::Warn, "Invalid id");
+        AppError::BadRequest(String::from("Invalid id"))
+    })
+}
+
+#[utoipa::path(
+    context_path="/api/attendance",
+    responses(
+        (status = 200, description = "List all house meetings in the current operating session", body = [HouseMeeting]),
+        (status = 500, description = "Error created by Query"),
+        )
+    )]
+#[get("/house", wrap = "CSHAuth::enabled()")]
+pub async fn get_house_meetings(state: Data<AppState>) -> impl Responder {
+    log!(Level::Info, "GET /attendance/house");
+    match house::get_house_meetings(&state.db, &state.year_start).await {
+        Ok(hms) => HttpResponse::Ok().json(hms),
+        Err(e) => e.into(),
     }
+}
 
-    match transaction.commit().await {
-        Ok(_) => HttpResponse::Ok().body(""),
-        Err(e) => {
-            log!(Level::Error, "Transaction failed to commit");
-            HttpResponse::InternalServerError().body(e.to_string())
-        }
+#[utoipa::path(
+    context_path="/api/attendance",
+    responses(
+        (status = 200, description = "Get a house meeting with the status of everyone on its roster", body = HouseMeetingRoster),
+        (status = 400, description = "Invalid id"),
+        (status = 404, description = "House meeting not found"),
+        (status = 500, description = "Error created by Query"),
+        )
+    )]
+#[get("/house/meeting/{id}", wrap = "CSHAuth::eboard_only()")]
+pub async fn get_house_meeting(path: Path<(String,)>, state: Data<AppState>) -> impl Responder {
+    let (id,) = path.into_inner();
+    log!(Level::Info, "GET /attendance/house/meeting/{id}");
+    let id = match parse_id(&id) {
+        Ok(id) => id,
+        Err(e) => return e.into(),
+    };
+    match house::get_house_meeting(&state.db, id).await {
+        Ok(roster) => HttpResponse::Ok().json(roster),
+        Err(e) => e.into(),
     }
 }
+
+#[utoipa::path(
+    context_path="/api/attendance",
+    request_body = HouseMeetingActive,
+    responses(
+        (status = 200, description = "Cancel or restore a house meeting"),
+        (status = 400, description = "Invalid id"),
+        (status = 404, description = "House meeting not found"),
+        (status = 500, description = "Error created by Query"),
+        )
+    )]
+#[put("/house/meeting/{id}", wrap = "CSHAuth::eboard_only()")]
+pub async fn set_house_meeting_active(
+    path: Path<(String,)>,
+    mut uow: UnitOfWork,
+    body: Json<HouseMeetingActive>,
+) -> Result<HttpResponse, AppError> {
+    let (id,) = path.into_inner();
+    log!(Level::Info, "PUT /attendance/house/meeting/{id}");
+    let id = parse_id(&id)?;
+
+    house::set_house_meeting_active(&mut uow, id, body.active).await?;
+
+    uow.commit().await?;
+    Ok(HttpResponse::Ok().body(""))
+}
+
+#[utoipa::path(
+    context_path="/api/attendance",
+    responses(
+        (status = 200, description = "Delete a house meeting and all of its attendance"),
+        (status = 400, description = "Invalid id"),
+        (status = 404, description = "House meeting not found"),
+        (status = 500, description = "Error created by Query"),
+        )
+    )]
+#[delete("/house/meeting/{id}", wrap = "CSHAuth::eboard_only()")]
+pub async fn delete_house_meeting(
+    path: Path<(String,)>,
+    mut uow: UnitOfWork,
+) -> Result<HttpResponse, AppError> {
+    let (id,) = path.into_inner();
+    log!(Level::Info, "DELETE /attendance/house/meeting/{id}");
+    let id = parse_id(&id)?;
+
+    house::delete_house_meeting(&mut uow, id).await?;
+
+    log!(Level::Trace, "Finished deleting house meeting");
+    uow.commit().await?;
+    Ok(HttpResponse::Ok().body(""))
+}
+
+#[utoipa::path(
+    context_path="/api/attendance",
+    request_body = ExcuseSubmission,
+    responses(
+        (status = 201, description = "Request to be excused from a house meeting"),
+        (status = 400, description = "Invalid id"),
+        (status = 401, description = "No valid token, or the freshman account isn't the user's"),
+        (status = 404, description = "House meeting or freshman account not found"),
+        (status = 409, description = "An excuse request for this house meeting is already pending"),
+        (status = 500, description = "Error created by Query or LDAP"),
+        )
+    )]
+#[post("/house/meeting/{id}/excuse", wrap = "CSHAuth::enabled()")]
+pub async fn submit_hm_excuse(
+    path: Path<(String,)>,
+    state: Data<AppState>,
+    mut uow: UnitOfWork,
+    user: User,
+    body: Json<ExcuseSubmission>,
+) -> Result<HttpResponse, AppError> {
+    let (id,) = path.into_inner();
+    log!(Level::Info, "POST /attendance/house/meeting/{id}/excuse");
+    let id = parse_id(&id)?;
+    let person = token_person(&state, &mut uow, &user, body.fid).await?;
+
+    house::submit_excuse(&mut uow, id, &person, &body.excuse).await?;
+
+    uow.commit().await?;
+    Ok(HttpResponse::Created().finish())
+}
+
+#[utoipa::path(
+    context_path="/api/attendance",
+    responses(
+        (status = 200, description = "Get all house meeting excuse requests awaiting a decision", body = [ExcuseRequest]),
+        (status = 500, description = "Error created by Query"),
+        )
+    )]
+#[get("/house/excuse/pending", wrap = "CSHAuth::eboard_only()")]
+pub async fn get_pending_hm_excuses(state: Data<AppState>) -> impl Responder {
+    log!(Level::Info, "GET /attendance/house/excuse/pending");
+    match house::get_pending_excuses(&state.db).await {
+        Ok(excuses) => HttpResponse::Ok().json(excuses),
+        Err(e) => e.into(),
+    }
+}
+
+#[utoipa::path(
+    context_path="/api/attendance",
+    request_body = ExcuseDecision,
+    responses(
+        (status = 200, description = "Accept or deny a house meeting excuse request"),
+        (status = 400, description = "Invalid id"),
+        (status = 404, description = "No pending excuse request with this id"),
+        (status = 500, description = "Error created by Query"),
+        )
+    )]
+#[put("/house/excuse/{id}", wrap = "CSHAuth::eboard_only()")]
+pub async fn decide_hm_excuse(
+    path: Path<(String,)>,
+    mut uow: UnitOfWork,
+    body: Json<ExcuseDecision>,
+) -> Result<HttpResponse, AppError> {
+    let (id,) = path.into_inner();
+    log!(Level::Info, "PUT /attendance/house/excuse/{id}");
+    let id = parse_id(&id)?;
+    let decision = if body.accepted {
+        ExcuseStatus::Accepted
+    } else {
+        ExcuseStatus::Denied
+    };
+
+    house::decide_excuse(&mut uow, id, decision).await?;
+
+    uow.commit().await?;
+    Ok(HttpResponse::Ok().body(""))
+}
+
+#[utoipa::path(
+    context_path="/api/attendance",
+    request_body = HouseMeetingAttendanceEdit,
+    responses(
+        (status = 200, description = "Change one attendee's status at one house meeting"),
+        (status = 400, description = "Invalid id"),
+        (status = 404, description = "Attendee is not on the house meeting's roster"),
+        (status = 500, description = "Error created by Query"),
+        )
+    )]
+#[put("/house/meeting/{id}/attendee/{user}", wrap = "CSHAuth::eboard_only()")]
+pub async fn edit_house_meeting_attendee(
+    path: Path<(String, String)>,
+    mut uow: UnitOfWork,
+    body: Json<HouseMeetingAttendanceEdit>,
+) -> Result<HttpResponse, AppError> {
+    let (id, user) = path.into_inner();
+    log!(
+        Level::Info,
+        "PUT /attendance/house/meeting/{id}/attendee/{user}"
+    );
+    let id = parse_id(&id)?;
+    let person = parse_person(user)?;
+
+    house::edit_attendee(&mut uow, id, &person, &body).await?;
+
+    uow.commit().await?;
+    Ok(HttpResponse::Ok().body(""))
+}
+
+#[utoipa::path(
+    context_path="/api/attendance",
+    request_body = HouseMeetingAttendanceEdit,
+    responses(
+        (status = 201, description = "Add a late attendee to a house meeting's roster"),
+        (status = 400, description = "Invalid id"),
+        (status = 404, description = "House meeting not found"),
+        (status = 409, description = "Attendee is already on the house meeting's roster"),
+        (status = 500, description = "Error created by Query"),
+        )
+    )]
+#[post("/house/meeting/{id}/attendee/{user}", wrap = "CSHAuth::eboard_only()")]
+pub async fn add_house_meeting_attendee(
+    path: Path<(String, String)>,
+    mut uow: UnitOfWork,
+    body: Json<HouseMeetingAttendanceEdit>,
+) -> Result<HttpResponse, AppError> {
+    let (id, user) = path.into_inner();
+    log!(
+        Level::Info,
+        "POST /attendance/house/meeting/{id}/attendee/{user}"
+    );
+    let id = parse_id(&id)?;
+    let person = parse_person(user)?;
+
+    house::add_attendee(&mut uow, id, &person, &body).await?;
+
+    uow.commit().await?;
+    Ok(HttpResponse::Created().finish())
+}
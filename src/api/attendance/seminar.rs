@@ -1,14 +1,16 @@
-use crate::api::{log_query, log_query_as, open_transaction};
+use crate::api::parse_person;
 use crate::app::AppState;
-use crate::auth::CSHAuth;
-use crate::schema::api::{Seminar, ID};
+use crate::auth::{CSHAuth, User};
+use crate::error::AppError;
+use crate::repo::attendance::seminar;
+use crate::schema::api::Seminar;
+use crate::transaction::UnitOfWork;
 use actix_web::{
     delete, get, post, put,
     web::{Data, Json, Path},
     HttpResponse, Responder,
 };
 use log::{log, Level};
-use sqlx::{query, query_as};
 
 #[utoipa::path(
     context_path="/api/attendance",
@@ -19,109 +21,34 @@ use sqlx::{query, query_as};
     )]
 #[post("/seminar", wrap = "CSHAuth::enabled()")]
 pub async fn submit_seminar_attendance(
-    state: Data<AppState>,
+    mut uow: UnitOfWork,
+    user: Option<User>,
     body: Json<Seminar>,
-) -> impl Responder {
-    if body.frosh.is_none() {
-        return HttpResponse::BadRequest().body("Missing attribute 'frosh'");
-    }
-
-    if body.members.is_none() {
-        return HttpResponse::BadRequest().body("Missing attribute 'members'");
-    }
-
-    let mut transaction = match open_transaction(&state.db).await {
-        Ok(t) => t,
-        Err(res) => return res,
+) -> Result<HttpResponse, AppError> {
+    let (frosh, members) = match (&body.frosh, &body.members) {
+        (None, _) => {
+            return Err(AppError::BadRequest(String::from(
+                "Missing attribute 'frosh'",
+            )))
+        }
+        (_, None) => {
+            return Err(AppError::BadRequest(String::from(
+                "Missing attribute 'members'",
+            )))
+        }
+        (Some(frosh), Some(members)) => (frosh, members),
     };
-    log!(Level::Trace, "Acquired transaction");
 
-    let id: i32;
-
-    // Add new technical seminar
-    match log_query_as(
-        query_as!(
-            ID,
-            "INSERT INTO technical_seminars (name, timestamp, active, approved)
-                VALUES ($1, $2, $3, $4) RETURNING id",
-            body.name,
-            body.timestamp,
-            true,
-            false
-        )
-        .fetch_all(&state.db)
-        .await,
-        Some(transaction),
-    )
-    .await
-    {
-        Ok((tx, i)) => {
-            transaction = tx.unwrap();
-            id = i[0].id;
-        }
-        Err(res) => return res,
-    }
+    let host = user.map(|u| u.preferred_username);
+    let id =
+        seminar::create_seminar(&mut uow, &body.name, &body.timestamp, host.as_deref()).await?;
     log!(Level::Debug, "Inserted meeting into db. ID={}", id);
 
-    let frosh = body.frosh.clone().unwrap();
-    let members = body.members.clone().unwrap();
-
-    let frosh_id = vec![id; frosh.len()];
-    let member_id = vec![id; members.len()];
-
-    // Add frosh, seminar relation
-    match log_query(
-        query!(
-            "INSERT INTO freshman_seminar_attendance (fid, seminar_id)
-                SELECT fid, seminar_id
-                FROM UNNEST($1::int4[], $2::int4[]) AS a(fid, seminar_id)",
-            frosh.as_slice(),
-            frosh_id.as_slice()
-        )
-        .fetch_all(&state.db)
-        .await
-        .map(|_| ()),
-        Some(transaction),
-    )
-    .await
-    {
-        Ok(tx) => {
-            transaction = tx.unwrap();
-        }
-        Err(res) => return res,
-    }
-
-    // Add member, seminar relation
-    match log_query(
-        query!(
-            "INSERT INTO member_seminar_attendance (uid, seminar_id)
-                SELECT uid, seminar_id
-                FROM UNNEST($1::TEXT[], $2::int4[]) AS a(uid, seminar_id)",
-            members.as_slice(),
-            member_id.as_slice()
-        )
-        .fetch_all(&state.db)
-        .await
-        .map(|_| ()),
-        Some(transaction),
-    )
-    .await
-    {
-        Ok(tx) => {
-            transaction = tx.unwrap();
-        }
-        Err(res) => return res,
-    }
+    seminar::add_seminar_attendance(&mut uow, id, frosh, members).await?;
 
     log!(Level::Trace, "Finished adding new seminar attendance");
-    // Commit transaction
-    match transaction.commit().await {
-        Ok(_) => HttpResponse::Created().finish(),
-        Err(e) => {
-            log!(Level::Error, "Transaction failed to commit");
-            HttpResponse::InternalServerError().body(e.to_string())
-        }
-    }
+    uow.commit().await?;
+    Ok(HttpResponse::Created().finish())
 }
 
 #[utoipa::path(
@@ -135,67 +62,13 @@ pub async fn submit_seminar_attendance(
 #[get("/seminar/{user}", wrap = "CSHAuth::enabled()")]
 pub async fn get_seminars_by_user(path: Path<(String,)>, state: Data<AppState>) -> impl Responder {
     let (user,) = path.into_inner();
-    if user.chars().next().unwrap().is_numeric() {
-        let user: i32 = match user.parse() {
-            Ok(user) => user,
-            Err(_e) => {
-                log!(Level::Warn, "Invalid id");
-                return HttpResponse::BadRequest().body("Invalid id");
-            }
-        };
-        match log_query_as(
-            query_as!(
-                Seminar,
-                "SELECT ts.name,
-                        ts.\"timestamp\",
-                        ARRAY[]::varchar[] AS members,
-                        ARRAY[]::integer[] AS frosh,
-                        ts.approved
-                    FROM technical_seminars ts
-                    LEFT JOIN freshman_seminar_attendance fsa ON
-                        fsa.seminar_id = ts.id
-                    WHERE ts.approved
-                    AND timestamp > $1::timestamp
-                    AND fsa.fid = $2::int4",
-                &state.year_start,
-                user
-            )
-            .fetch_all(&state.db)
-            .await,
-            None,
-        )
-        .await
-        {
-            Ok((_, seminars)) => HttpResponse::Ok().json(seminars),
-            Err(e) => return e,
-        }
-    } else {
-        match log_query_as(
-            query_as!(
-                Seminar,
-                "SELECT ts.name,
-                        ts.\"timestamp\",
-                        ARRAY[]::varchar[] AS members,
-                        ARRAY[]::integer[] AS frosh,
-                        ts.approved
-                    FROM technical_seminars ts
-                    LEFT JOIN member_seminar_attendance msa ON
-                        msa.seminar_id = ts.id
-                    WHERE ts.approved
-                    AND timestamp > $1::timestamp
-                    AND msa.uid = $2",
-                &state.year_start,
-                user
-            )
-            .fetch_all(&state.db)
-            .await,
-            None,
-        )
-        .await
-        {
-            Ok((_, seminars)) => HttpResponse::Ok().json(seminars),
-            Err(e) => return e,
-        }
+    let person = match parse_person(user) {
+        Ok(person) => person,
+        Err(e) => return e.into(),
+    };
+    match seminar::get_seminars_attended(&state.db, &person, &state.year_start).await {
+        Ok(seminars) => HttpResponse::Ok().json(seminars),
+        Err(e) => e.into(),
     }
 }
 
@@ -209,30 +82,9 @@ pub async fn get_seminars_by_user(path: Path<(String,)>, state: Data<AppState>)
 #[get("/seminar", wrap = "CSHAuth::enabled()")]
 pub async fn get_seminars(state: Data<AppState>) -> impl Responder {
     log!(Level::Debug, "{}", &state.year_start);
-    match query_as!(
-        Seminar,
-        "SELECT member_seminars.name,
-                member_seminars.timestamp,
-                member_seminars.members,
-                array_agg(fsa.fid) AS frosh,
-                member_seminars.approved
-            FROM(SELECT ts.id, ts.name, ts.timestamp, array_agg(msa.uid) AS members, ts.approved
-                 FROM technical_seminars ts
-                 INNER JOIN member_seminar_attendance msa ON
-                     msa.seminar_id = ts.id
-                 WHERE timestamp > $1::timestamp
-                 GROUP BY ts.id, ts.name, ts.timestamp, ts.approved) AS member_seminars
-            INNER JOIN freshman_seminar_attendance fsa ON
-                fsa.seminar_id = member_seminars.id
-            GROUP BY member_seminars.id, member_seminars.name, member_seminars.timestamp, \
-         member_seminars.members, member_seminars.approved",
-        &state.year_start
-    )
-    .fetch_all(&state.db)
-    .await
-    {
+    match seminar::get_seminars(&state.db, &state.year_start).await {
         Ok(seminars) => HttpResponse::Ok().json(seminars),
-        Err(e) => HttpResponse::InternalServerError().body(e.to_string()),
+        Err(e) => e.into(),
     }
 }
 
@@ -244,77 +96,23 @@ pub async fn get_seminars(state: Data<AppState>) -> impl Responder {
         )
     )]
 #[delete("/seminar/{id}", wrap = "CSHAuth::eboard_only()")]
-pub async fn delete_seminar(path: Path<(String,)>, state: Data<AppState>) -> impl Responder {
+pub async fn delete_seminar(
+    path: Path<(String,)>,
+    mut uow: UnitOfWork,
+) -> Result<HttpResponse, AppError> {
     let (id,) = path.into_inner();
     let id = match id.parse::<i32>() {
         Ok(id) => id,
         Err(_e) => {
             log!(Level::Warn, "Invalid id");
-            return HttpResponse::BadRequest().body("Invalid id");
+            return Err(AppError::BadRequest(String::from("Invalid id")));
         }
     };
-    let mut transaction = match open_transaction(&state.db).await {
-        Ok(t) => t,
-        Err(res) => return res,
-    };
-    log!(Level::Trace, "Acquired transaction");
-    match log_query(
-        query!(
-            "DELETE FROM freshman_seminar_attendance WHERE seminar_id = $1",
-            id
-        )
-        .execute(&state.db)
-        .await
-        .map(|_| ()),
-        Some(transaction),
-    )
-    .await
-    {
-        Ok(tx) => {
-            transaction = tx.unwrap();
-        }
-        Err(res) => return res,
-    }
-    match log_query(
-        query!(
-            "DELETE FROM member_seminar_attendance WHERE seminar_id = $1",
-            id
-        )
-        .execute(&state.db)
-        .await
-        .map(|_| ()),
-        Some(transaction),
-    )
-    .await
-    {
-        Ok(tx) => {
-            transaction = tx.unwrap();
-        }
-        Err(res) => return res,
-    }
-    match log_query(
-        query!("DELETE FROM technical_seminars WHERE id = $1", id)
-            .execute(&state.db)
-            .await
-            .map(|_| ()),
-        Some(transaction),
-    )
-    .await
-    {
-        Ok(tx) => {
-            transaction = tx.unwrap();
-        }
-        Err(res) => return res,
-    }
+    seminar::delete_seminar(&mut uow, id).await?;
 
     log!(Level::Trace, "Finished deleting seminar");
-    match transaction.commit().await {
-        Ok(_) => HttpResponse::Ok().body(""),
-        Err(e) => {
-            log!(Level::Error, "Transaction failed to commit");
-            HttpResponse::InternalServerError().body(e.to_string())
-        }
-    }
+    uow.commit().await?;
+    Ok(HttpResponse::Ok().body(""))
 }
 
 #[utoipa::path(
@@ -327,124 +125,40 @@ pub async fn delete_seminar(path: Path<(String,)>, state: Data<AppState>) -> imp
 #[put("/seminar/{id}", wrap = "CSHAuth::eboard_only()")]
 pub async fn edit_seminar_attendance(
     path: Path<(String,)>,
-    state: Data<AppState>,
+    mut uow: UnitOfWork,
     body: Json<Seminar>,
-) -> impl Responder {
+) -> Result<HttpResponse, AppError> {
     let (id,) = path.into_inner();
     log!(Level::Info, "PUT /attendance/seminar/{id}");
 
-    if body.frosh.is_none() {
-        return HttpResponse::BadRequest().body("Missing attribute 'frosh'");
-    }
-
-    if body.members.is_none() {
-        return HttpResponse::BadRequest().body("Missing attribute 'members'");
-    }
+    let (frosh, members) = match (&body.frosh, &body.members) {
+        (None, _) => {
+            return Err(AppError::BadRequest(String::from(
+                "Missing attribute 'frosh'",
+            )))
+        }
+        (_, None) => {
+            return Err(AppError::BadRequest(String::from(
+                "Missing attribute 'members'",
+            )))
+        }
+        (Some(frosh), Some(members)) => (frosh, members),
+    };
 
     let id = match id.parse::<i32>() {
         Ok(id) => id,
         Err(_e) => {
             log!(Level::Warn, "Invalid id");
-            return HttpResponse::BadRequest().body("Invalid id");
+            return Err(AppError::BadRequest(String::from("Invalid id")));
         }
     };
-    let mut transaction = match open_transaction(&state.db).await {
-        Ok(t) => t,
-        Err(res) => return res,
-    };
-    log!(Level::Trace, "Acquired transaction");
-
-    match log_query(
-        query!(
-            "DELETE FROM freshman_seminar_attendance WHERE seminar_id = $1",
-            id
-        )
-        .execute(&state.db)
-        .await
-        .map(|_| ()),
-        Some(transaction),
-    )
-    .await
-    {
-        Ok(tx) => transaction = tx.unwrap(),
-        Err(res) => return res,
-    }
-
-    match log_query(
-        query!(
-            "DELETE FROM member_seminar_attendance WHERE seminar_id = $1",
-            id
-        )
-        .execute(&state.db)
-        .await
-        .map(|_| ()),
-        Some(transaction),
-    )
-    .await
-    {
-        Ok(tx) => transaction = tx.unwrap(),
-        Err(res) => return res,
-    }
 
+    seminar::delete_seminar_attendance(&mut uow, id).await?;
     log!(Level::Trace, "finished deleting existing attendance");
 
-    let frosh = body.frosh.clone().unwrap();
-    let members = body.members.clone().unwrap();
-
-    let frosh_id = vec![id; frosh.len()];
-    let member_id = vec![id; members.len()];
-
-    // Add frosh, seminar relation
-    match log_query(
-        query!(
-            "INSERT INTO freshman_seminar_attendance (fid, seminar_id)
-                SELECT fid, seminar_id
-                FROM UNNEST($1::int4[], $2::int4[]) AS a(fid, seminar_id)",
-            frosh.as_slice(),
-            frosh_id.as_slice()
-        )
-        .fetch_all(&state.db)
-        .await
-        .map(|_| ()),
-        Some(transaction),
-    )
-    .await
-    {
-        Ok(tx) => {
-            transaction = tx.unwrap();
-        }
-        Err(res) => return res,
-    }
-
-    // Add member, seminar relation
-    match log_query(
-        query!(
-            "INSERT INTO member_seminar_attendance (uid, seminar_id)
-                SELECT uid, seminar_id
-                FROM UNNEST($1::TEXT[], $2::int4[]) AS a(uid, seminar_id)",
-            members.as_slice(),
-            member_id.as_slice()
-        )
-        .fetch_all(&state.db)
-        .await
-        .map(|_| ()),
-        Some(transaction),
-    )
-    .await
-    {
-        Ok(tx) => {
-            transaction = tx.unwrap();
-        }
-        Err(res) => return res,
-    }
+    seminar::add_seminar_attendance(&mut uow, id, frosh, members).await?;
 
     log!(Level::Trace, "Finished adding new seminar attendance");
-    // Commit transaction
-    match transaction.commit().await {
-        Ok(_) => HttpResponse::Ok().body(""),
-        Err(e) => {
-            log!(Level::Error, "Transaction failed to commit");
-            HttpResponse::InternalServerError().body(e.to_string())
-        }
-    }
+    uow.commit().await?;
+    Ok(HttpResponse::Ok().body(""))
 }
@@ -1,10 +1,9 @@
-use crate::{api::log_query_as, app::AppState, schema::api::IntroFormSubmission};
+use crate::{app::AppState, repo::forms};
 use actix_web::{
     get,
     web::{Data, Path},
     HttpResponse, Responder,
 };
-use sqlx::query_as;
 
 #[utoipa::path(
     context_path="/forms",
@@ -19,21 +18,9 @@ pub async fn get_intro_form_for_user(
     path: Path<(String,)>,
 ) -> impl Responder {
     let (uid,) = path.into_inner();
-    match log_query_as(
-        query_as!(
-            IntroFormSubmission,
-            "select uid, social_events, other_notes as comments from freshman_eval_data where uid \
-             = $1",
-            uid
-        )
-        .fetch_all(&state.db)
-        .await,
-        None,
-    )
-    .await
-    {
-        Ok((_, ret)) => HttpResponse::Ok().json(ret),
-        Err(e) => return e,
+    match forms::get_intro_forms(&state.db, &uid).await {
+        Ok(ret) => HttpResponse::Ok().json(ret),
+        Err(e) => e.into(),
     }
 }
 
@@ -0,0 +1,249 @@
+use crate::app::AppState;
+use crate::auth::CSHAuth;
+use crate::error::{ldap_error, AppError};
+use crate::housing::{compute, rank, HOUSING_WEIGHTS};
+use crate::ldap::search::SearchAttrs;
+use crate::repo::housing;
+use crate::schema::api::{HousingPoints, HousingPointsRanking, HousingQueueEntry};
+use crate::transaction::UnitOfWork;
+use actix_web::{
+    delete, get, post, put,
+    web::{Data, Path},
+    HttpResponse, Responder,
+};
+use chrono::Utc;
+use log::{log, Level};
+use std::collections::HashMap;
+
+/// Make sure `uid` is a real member before recording anything about them
+async fn check_member(state: &Data<AppState>, uid: &str) -> Result<(), AppError> {
+    match state.ldap.get_user(uid, &SearchAttrs::minimal()).await {
+        Ok(users) if users.is_empty() => {
+            log!(Level::Warn, "No such user {uid}");
+            Err(AppError::NotFound(String::from("User not found")))
+        }
+        Ok(_) => Ok(()),
+        Err(e) => Err(ldap_error(e)),
+    }
+}
+
+/// Compute housing points for each of `uids`, filling in names from LDAP
+async fn housing_points(
+    state: &Data<AppState>,
+    uids: &[String],
+) -> Result<Vec<HousingPoints>, AppError> {
+    let inputs = housing::get_point_inputs(&state.db, uids, &state.year_start).await?;
+    let users = state
+        .ldap
+        .get_users(uids, &SearchAttrs::minimal())
+        .await
+        .map_err(ldap_error)?;
+    let names: HashMap<&str, &str> = users
+        .iter()
+        .map(|u| (u.uid.as_str(), u.cn.as_str()))
+        .collect();
+
+    let today = Utc::now().date_naive();
+    Ok(inputs
+        .iter()
+        .map(|inputs| {
+            let mut member = compute(inputs, &HOUSING_WEIGHTS, today);
+            member.name = names.get(inputs.uid.as_str()).map(ToString::to_string);
+            member
+        })
+        .collect())
+}
+
+#[utoipa::path(
+    context_path="/api/housing",
+    responses(
+        (status = 201, description = "Grant a member on floor status as of now", body = OnFloorDate),
+        (status = 404, description = "User not found"),
+        (status = 409, description = "Member is already on floor"),
+        (status = 500, description = "Error created by Query"),
+        )
+    )]
+#[put("/onfloor/{uid}", wrap = "CSHAuth::eboard_only()")]
+pub async fn grant_onfloor(
+    path: Path<(String,)>,
+    state: Data<AppState>,
+    mut uow: UnitOfWork,
+) -> Result<HttpResponse, AppError> {
+    let (uid,) = path.into_inner();
+    log!(Level::Info, "PUT /housing/onfloor/{uid}");
+    check_member(&state, &uid).await?;
+
+    let granted = housing::grant_onfloor(&mut uow, &uid, &Utc::now().naive_utc()).await?;
+
+    uow.commit().await?;
+    Ok(HttpResponse::Created().json(granted))
+}
+
+#[utoipa::path(
+    context_path="/api/housing",
+    responses(
+        (status = 200, description = "Revoke a member's on floor status"),
+        (status = 404, description = "Member is not on floor"),
+        (status = 500, description = "Error created by Query"),
+        )
+    )]
+#[delete("/onfloor/{uid}", wrap = "CSHAuth::eboard_only()")]
+pub async fn revoke_onfloor(
+    path: Path<(String,)>,
+    mut uow: UnitOfWork,
+) -> Result<HttpResponse, AppError> {
+    let (uid,) = path.into_inner();
+    log!(Level::Info, "DELETE /housing/onfloor/{uid}");
+
+    housing::revoke_onfloor(&mut uow, &uid).await?;
+
+    uow.commit().await?;
+    Ok(HttpResponse::Ok().finish())
+}
+
+#[utoipa::path(
+    context_path="/api/housing",
+    responses(
+        (status = 201, description = "Add a member to the housing queue"),
+        (status = 404, description = "User not found"),
+        (status = 409, description = "Member is already in the housing queue"),
+        (status = 500, description = "Error created by Query"),
+        )
+    )]
+#[post("/queue/{uid}", wrap = "CSHAuth::eboard_only()")]
+pub async fn add_to_housing_queue(
+    path: Path<(String,)>,
+    state: Data<AppState>,
+    mut uow: UnitOfWork,
+) -> Result<HttpResponse, AppError> {
+    let (uid,) = path.into_inner();
+    log!(Level::Info, "POST /housing/queue/{uid}");
+    check_member(&state, &uid).await?;
+
+    housing::add_to_queue(&mut uow, &uid).await?;
+
+    uow.commit().await?;
+    Ok(HttpResponse::Created().finish())
+}
+
+#[utoipa::path(
+    context_path="/api/housing",
+    responses(
+        (status = 200, description = "Remove a member from the housing queue"),
+        (status = 404, description = "Member is not in the housing queue"),
+        (status = 500, description = "Error created by Query"),
+        )
+    )]
+#[delete("/queue/{uid}", wrap = "CSHAuth::eboard_only()")]
+pub async fn remove_from_housing_queue(
+    path: Path<(String,)>,
+    mut uow: UnitOfWork,
+) -> Result<HttpResponse, AppError> {
+    let (uid,) = path.into_inner();
+    log!(Level::Info, "DELETE /housing/queue/{uid}");
+
+    housing::remove_from_queue(&mut uow, &uid).await?;
+
+    uow.commit().await?;
+    Ok(HttpResponse::Ok().finish())
+}
+
+#[utoipa::path(
+    context_path="/api/housing",
+    responses(
+        (status = 200, description = "The housing queue, longest on floor first", body = [HousingQueueEntry]),
+        (status = 500, description = "Error created by Query or LDAP"),
+        (status = 503, description = "LDAP unavailable"),
+        )
+    )]
+#[get("/queue", wrap = "CSHAuth::enabled()")]
+pub async fn get_housing_queue(state: Data<AppState>) -> impl Responder {
+    log!(Level::Info, "GET /housing/queue");
+    let queue = match housing::get_queue(&state.db).await {
+        Ok(queue) => queue,
+        Err(e) => return e.into(),
+    };
+
+    let uids: Vec<String> = queue.iter().map(|entry| entry.uid.clone()).collect();
+    let points = match housing_points(&state, &uids).await {
+        Ok(points) => points,
+        Err(e) => return e.into(),
+    };
+
+    let entries: Vec<HousingQueueEntry> = queue
+        .into_iter()
+        .map(|entry| {
+            let member = points.iter().find(|p| p.uid == entry.uid);
+            HousingQueueEntry {
+                name: member.and_then(|p| p.name.clone()),
+                housing_points: member.map(|p| p.total).unwrap_or_default(),
+                uid: entry.uid,
+                onfloor_granted: entry.onfloor_granted,
+            }
+        })
+        .collect();
+    HttpResponse::Ok().json(entries)
+}
+
+#[utoipa::path(
+    context_path="/api/housing",
+    responses(
+        (status = 200, description = "Members of the housing queue ranked by housing points, with the weights used", body = HousingPointsRanking),
+        (status = 500, description = "Error created by Query or LDAP"),
+        (status = 503, description = "LDAP unavailable"),
+        )
+    )]
+#[get("/points", wrap = "CSHAuth::enabled()")]
+pub async fn get_housing_points(state: Data<AppState>) -> impl Responder {
+    log!(Level::Info, "GET /housing/points");
+    let uids = match housing::get_queue_uids(&state.db).await {
+        Ok(uids) => uids,
+        Err(e) => return e.into(),
+    };
+    match housing_points(&state, &uids).await {
+        Ok(members) => HttpResponse::Ok().json(HousingPointsRanking {
+            weights: HOUSING_WEIGHTS.clone(),
+            members: rank(members),
+        }),
+        Err(e) => e.into(),
+    }
+}
+
+#[utoipa::path(
+    context_path="/api/housing",
+    responses(
+        (status = 200, description = "Breakdown of how a member's housing points were computed", body = HousingPoints),
+        (status = 404, description = "User not found"),
+        (status = 500, description = "Error created by Query or LDAP"),
+        (status = 503, description = "LDAP unavailable"),
+        )
+    )]
+#[get("/points/{uid}", wrap = "CSHAuth::enabled()")]
+pub async fn get_member_housing_points(
+    path: Path<(String,)>,
+    state: Data<AppState>,
+) -> impl Responder {
+    let (uid,) = path.into_inner();
+    log!(Level::Info, "GET /housing/points/{uid}");
+    if let Err(res) = check_member(&state, &uid).await {
+        return res.into();
+    }
+    let queue = match housing::get_queue_uids(&state.db).await {
+        Ok(uids) => uids,
+        Err(e) => return e.into(),
+    };
+    if !queue.contains(&uid) {
+        // Rank only means something within the housing queue
+        return match housing_points(&state, &[uid]).await {
+            Ok(mut members) => HttpResponse::Ok().json(members.remove(0)),
+            Err(e) => e.into(),
+        };
+    }
+    match housing_points(&state, &queue).await {
+        Ok(members) => match rank(members).into_iter().find(|p| p.uid == uid) {
+            Some(member) => HttpResponse::Ok().json(member),
+            None => AppError::NotFound(String::from("User not found")).into(),
+        },
+        Err(e) => e.into(),
+    }
+}
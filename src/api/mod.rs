@@ -1,8 +1,14 @@
-use actix_web::HttpResponse;
+use crate::app::AppState;
+use crate::auth::User;
+use crate::error::{ldap_error, AppError};
+use crate::identity::rit_username_key;
+use crate::ldap::search::SearchAttrs;
+use crate::repo::{freshmen, Person};
 use log::{log, Level};
-use sqlx::{Error, Pool, Postgres, Transaction};
+use sqlx::PgConnection;
 
 pub mod attendance {
+    pub mod checkin;
     pub mod directorship;
     pub mod house;
     pub mod seminar;
@@ -13,6 +19,7 @@ pub mod forms {
 }
 
 pub mod batch {
+    #[allow(clippy::module_inception)]
     pub mod batch;
 }
 
@@ -20,64 +27,70 @@ pub mod evals {
     pub mod routes;
 }
 
-pub mod users {
+pub mod housing {
     pub mod routes;
 }
 
-pub async fn open_transaction(db: &Pool<Postgres>) -> Result<Transaction<Postgres>, HttpResponse> {
-    match db.try_begin().await {
-        Ok(Some(t)) => Ok(t),
-        Ok(None) => {
-            log!(Level::Error, "Failed to open transaction");
-            Err(HttpResponse::InternalServerError().body("Internal DB Error: Ok(None) transaction"))
-        }
-        Err(e) => {
-            log!(Level::Error, "Failed to open transaction");
-            Err(HttpResponse::InternalServerError().body(format!("Internal DB Error: {}", e)))
-        }
-    }
+pub mod packet {
+    pub mod routes;
+}
+
+pub mod users {
+    pub mod convert;
+    pub mod freshmen;
+    pub mod import;
+    pub mod routes;
 }
 
-pub async fn log_query_as<T>(
-    query: Result<Vec<T>, Error>,
-    tx: Option<Transaction<'_, Postgres>>,
-) -> Result<(Option<Transaction<'_, Postgres>>, Vec<T>), HttpResponse> {
-    match query {
-        Ok(v) => Ok((tx, v)),
-        Err(e) => {
-            log!(Level::Warn, "DB Query failed: {}", e);
-            if let Some(tx) = tx {
-                match tx.rollback().await {
-                    Ok(_) => {}
-                    Err(tx_e) => {
-                        log!(Level::Error, "Transaction failed to rollback: {}", tx_e);
-                        return Err(HttpResponse::InternalServerError().body("Internal DB Error"));
-                    }
-                }
+/// A user given in a path, which is a freshman ID if it's numeric or a CSH
+/// username otherwise
+pub fn parse_person(user: String) -> Result<Person, AppError> {
+    if user.chars().next().is_some_and(|c| c.is_numeric()) {
+        match user.parse() {
+            Ok(fid) => Ok(Person::Freshman(fid)),
+            Err(_) => {
+                log!(Level::Warn, "Invalid id");
+                Err(AppError::BadRequest(String::from("Invalid id")))
             }
-            return Err(HttpResponse::InternalServerError().body("Internal DB Error"));
         }
+    } else {
+        Ok(Person::Member(user))
     }
 }
 
-pub async fn log_query(
-    query: Result<(), Error>,
-    tx: Option<Transaction<'_, Postgres>>,
-) -> Result<Option<Transaction<'_, Postgres>>, HttpResponse> {
-    match query {
-        Ok(_) => Ok(tx),
-        Err(e) => {
-            log!(Level::Warn, "DB Query failed: {}", e);
-            if let Some(tx) = tx {
-                match tx.rollback().await {
-                    Ok(_) => {}
-                    Err(tx_e) => {
-                        log!(Level::Error, "Transaction failed to rollback: {}", tx_e);
-                        return Err(HttpResponse::InternalServerError().body("Internal DB Error"));
-                    }
-                }
-            }
-            return Err(HttpResponse::InternalServerError().body("Internal DB Error"));
-        }
+/// Who a request from `user` is made for: the member the token belongs to,
+/// or freshman account `fid` if it's theirs. A freshman account belongs to
+/// whoever has the same RIT username in LDAP.
+pub async fn token_person(
+    state: &AppState,
+    conn: &mut PgConnection,
+    user: &User,
+    fid: Option<i32>,
+) -> Result<Person, AppError> {
+    let fid = match fid {
+        Some(fid) => fid,
+        None => return Ok(Person::Member(user.preferred_username.clone())),
+    };
+    let account = freshmen::get_freshman(conn, fid).await?;
+    let users = state
+        .ldap
+        .get_user(&user.preferred_username, &SearchAttrs::minimal())
+        .await
+        .map_err(ldap_error)?;
+
+    let key = account.rit_username.as_deref().and_then(rit_username_key);
+    if key.is_some()
+        && users
+            .iter()
+            .any(|u| rit_username_key(&u.rit_username) == key)
+    {
+        Ok(Person::Freshman(fid))
+    } else {
+        log!(
+            Level::Warn,
+            "{} tried to act as freshman {fid}",
+            user.preferred_username
+        );
+        Err(AppError::Unauthorized)
     }
 }
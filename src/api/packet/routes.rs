@@ -0,0 +1,104 @@
+use crate::app::AppState;
+use crate::auth::CSHAuth;
+use crate::error::AppError;
+use crate::schema::db::PacketSettings;
+use crate::transaction::UnitOfWork;
+use crate::{packet, repo};
+use actix_web::{
+    get, put,
+    web::{Data, Json, Path},
+    HttpResponse, Responder,
+};
+use log::{log, Level};
+
+#[utoipa::path(
+    context_path="/api/packet",
+    responses(
+        (status = 200, description = "Packets left out of evals because they are missing information", body = [IncompletePacket]),
+        (status = 500, description = "Error created by Query"),
+        )
+    )]
+#[get("/incomplete", wrap = "CSHAuth::evals_only()")]
+pub async fn get_incomplete_packets(state: Data<AppState>) -> impl Responder {
+    log!(Level::Info, "GET /packet/incomplete");
+    match packet::get_all_packets(&state.packet_db).await {
+        Ok(report) => HttpResponse::Ok().json(report.incomplete),
+        Err(e) => e.into(),
+    }
+}
+
+#[utoipa::path(
+    context_path="/api/packet",
+    responses(
+        (status = 200, description = "Packet settings for the current operating year", body = PacketSettings),
+        (status = 500, description = "Error created by Query"),
+        )
+    )]
+#[get("/settings", wrap = "CSHAuth::enabled()")]
+pub async fn get_packet_settings(state: Data<AppState>) -> impl Responder {
+    log!(Level::Info, "GET /packet/settings");
+    let mut conn = match state.db.acquire().await {
+        Ok(conn) => conn,
+        Err(e) => return AppError::Database(e).into(),
+    };
+    match repo::packet::get_packet_settings(&mut conn, &state.year_start).await {
+        Ok(settings) => HttpResponse::Ok().json(settings),
+        Err(e) => e.into(),
+    }
+}
+
+#[utoipa::path(
+    context_path="/api/packet",
+    request_body = PacketSettings,
+    responses(
+        (status = 200, description = "Set packet settings for the operating year starting at year_start", body = PacketSettings),
+        (status = 400, description = "Invalid misc signature cap"),
+        (status = 500, description = "Error created by Query"),
+        )
+    )]
+#[put("/settings", wrap = "CSHAuth::evals_only()")]
+pub async fn set_packet_settings(
+    mut uow: UnitOfWork,
+    body: Json<PacketSettings>,
+) -> Result<HttpResponse, AppError> {
+    log!(Level::Info, "PUT /packet/settings");
+    let settings = body.into_inner();
+    if settings.misc_cap < 0 {
+        return Err(AppError::BadRequest(String::from(
+            "Misc signature cap can't be negative",
+        )));
+    }
+    let settings = repo::packet::set_packet_settings(&mut uow, &settings).await?;
+
+    uow.commit().await?;
+    Ok(HttpResponse::Ok().json(settings))
+}
+
+#[utoipa::path(
+    context_path="/api/packet",
+    responses(
+        (status = 200, description = "Upperclassmen and misc signatures on an intro member's packet", body = PacketSignatures),
+        (status = 404, description = "No packet for this RIT username"),
+        (status = 422, description = "Packet is missing information", body = IncompletePacket),
+        (status = 500, description = "Error created by Query"),
+        )
+    )]
+#[get("/{rit_username}", wrap = "CSHAuth::enabled()")]
+pub async fn get_packet_signatures(path: Path<(String,)>, state: Data<AppState>) -> impl Responder {
+    let (rit_username,) = path.into_inner();
+    log!(Level::Info, "GET /packet/{rit_username}");
+    let mut conn = match state.db.acquire().await {
+        Ok(conn) => conn,
+        Err(e) => return AppError::Database(e).into(),
+    };
+    let settings = match repo::packet::get_packet_settings(&mut conn, &state.year_start).await {
+        Ok(settings) => settings,
+        Err(e) => return e.into(),
+    };
+    match packet::get_packet_signatures(&rit_username, &settings, &state.packet_db).await {
+        Ok(Some(Ok(signatures))) => HttpResponse::Ok().json(signatures),
+        Ok(Some(Err(incomplete))) => HttpResponse::UnprocessableEntity().json(incomplete),
+        Ok(None) => AppError::NotFound(String::from("No packet found")).into(),
+        Err(e) => e.into(),
+    }
+}
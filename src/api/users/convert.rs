@@ -0,0 +1,139 @@
+use crate::identity::{rit_username_key, FreshmanAccountIdentity};
+use crate::ldap::user::LdapUser;
+use crate::schema::api::{
+    ConversionLogEntry, ConversionPair, ConversionProposal, ConversionResult, ConvertedData,
+};
+use crate::schema::db::{FreshmanAccount, FreshmanConversion};
+use std::collections::{HashMap, HashSet};
+
+/// Match freshman accounts to the intro members whose RIT username is the
+/// same
+#[must_use]
+pub fn propose(
+    accounts: &[FreshmanAccountIdentity],
+    intro_members: &[LdapUser],
+) -> Vec<ConversionProposal> {
+    let mut by_username: HashMap<String, Vec<&LdapUser>> = HashMap::new();
+    for member in intro_members {
+        if let Some(key) = rit_username_key(&member.rit_username) {
+            by_username.entry(key).or_default().push(member);
+        }
+    }
+    accounts
+        .iter()
+        .map(|account| {
+            let matches = account
+                .rit_username
+                .as_deref()
+                .and_then(rit_username_key)
+                .and_then(|key| by_username.get(&key))
+                .map(Vec::as_slice)
+                .unwrap_or_default();
+            let only = match matches {
+                [member] => Some(*member),
+                _ => None,
+            };
+            ConversionProposal {
+                fid: account.fid,
+                name: account.name.clone(),
+                rit_username: account.rit_username.clone(),
+                uid: only.map(|m| m.uid.clone()),
+                member_name: only.map(|m| m.cn.clone()),
+                candidates: if matches.len() > 1 {
+                    matches.iter().map(|m| m.uid.clone()).collect()
+                } else {
+                    Vec::new()
+                },
+            }
+        })
+        .collect()
+}
+
+/// Work out which CSH account each freshman in `pairs` is converted to, and
+/// anything stopping the conversion. `known_uids` holds every CSH account
+/// that exists.
+#[must_use]
+pub fn resolve(
+    pairs: &[ConversionPair],
+    proposals: &[ConversionProposal],
+    known_uids: &HashSet<String>,
+) -> Vec<ConversionResult> {
+    let proposals: HashMap<i32, &ConversionProposal> =
+        proposals.iter().map(|p| (p.fid, p)).collect();
+    let mut fids = HashSet::new();
+    let mut uids = HashSet::new();
+    pairs
+        .iter()
+        .map(|pair| {
+            let mut errors = Vec::new();
+            let proposal = proposals.get(&pair.fid);
+            let uid = match (&pair.uid, proposal) {
+                (Some(uid), _) => Some(uid.trim().to_owned()),
+                (None, Some(proposal)) => proposal.uid.clone(),
+                (None, None) => None,
+            };
+
+            if proposal.is_none() {
+                errors.push(format!("No freshman account {}", pair.fid));
+            }
+            if !fids.insert(pair.fid) {
+                errors.push(format!("Freshman {} is listed more than once", pair.fid));
+            }
+            match &uid {
+                Some(uid) => {
+                    if !known_uids.contains(uid) {
+                        errors.push(format!("No CSH account {uid}"));
+                    }
+                    if !uids.insert(uid.clone()) {
+                        errors.push(format!("{uid} is paired with more than one freshman"));
+                    }
+                }
+                None => match proposal {
+                    Some(proposal) if !proposal.candidates.is_empty() => errors.push(format!(
+                        "Matches more than one CSH account ({}), give a uid",
+                        proposal.candidates.join(", ")
+                    )),
+                    Some(_) => errors.push(String::from("No CSH account matched, give a uid")),
+                    None => {}
+                },
+            }
+
+            ConversionResult {
+                fid: pair.fid,
+                proposed: pair.uid.is_none() && uid.is_some(),
+                uid,
+                converted: None,
+                conversion: None,
+                errors,
+            }
+        })
+        .collect()
+}
+
+impl From<FreshmanConversion> for ConversionLogEntry {
+    fn from(conversion: FreshmanConversion) -> Self {
+        ConversionLogEntry {
+            moved: ConvertedData {
+                seminars: conversion.seminar_attendance.len() as i64,
+                directorships: conversion.committee_attendance.len() as i64,
+                house_meetings: conversion.hm_attendance.len() as i64,
+                excuses: conversion.excuse_requests.len() as i64,
+                batches: conversion.batch_users.len() as i64,
+                batch_pulls: i64::from(conversion.batch_pull.is_some()),
+            },
+            freshman: FreshmanAccount {
+                id: conversion.fid,
+                name: conversion.name,
+                eval_date: conversion.eval_date,
+                onfloor_status: conversion.onfloor_status,
+                room_number: conversion.room_number,
+                signatures_missed: conversion.signatures_missed,
+                rit_username: conversion.rit_username,
+            },
+            id: conversion.id,
+            uid: conversion.uid,
+            converted: conversion.converted,
+            undone: conversion.undone,
+        }
+    }
+}
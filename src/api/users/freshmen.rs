@@ -0,0 +1,73 @@
+use crate::api::users::import::present;
+use crate::identity::rit_username_key;
+use crate::schema::api::FreshmanAccountEdit;
+use crate::schema::db::FreshmanAccount;
+use std::collections::HashSet;
+
+/// Longest name 'freshman_accounts' can hold
+const MAX_NAME: usize = 64;
+/// Longest RIT username 'freshman_accounts' can hold
+const MAX_RIT_USERNAME: usize = 10;
+
+/// Apply `edit` to `account`. `taken` holds the RIT usernames of every other
+/// freshman account, as given by `rit_username_key`. Returns the edited
+/// account, or everything wrong with the edit.
+pub fn apply_edit(
+    account: &FreshmanAccount,
+    edit: FreshmanAccountEdit,
+    taken: &HashSet<String>,
+) -> Result<FreshmanAccount, Vec<String>> {
+    let mut edited = account.clone();
+    let mut errors = Vec::new();
+
+    if edit.name.is_none()
+        && edit.eval_date.is_none()
+        && edit.onfloor_status.is_none()
+        && edit.room_number.is_none()
+        && edit.rit_username.is_none()
+    {
+        return Err(vec![String::from("Nothing to change")]);
+    }
+
+    if let Some(name) = edit.name {
+        match present(Some(name)) {
+            None => errors.push(String::from("name can't be empty")),
+            Some(name) if name.chars().count() > MAX_NAME => {
+                errors.push(format!("name can't be longer than {MAX_NAME} characters"))
+            }
+            Some(name) => edited.name = name,
+        }
+    }
+    if let Some(raw) = edit.rit_username {
+        match rit_username_key(&raw) {
+            None => errors.push(String::from("rit_username can't be empty")),
+            Some(username) if username.chars().count() > MAX_RIT_USERNAME => errors.push(format!(
+                "rit_username can't be longer than {MAX_RIT_USERNAME} characters"
+            )),
+            Some(username) if taken.contains(&username) => {
+                errors.push(format!("a freshman account already exists for {username}"))
+            }
+            Some(username) => edited.rit_username = Some(username),
+        }
+    }
+    if let Some(eval_date) = edit.eval_date {
+        edited.eval_date = eval_date;
+    }
+    if let Some(room_number) = edit.room_number {
+        edited.room_number = present(Some(room_number));
+    }
+    if let Some(onfloor_status) = edit.onfloor_status {
+        edited.onfloor_status = Some(onfloor_status);
+    }
+    if edited.onfloor_status == Some(true) && edited.room_number.is_none() {
+        errors.push(String::from(
+            "room_number is required for on floor freshmen",
+        ));
+    }
+
+    if errors.is_empty() {
+        Ok(edited)
+    } else {
+        Err(errors)
+    }
+}
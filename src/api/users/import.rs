@@ -0,0 +1,112 @@
+use crate::identity::rit_username_key;
+use crate::schema::api::{FreshmanImportError, FreshmanImportRow, NewIntroMember};
+use std::collections::{HashMap, HashSet};
+
+/// A row of an import, or why it couldn't be parsed
+pub type ParsedRow = Result<FreshmanImportRow, String>;
+
+/// Parse a CSV import. The first line must be a header naming the columns of
+/// `FreshmanImportRow`, in any order. Dates are `YYYY-MM-DD` and
+/// `onfloor_status` is `true` or `false`. Empty cells are treated as missing.
+#[must_use]
+pub fn parse_csv(body: &[u8]) -> Vec<ParsedRow> {
+    csv::ReaderBuilder::new()
+        .trim(csv::Trim::All)
+        .from_reader(body)
+        .deserialize::<FreshmanImportRow>()
+        .map(|row| row.map_err(|e| e.to_string()))
+        .collect()
+}
+
+/// Parse a JSON array import. Each element is parsed on its own so one bad
+/// row doesn't hide problems with the rest.
+pub fn parse_json(body: &[u8]) -> Result<Vec<ParsedRow>, String> {
+    let rows: Vec<serde_json::Value> = serde_json::from_slice(body).map_err(|e| e.to_string())?;
+    Ok(rows
+        .into_iter()
+        .map(|row| serde_json::from_value(row).map_err(|e| e.to_string()))
+        .collect())
+}
+
+pub(crate) fn present(value: Option<String>) -> Option<String> {
+    value.map(|v| v.trim().to_owned()).filter(|v| !v.is_empty())
+}
+
+/// Check every row of an import. `existing` holds the RIT usernames of
+/// freshman accounts already in the database, as given by
+/// `rit_username_key`. Returns the freshmen to create if every row is valid,
+/// or every row's errors otherwise.
+pub fn validate(
+    rows: Vec<ParsedRow>,
+    existing: &HashSet<String>,
+) -> Result<Vec<NewIntroMember>, Vec<FreshmanImportError>> {
+    let mut freshmen = Vec::with_capacity(rows.len());
+    let mut errors = Vec::new();
+    let mut seen: HashMap<String, usize> = HashMap::new();
+
+    for (i, row) in rows.into_iter().enumerate() {
+        let row_number = i + 1;
+        let row = match row {
+            Ok(row) => row,
+            Err(e) => {
+                errors.push(FreshmanImportError {
+                    row: row_number,
+                    rit_username: None,
+                    errors: vec![e],
+                });
+                continue;
+            }
+        };
+
+        let mut row_errors = Vec::new();
+        let name = present(row.name);
+        if name.is_none() {
+            row_errors.push(String::from("name is required"));
+        }
+        let rit_username = row.rit_username.as_deref().and_then(rit_username_key);
+        match &rit_username {
+            None => row_errors.push(String::from("rit_username is required")),
+            Some(username) if existing.contains(username) => {
+                row_errors.push(format!("a freshman account already exists for {username}"))
+            }
+            Some(username) => match seen.get(username) {
+                Some(first) => row_errors.push(format!("{username} is already in row {first}")),
+                None => {
+                    seen.insert(username.clone(), row_number);
+                }
+            },
+        }
+        if row.eval_date.is_none() {
+            row_errors.push(String::from("eval_date is required"));
+        }
+        let onfloor_status = row.onfloor_status.unwrap_or(false);
+        let room_number = present(row.room_number);
+        if onfloor_status && room_number.is_none() {
+            row_errors.push(String::from(
+                "room_number is required for on floor freshmen",
+            ));
+        }
+
+        match (name, row.eval_date, rit_username.clone()) {
+            (Some(name), Some(eval_date), Some(rit_username)) if row_errors.is_empty() => freshmen
+                .push(NewIntroMember {
+                    name,
+                    eval_date,
+                    onfloor_status,
+                    room_number,
+                    rit_username,
+                }),
+            _ => errors.push(FreshmanImportError {
+                row: row_number,
+                rit_username,
+                errors: row_errors,
+            }),
+        }
+    }
+
+    if errors.is_empty() {
+        Ok(freshmen)
+    } else {
+        Err(errors)
+    }
+}
@@ -1,15 +1,30 @@
-use crate::api::{log_query, log_query_as, open_transaction};
-use crate::auth::CSHAuth;
-use crate::ldap;
-use crate::schema::api::{FreshmanUpgrade, ID};
+use crate::api::users::{convert, freshmen::apply_edit, import};
+use crate::auth::{CSHAuth, User};
+use crate::error::{ldap_error, AppError};
+use crate::identity;
+use crate::ldap::{group::Group, search::SearchAttrs};
+use crate::repo::{freshmen, users};
+use crate::schema::api::{
+    ConversionPair, ConversionProposal, ConversionReport, DryRun, FreshmanAccountEdit,
+    FreshmanImportReport, FreshmanUpgrade, MemberProfile,
+};
+use crate::transaction::UnitOfWork;
 use crate::{app::AppState, schema::api::NewIntroMember};
 use actix_web::{
-    get, post, put,
-    web::{Data, Json, Path},
-    HttpResponse, Responder,
+    delete, get, post, put,
+    web::{Bytes, Data, Json, Path, Query},
+    HttpMessage, HttpRequest, HttpResponse, Responder,
 };
 use log::{log, Level};
-use sqlx::{query, query_as};
+use std::collections::HashSet;
+
+/// Eboard and admins can see sensitive attributes such as drink balance
+fn attrs_for(user: &Option<User>) -> SearchAttrs {
+    match user {
+        Some(user) if user.admin() => SearchAttrs::default().with_sensitive(),
+        _ => SearchAttrs::default(),
+    }
+}
 
 #[utoipa::path(
     context_path="/api/users",
@@ -19,9 +34,13 @@ use sqlx::{query, query_as};
     )]
 #[get("/voting_count", wrap = "CSHAuth::enabled()")]
 pub async fn get_voting_count(state: Data<AppState>) -> impl Responder {
-    match ldap::get_active_upperclassmen(&state.ldap).await {
+    match state
+        .ldap
+        .get_active_upperclassmen(&SearchAttrs::minimal())
+        .await
+    {
         Ok(v) => HttpResponse::Ok().body(format!("{}", v.len())),
-        Err(e) => HttpResponse::InternalServerError().body(e.to_string()),
+        Err(e) => ldap_error(e).into(),
     }
 }
 
@@ -33,9 +52,13 @@ pub async fn get_voting_count(state: Data<AppState>) -> impl Responder {
     )]
 #[get("/active_count", wrap = "CSHAuth::enabled()")]
 pub async fn get_active_count(state: Data<AppState>) -> impl Responder {
-    match ldap::get_group_members(&state.ldap, "active").await {
+    match state
+        .ldap
+        .get_group_members(Group::Active, &SearchAttrs::minimal())
+        .await
+    {
         Ok(v) => HttpResponse::Ok().body(format!("{}", v.len())),
-        Err(e) => HttpResponse::InternalServerError().body(e.to_string()),
+        Err(e) => ldap_error(e).into(),
     }
 }
 
@@ -46,11 +69,19 @@ pub async fn get_active_count(state: Data<AppState>) -> impl Responder {
         )
     )]
 #[get("/search/{query}", wrap = "CSHAuth::enabled()")]
-pub async fn search_members(state: Data<AppState>, path: Path<(String,)>) -> impl Responder {
+pub async fn search_members(
+    state: Data<AppState>,
+    path: Path<(String,)>,
+    user: Option<User>,
+) -> impl Responder {
     let query = path.into_inner().0;
-    match ldap::search_users(&state.ldap, query.as_str()).await {
+    match state
+        .ldap
+        .search_users(query.as_str(), &attrs_for(&user))
+        .await
+    {
         Ok(v) => HttpResponse::Ok().json(v),
-        Err(e) => HttpResponse::InternalServerError().body(e.to_string()),
+        Err(e) => ldap_error(e).into(),
     }
 }
 
@@ -61,13 +92,68 @@ pub async fn search_members(state: Data<AppState>, path: Path<(String,)>) -> imp
         )
     )]
 #[get("/all", wrap = "CSHAuth::enabled()")]
-pub async fn all_members(state: Data<AppState>) -> impl Responder {
-    match ldap::get_group_members(&state.ldap, "member").await {
+pub async fn all_members(state: Data<AppState>, user: Option<User>) -> impl Responder {
+    match state
+        .ldap
+        .get_group_members(Group::Member, &attrs_for(&user))
+        .await
+    {
         Ok(v) => HttpResponse::Ok().json(v),
-        Err(e) => HttpResponse::InternalServerError().body(e.to_string()),
+        Err(e) => ldap_error(e).into(),
     }
 }
 
+#[utoipa::path(
+    context_path="/api/users",
+    responses(
+        (status = 200, description = "Gets all members of a group", body = [LdapUser]),
+        (status = 400, description = "Unknown group"),
+        )
+    )]
+#[get("/groups/{group}", wrap = "CSHAuth::enabled()")]
+pub async fn get_group(
+    state: Data<AppState>,
+    path: Path<(String,)>,
+    user: Option<User>,
+) -> impl Responder {
+    let group = match path.into_inner().0.parse::<Group>() {
+        Ok(group) => group,
+        Err(e) => return AppError::BadRequest(e.to_string()).into(),
+    };
+    match state.ldap.get_group_members(group, &attrs_for(&user)).await {
+        Ok(v) => HttpResponse::Ok().json(v),
+        Err(e) => ldap_error(e).into(),
+    }
+}
+
+#[utoipa::path(
+    context_path="/api/users",
+    responses(
+        (status = 200, description = "LDAP cache hit/miss counts", body = LdapCacheStats),
+        (status = 404, description = "Directory has no cache"),
+        )
+    )]
+#[get("/cache", wrap = "CSHAuth::enabled()")]
+pub async fn get_ldap_cache_stats(state: Data<AppState>) -> impl Responder {
+    match state.ldap.cache() {
+        Some(cache) => HttpResponse::Ok().json(cache.stats()),
+        None => AppError::NotFound(String::from("Directory has no cache")).into(),
+    }
+}
+
+#[utoipa::path(
+    context_path="/api/users",
+    responses(
+        (status = 204, description = "LDAP cache cleared"),
+        )
+    )]
+#[delete("/cache", wrap = "CSHAuth::evals_only()")]
+pub async fn clear_ldap_cache(state: Data<AppState>) -> impl Responder {
+    log!(Level::Info, "Clearing LDAP cache");
+    state.ldap.invalidate_cache();
+    HttpResponse::NoContent().finish()
+}
+
 #[utoipa::path(
     context_path="/api/users",
     responses(
@@ -76,49 +162,285 @@ pub async fn all_members(state: Data<AppState>) -> impl Responder {
     )]
 #[post("/", wrap = "CSHAuth::evals_only()")]
 pub async fn create_freshman_user(
-    state: Data<AppState>,
+    mut uow: UnitOfWork,
     body: Json<NewIntroMember>,
-) -> impl Responder {
-    let mut transaction = match open_transaction(&state.db).await {
-        Ok(t) => t,
-        Err(res) => return res,
+) -> Result<HttpResponse, AppError> {
+    let id = freshmen::create_freshman(&mut uow, &body).await?;
+    log!(Level::Debug, "Inserted freshman into db. ID={}", id);
+    uow.commit().await?;
+    Ok(HttpResponse::Created().finish())
+}
+
+#[utoipa::path(
+    context_path="/api/users",
+    responses(
+        (status = 200, description = "Every freshman account", body = [FreshmanAccount]),
+        (status = 500, description = "Error created by Query"),
+        )
+    )]
+#[get("/freshmen", wrap = "CSHAuth::evals_only()")]
+pub async fn get_freshman_accounts(state: Data<AppState>) -> impl Responder {
+    log!(Level::Info, "GET /users/freshmen");
+    match freshmen::get_freshmen(&state.db).await {
+        Ok(accounts) => HttpResponse::Ok().json(accounts),
+        Err(e) => e.into(),
+    }
+}
+
+#[utoipa::path(
+    context_path="/api/users",
+    responses(
+        (status = 200, description = "A freshman account", body = FreshmanAccount),
+        (status = 404, description = "No such freshman account"),
+        (status = 500, description = "Error created by Query"),
+        )
+    )]
+#[get("/freshmen/{fid}", wrap = "CSHAuth::evals_only()")]
+pub async fn get_freshman_account(state: Data<AppState>, path: Path<(i32,)>) -> impl Responder {
+    let (fid,) = path.into_inner();
+    log!(Level::Info, "GET /users/freshmen/{fid}");
+    let mut conn = match state.db.acquire().await {
+        Ok(conn) => conn,
+        Err(e) => return AppError::Database(e).into(),
     };
-    log!(Level::Trace, "Acquired transaction");
-
-    let id: i32;
-
-    match log_query_as(
-        query_as!(
-            ID,
-            "INSERT INTO freshman_accounts (name, eval_date, onfloor_status, room_number, \
-             signatures_missed, rit_username)
-        VALUES ($1::varchar, $2::date, $3, $4::varchar, null, $5::varchar) RETURNING id",
-            body.name,
-            body.eval_date,
-            body.onfloor_status,
-            body.room_number,
-            body.rit_username
-        )
-        .fetch_all(&state.db)
-        .await,
-        Some(transaction),
-    )
-    .await
-    {
-        Ok((tx, i)) => {
-            transaction = tx.unwrap();
-            id = i[0].id;
+    match freshmen::get_freshman(&mut conn, fid).await {
+        Ok(account) => HttpResponse::Ok().json(account),
+        Err(e) => e.into(),
+    }
+}
+
+#[utoipa::path(
+    context_path="/api/users",
+    request_body = FreshmanAccountEdit,
+    responses(
+        (status = 200, description = "The edited freshman account", body = FreshmanAccount),
+        (status = 404, description = "No such freshman account"),
+        (status = 422, description = "Everything wrong with the edit", body = [String]),
+        (status = 500, description = "Error created by Query"),
+        )
+    )]
+#[put("/freshmen/{fid}", wrap = "CSHAuth::evals_only()")]
+pub async fn edit_freshman_account(
+    mut uow: UnitOfWork,
+    path: Path<(i32,)>,
+    body: Json<FreshmanAccountEdit>,
+) -> Result<HttpResponse, AppError> {
+    let (fid,) = path.into_inner();
+    log!(Level::Info, "PUT /users/freshmen/{fid}");
+
+    let account = freshmen::lock_freshman(&mut uow, fid).await?;
+    let taken = freshmen::get_rit_usernames(&mut uow, Some(fid)).await?;
+    let edited = match apply_edit(&account, body.into_inner(), &taken) {
+        Ok(edited) => edited,
+        Err(errors) => return Ok(HttpResponse::UnprocessableEntity().json(errors)),
+    };
+    let edited = freshmen::update_freshman(&mut uow, &edited).await?;
+
+    log!(Level::Debug, "Edited freshman {fid}");
+    uow.commit().await?;
+    Ok(HttpResponse::Ok().json(edited))
+}
+
+#[utoipa::path(
+    context_path="/api/users",
+    responses(
+        (status = 200, description = "The deleted freshman account", body = FreshmanAccount),
+        (status = 404, description = "No such freshman account"),
+        (status = 500, description = "Error created by Query"),
+        )
+    )]
+#[delete("/freshmen/{fid}", wrap = "CSHAuth::evals_only()")]
+pub async fn delete_freshman_account(
+    mut uow: UnitOfWork,
+    path: Path<(i32,)>,
+) -> Result<HttpResponse, AppError> {
+    let (fid,) = path.into_inner();
+    log!(Level::Info, "DELETE /users/freshmen/{fid}");
+
+    let deleted = freshmen::delete_freshman(&mut uow, fid).await?;
+
+    log!(Level::Debug, "Deleted freshman {fid}");
+    uow.commit().await?;
+    Ok(HttpResponse::Ok().json(deleted))
+}
+
+#[utoipa::path(
+    context_path="/api/users",
+    params(DryRun),
+    request_body(content = [FreshmanImportRow], description = "A JSON array of freshmen, or a CSV with a header row naming the same fields"),
+    responses(
+        (status = 200, description = "Dry run found no problems", body = FreshmanImportReport),
+        (status = 201, description = "Every freshman was imported", body = FreshmanImportReport),
+        (status = 400, description = "Body isn't a JSON array or CSV"),
+        (status = 415, description = "Content type isn't application/json or text/csv"),
+        (status = 422, description = "Some rows are invalid, so nothing was imported", body = FreshmanImportReport),
+        (status = 500, description = "Error created by Query"),
+        )
+    )]
+#[post("/import", wrap = "CSHAuth::evals_only()")]
+pub async fn import_freshmen(
+    mut uow: UnitOfWork,
+    req: HttpRequest,
+    body: Bytes,
+    dry_run: Query<DryRun>,
+) -> Result<HttpResponse, AppError> {
+    log!(Level::Info, "POST /users/import");
+    let rows = match req.content_type() {
+        "text/csv" => import::parse_csv(&body),
+        "application/json" => match import::parse_json(&body) {
+            Ok(rows) => rows,
+            Err(e) => {
+                log!(Level::Warn, "Invalid freshman import: {e}");
+                return Err(AppError::BadRequest(e));
+            }
+        },
+        other => {
+            return Err(AppError::UnsupportedMediaType(format!(
+                "Can't import {other}, use application/json or text/csv"
+            )))
+        }
+    };
+    let row_count = rows.len();
+
+    let existing = freshmen::get_rit_usernames(&mut uow, None).await?;
+
+    let new_freshmen = match import::validate(rows, &existing) {
+        Ok(freshmen) => freshmen,
+        Err(errors) => {
+            log!(
+                Level::Info,
+                "Rejected freshman import with {} bad rows",
+                errors.len()
+            );
+            return Ok(
+                HttpResponse::UnprocessableEntity().json(FreshmanImportReport {
+                    dry_run: dry_run.dry_run,
+                    rows: row_count,
+                    created: Vec::new(),
+                    errors,
+                }),
+            );
         }
-        Err(res) => return res,
+    };
+    if dry_run.dry_run {
+        return Ok(HttpResponse::Ok().json(FreshmanImportReport {
+            dry_run: true,
+            rows: row_count,
+            created: Vec::new(),
+            errors: Vec::new(),
+        }));
     }
-    log!(Level::Debug, "Inserted freshman into db. ID={}", id);
-    match transaction.commit().await {
-        Ok(_) => HttpResponse::Created().finish(),
-        Err(e) => {
-            log!(Level::Error, "Transaction failed to commit");
-            HttpResponse::InternalServerError().body(e.to_string())
+
+    let mut created = Vec::with_capacity(new_freshmen.len());
+    for freshman in &new_freshmen {
+        created.push(freshmen::create_freshman(&mut uow, freshman).await?);
+    }
+    log!(Level::Debug, "Imported {} freshmen", created.len());
+    uow.commit().await?;
+    Ok(HttpResponse::Created().json(FreshmanImportReport {
+        dry_run: false,
+        rows: row_count,
+        created,
+        errors: Vec::new(),
+    }))
+}
+
+/// Freshman accounts and the intro members they look like they were
+/// converted to
+async fn conversion_proposals(
+    state: &AppState,
+) -> Result<(Vec<ConversionProposal>, HashSet<String>), AppError> {
+    let accounts = identity::get_freshman_account_identities(&state.db).await?;
+    let intro_members = match state.ldap.get_intro_members(&SearchAttrs::minimal()).await {
+        Ok(members) => members,
+        Err(e) => return Err(ldap_error(e)),
+    };
+    Ok((
+        convert::propose(&accounts, &intro_members),
+        intro_members.into_iter().map(|m| m.uid).collect(),
+    ))
+}
+
+#[utoipa::path(
+    context_path="/api/users",
+    responses(
+        (status = 200, description = "Every freshman account and the CSH account it matches by RIT username", body = [ConversionProposal]),
+        (status = 500, description = "Error created by Query or LDAP"),
+        (status = 503, description = "LDAP unavailable"),
+        )
+    )]
+#[get("/convert", wrap = "CSHAuth::evals_only()")]
+pub async fn get_conversion_proposals(state: Data<AppState>) -> impl Responder {
+    log!(Level::Info, "GET /users/convert");
+    match conversion_proposals(&state).await {
+        Ok((proposals, _)) => HttpResponse::Ok().json(proposals),
+        Err(e) => e.into(),
+    }
+}
+
+#[utoipa::path(
+    context_path="/api/users",
+    params(DryRun),
+    request_body(content = [ConversionPair], description = "Freshmen to convert. Leave out uid to use the proposed match"),
+    responses(
+        (status = 200, description = "Every freshman was converted, or would be on a dry run", body = ConversionReport),
+        (status = 404, description = "A freshman account was deleted during conversion"),
+        (status = 422, description = "Some pairs can't be converted, so nothing was", body = ConversionReport),
+        (status = 500, description = "Error created by Query or LDAP"),
+        (status = 503, description = "LDAP unavailable"),
+        )
+    )]
+#[post("/convert", wrap = "CSHAuth::evals_only()")]
+pub async fn convert_freshmen(
+    state: Data<AppState>,
+    mut uow: UnitOfWork,
+    body: Json<Vec<ConversionPair>>,
+    dry_run: Query<DryRun>,
+) -> Result<HttpResponse, AppError> {
+    log!(Level::Info, "POST /users/convert");
+    let (proposals, mut known_uids) = conversion_proposals(&state).await?;
+    // Evals can pair freshmen with accounts that aren't intro members
+    for uid in body.iter().filter_map(|pair| pair.uid.as_deref()) {
+        let uid = uid.trim();
+        if known_uids.contains(uid) {
+            continue;
+        }
+        match state.ldap.get_user(uid, &SearchAttrs::minimal()).await {
+            Ok(users) => known_uids.extend(users.into_iter().map(|u| u.uid)),
+            Err(e) => return Err(ldap_error(e)),
+        }
+    }
+
+    let mut results = convert::resolve(&body, &proposals, &known_uids);
+    if results.iter().any(|r| !r.errors.is_empty()) {
+        return Ok(HttpResponse::UnprocessableEntity().json(ConversionReport {
+            dry_run: dry_run.dry_run,
+            results,
+        }));
+    }
+    for result in &mut results {
+        let uid = result.uid.as_deref().unwrap_or_default();
+        let (conversion, converted) = freshmen::convert_freshman(&mut uow, result.fid, uid).await?;
+        result.converted = Some(converted);
+        // A dry run's conversions are rolled back, so there's nothing to undo
+        if !dry_run.dry_run {
+            result.conversion = Some(conversion);
         }
     }
+
+    let report = ConversionReport {
+        dry_run: dry_run.dry_run,
+        results,
+    };
+    if dry_run.dry_run {
+        uow.rollback().await;
+        return Ok(HttpResponse::Ok().json(report));
+    }
+    log!(Level::Debug, "Converted {} freshmen", report.results.len());
+    uow.commit().await?;
+    // The new members show up in different groups now
+    state.ldap.invalidate_cache();
+    Ok(HttpResponse::Ok().json(report))
 }
 
 #[utoipa::path(
@@ -130,114 +452,102 @@ pub async fn create_freshman_user(
 #[put("/", wrap = "CSHAuth::evals_only()")]
 pub async fn convert_freshman_user(
     state: Data<AppState>,
+    mut uow: UnitOfWork,
     body: Json<FreshmanUpgrade>,
+) -> Result<HttpResponse, AppError> {
+    freshmen::convert_freshman(&mut uow, body.fid, &body.uid).await?;
+    uow.commit().await?;
+    // The new member shows up in different groups now
+    state.ldap.invalidate_cache();
+    Ok(HttpResponse::Created().finish())
+}
+
+#[utoipa::path(
+    context_path="/api/users",
+    responses(
+        (status = 200, description = "Every freshman converted to a member, most recent first", body = [ConversionLogEntry]),
+        (status = 500, description = "Error created by Query"),
+        )
+    )]
+#[get("/conversions", wrap = "CSHAuth::evals_only()")]
+pub async fn get_conversions(state: Data<AppState>) -> impl Responder {
+    log!(Level::Info, "GET /users/conversions");
+    match freshmen::get_conversions(&state.db).await {
+        Ok(conversions) => HttpResponse::Ok().json(conversions),
+        Err(e) => e.into(),
+    }
+}
+
+#[utoipa::path(
+    context_path="/api/users",
+    responses(
+        (status = 200, description = "The freshman account was restored and everything left of what was moved was moved back", body = ConversionUndo),
+        (status = 404, description = "No such conversion"),
+        (status = 409, description = "The conversion was already undone, or the freshman account exists again"),
+        (status = 500, description = "Error created by Query"),
+        )
+    )]
+#[post("/conversions/{id}/undo", wrap = "CSHAuth::evals_only()")]
+pub async fn undo_conversion(
+    mut uow: UnitOfWork,
+    path: Path<(i32,)>,
+) -> Result<HttpResponse, AppError> {
+    let (id,) = path.into_inner();
+    log!(Level::Info, "POST /users/conversions/{id}/undo");
+
+    let undo = freshmen::undo_conversion(&mut uow, id).await?;
+
+    uow.commit().await?;
+    Ok(HttpResponse::Ok().json(undo))
+}
+
+#[utoipa::path(
+    context_path="/api/users",
+    responses(
+        (status = 200, description = "Everything about a member", body = MemberProfile),
+        (status = 404, description = "User not found"),
+        (status = 500, description = "Error created by Query or LDAP"),
+        (status = 503, description = "LDAP unavailable"),
+        )
+    )]
+#[get("/{uid}", wrap = "CSHAuth::enabled()")]
+pub async fn get_member_profile(
+    state: Data<AppState>,
+    path: Path<(String,)>,
+    user: Option<User>,
 ) -> impl Responder {
-    let mut transaction = match open_transaction(&state.db).await {
-        Ok(t) => t,
-        Err(res) => return res,
-    };
-    log!(Level::Trace, "Acquired transaction");
-
-    // Migrate directorship attendance
-    transaction = match log_query(
-        query!(
-            "WITH dir_attendance AS (
-                DELETE FROM freshman_committee_attendance fca
-                WHERE fid=$1::int4
-                RETURNING $2::varchar AS uid, fca.meeting_id
-            ) INSERT INTO member_committee_attendance (uid, meeting_id)
-            SELECT * FROM dir_attendance",
-            body.fid,
-            body.uid,
-        )
-        .fetch_all(&state.db)
-        .await
-        .map(|_| ()),
-        Some(transaction),
-    )
-    .await
-    {
-        Ok(tx) => tx.unwrap(),
-        Err(res) => return res,
-    };
+    let (uid,) = path.into_inner();
+    log!(Level::Info, "GET /users/{uid}");
+    let year_start = state.year_start;
+    let attrs = attrs_for(&user);
 
-    // Migrate house meeting attendance
-    transaction = match log_query(
-        query!(
-            "WITH hm_attendance AS (
-                DELETE FROM freshman_hm_attendance fha 
-                WHERE fid=$1::int4
-                RETURNING $2::varchar AS uid, fha.meeting_id, fha.excuse, fha.attendance_status
-            ) INSERT INTO member_hm_attendance (uid, meeting_id, excuse, attendance_status)
-            SELECT * FROM hm_attendance",
-            body.fid,
-            body.uid,
-        )
-        .fetch_all(&state.db)
-        .await
-        .map(|_| ()),
-        Some(transaction),
-    )
-    .await
-    {
-        Ok(tx) => tx.unwrap(),
-        Err(res) => return res,
-    };
+    let (ldap_user, records) = futures::join!(
+        state.ldap.get_user(&uid, &attrs),
+        users::get_member_records(&state.db, &uid, &year_start),
+    );
 
-    // Migrate seminar attendance
-    transaction = match log_query(
-        query!(
-            "WITH sem_attendance AS (
-                DELETE FROM freshman_seminar_attendance fsa 
-                WHERE fid=$1::int4
-                RETURNING $2::varchar AS uid, fsa.seminar_id
-            ) INSERT INTO member_seminar_attendance (uid, seminar_id)
-            SELECT * FROM sem_attendance",
-            body.fid,
-            body.uid,
-        )
-        .fetch_all(&state.db)
-        .await
-        .map(|_| ()),
-        Some(transaction),
-    )
-    .await
-    {
-        Ok(tx) => tx.unwrap(),
-        Err(res) => return res,
+    let ldap_user = match ldap_user {
+        Ok(users) => match users.into_iter().next() {
+            Some(user) => user,
+            None => return AppError::NotFound(String::from("User not found")).into(),
+        },
+        Err(e) => return ldap_error(e).into(),
     };
-
-    // Remove freshman from any batch tables for deletion
-    // cascading delete scares me
-    transaction = match log_query(
-        query!(
-            "WITH fbps_deleted AS (
-                DELETE FROM freshman_batch_pulls fbp 
-                WHERE fbp.fid=$1::int4
-            ),
-            fbus_deleted AS (
-                DELETE FROM freshman_batch_users fbu  
-                WHERE fbu.fid=$1::int4
-            )
-            DELETE FROM freshman_accounts fa WHERE fa.id=$1::int4",
-            body.fid,
-        )
-        .fetch_all(&state.db)
-        .await
-        .map(|_| ()),
-        Some(transaction),
-    )
-    .await
-    {
-        Ok(tx) => tx.unwrap(),
-        Err(res) => return res,
+    let records = match records {
+        Ok(records) => records,
+        Err(e) => return e.into(),
     };
 
-    match transaction.commit().await {
-        Ok(_) => HttpResponse::Created().finish(),
-        Err(e) => {
-            log!(Level::Error, "Transaction failed to commit");
-            HttpResponse::InternalServerError().body(e.to_string())
-        }
-    }
+    HttpResponse::Ok().json(MemberProfile {
+        user: ldap_user,
+        onfloor_granted: records.onfloor_granted,
+        in_housing_queue: records.in_housing_queue,
+        coop: records.coop,
+        seminars: records.seminars,
+        directorships: records.directorships,
+        missed_hms: records.missed_hms,
+        major_projects: records.major_projects,
+        conditionals: records.conditionals,
+        eval_result: records.eval_result,
+    })
 }
@@ -1,15 +1,40 @@
 use crate::{
     api::{
-        attendance::{directorship::*, house::*, seminar::*},
+        attendance::{checkin::*, directorship::*, house::*, seminar::*},
         batch::batch::*,
         evals::routes::*,
         forms::routes::*,
+        housing::routes::*,
+        packet::routes::*,
         users::routes::*,
     },
-    ldap::{client::LdapClient, user::LdapUser},
+    error::AppError,
+    ldap::{
+        cache::LdapCacheStats,
+        directory::{self, Directory},
+        group::Group,
+        membership::{FailedGroupChange, GroupAction, GroupChange, GroupChangeReport},
+        user::LdapUser,
+    },
     schema::{
-        api::{Directorship, FreshmanUpgrade, IntroStatus, MemberStatus, NewIntroMember, Seminar},
-        db::CommitteeType,
+        api::{
+            CheckInCode, CheckInSubmission, ConversionLogEntry, ConversionPair, ConversionProposal,
+            ConversionReport, ConversionResult, ConversionUndo, ConvertedData, Directorship,
+            ErrorBody, ExcuseDecision, ExcuseRequest, ExcuseSubmission, FreshmanAccountEdit,
+            FreshmanHouseMeetingStatus, FreshmanIdentity, FreshmanImportError,
+            FreshmanImportReport, FreshmanImportRow, FreshmanUpgrade, HouseMeetingActive,
+            HouseMeetingAttendanceEdit, HouseMeetingRoster, HousingPointComponent,
+            HousingPointWeights, HousingPoints, HousingPointsRanking, HousingQueueEntry,
+            IdentityIssue, IdentityIssueKind, IdentityReport, IncompletePacket, IntroStatus,
+            MemberHouseMeetingStatus, MemberProfile, MemberStatus, MiscSignatures, NewIntroMember,
+            PacketSignatures, ProfileConditional, ProfileMajorProject, Seminar,
+            UpperclassmenSignatures,
+        },
+        db::{
+            CommitteeType, ConditionalStatus, CoopSemester, ExcuseStatus, FreshmanAccount,
+            HouseMeeting, MajorProjectStatus, OnFloorDate, PacketRounding, PacketSettings,
+            SpringEvalStatus,
+        },
     },
 };
 use actix_web::web::{self, scope, Data};
@@ -28,7 +53,7 @@ pub struct AppState {
     pub db: Pool<Postgres>,
     pub packet_db: Pool<Postgres>,
     pub year_start: chrono::NaiveDateTime,
-    pub ldap: LdapClient,
+    pub ldap: Arc<dyn Directory>,
     pub jwt_cache: Arc<Mutex<HashMap<String, PKey<Public>>>>,
 }
 
@@ -50,12 +75,28 @@ pub fn configure_app(cfg: &mut web::ServiceConfig) {
             delete_directorship,
             // attendance/house
             submit_hm_attendance,
+            start_house_meeting,
             get_hm_absences_by_user,
             get_hm_attendance_by_user_evals,
             modify_hm_attendance,
+            get_house_meetings,
+            get_house_meeting,
+            set_house_meeting_active,
+            delete_house_meeting,
+            submit_hm_excuse,
+            get_pending_hm_excuses,
+            decide_hm_excuse,
+            edit_house_meeting_attendee,
+            add_house_meeting_attendee,
+            // attendance/checkin
+            open_seminar_checkin,
+            open_directorship_checkin,
+            close_checkin,
+            submit_checkin,
             // evals
             get_intro_evals_wrapper,
             get_member_evals,
+            get_identity_report,
             get_gatekeep,
             // evals/batch
             create_batch,
@@ -70,12 +111,38 @@ pub fn configure_app(cfg: &mut web::ServiceConfig) {
             get_active_count,
             search_members,
             all_members,
+            get_group,
+            get_ldap_cache_stats,
+            clear_ldap_cache,
             create_freshman_user,
+            get_freshman_accounts,
+            get_freshman_account,
+            edit_freshman_account,
+            delete_freshman_account,
+            import_freshmen,
+            get_conversion_proposals,
+            convert_freshmen,
             convert_freshman_user,
+            get_conversions,
+            undo_conversion,
+            get_member_profile,
+            // housing
+            grant_onfloor,
+            revoke_onfloor,
+            add_to_housing_queue,
+            remove_from_housing_queue,
+            get_housing_queue,
+            get_housing_points,
+            get_member_housing_points,
+            // packet
+            get_incomplete_packets,
+            get_packet_settings,
+            set_packet_settings,
+            get_packet_signatures,
             // forms
             get_intro_form_for_user
         ),
-        components(schemas(Seminar, Directorship, CommitteeType, LdapUser, NewIntroMember, FreshmanUpgrade, MemberStatus, IntroStatus)),
+        components(schemas(ErrorBody, Seminar, Directorship, CheckInCode, CheckInSubmission, HouseMeeting, HouseMeetingRoster, MemberHouseMeetingStatus, FreshmanHouseMeetingStatus, HouseMeetingActive, HouseMeetingAttendanceEdit, ExcuseSubmission, ExcuseRequest, ExcuseDecision, ExcuseStatus, CommitteeType, LdapUser, Group, GroupAction, GroupChange, GroupChangeReport, FailedGroupChange, LdapCacheStats, NewIntroMember, FreshmanAccount, FreshmanAccountEdit, FreshmanUpgrade, MemberStatus, IntroStatus, HousingQueueEntry, HousingPoints, HousingPointComponent, HousingPointWeights, HousingPointsRanking, IncompletePacket, PacketSignatures, UpperclassmenSignatures, MiscSignatures, FreshmanIdentity, IdentityIssue, IdentityIssueKind, IdentityReport, FreshmanImportRow, FreshmanImportError, FreshmanImportReport, ConversionProposal, ConversionPair, ConvertedData, ConversionResult, ConversionReport, ConversionLogEntry, ConversionUndo, PacketSettings, PacketRounding, OnFloorDate, MemberProfile, ProfileMajorProject, ProfileConditional, ConditionalStatus, MajorProjectStatus, SpringEvalStatus, CoopSemester)),
 
         tags(
             (name = "Conditional", description = "Conditional Actix API")
@@ -98,6 +165,19 @@ pub fn configure_app(cfg: &mut web::ServiceConfig) {
 
     let openapi = ApiDoc::openapi();
 
+    // Malformed bodies, paths and query strings get the same error body as
+    // every other error
+    cfg.app_data(
+        web::JsonConfig::default().error_handler(|e, _| AppError::BadRequest(e.to_string()).into()),
+    )
+    .app_data(
+        web::PathConfig::default().error_handler(|e, _| AppError::BadRequest(e.to_string()).into()),
+    )
+    .app_data(
+        web::QueryConfig::default()
+            .error_handler(|e, _| AppError::BadRequest(e.to_string()).into()),
+    );
+
     cfg.service(
         scope("/api")
             .service(
@@ -116,15 +196,31 @@ pub fn configure_app(cfg: &mut web::ServiceConfig) {
                     .service(edit_directorship_attendance)
                     // House meeting routes
                     .service(submit_hm_attendance)
+                    .service(start_house_meeting)
                     .service(get_hm_absences_by_user)
                     .service(get_hm_attendance_by_user_evals)
-                    .service(modify_hm_attendance),
+                    .service(modify_hm_attendance)
+                    .service(get_house_meetings)
+                    .service(get_house_meeting)
+                    .service(set_house_meeting_active)
+                    .service(delete_house_meeting)
+                    .service(submit_hm_excuse)
+                    .service(get_pending_hm_excuses)
+                    .service(decide_hm_excuse)
+                    .service(edit_house_meeting_attendee)
+                    .service(add_house_meeting_attendee)
+                    // Check-in routes
+                    .service(open_seminar_checkin)
+                    .service(open_directorship_checkin)
+                    .service(close_checkin)
+                    .service(submit_checkin),
             )
             .service(
                 scope("/evals")
                     // Evals routes
                     .service(get_intro_evals_wrapper)
                     .service(get_member_evals)
+                    .service(get_identity_report)
                     .service(get_conditional)
                     .service(get_gatekeep)
                     .service(
@@ -145,8 +241,39 @@ pub fn configure_app(cfg: &mut web::ServiceConfig) {
                     .service(get_active_count)
                     .service(search_members)
                     .service(all_members)
+                    .service(get_group)
+                    .service(get_ldap_cache_stats)
+                    .service(clear_ldap_cache)
                     .service(create_freshman_user)
-                    .service(convert_freshman_user),
+                    .service(get_freshman_accounts)
+                    .service(get_freshman_account)
+                    .service(edit_freshman_account)
+                    .service(delete_freshman_account)
+                    .service(import_freshmen)
+                    .service(get_conversion_proposals)
+                    .service(convert_freshmen)
+                    .service(convert_freshman_user)
+                    .service(get_conversions)
+                    .service(undo_conversion)
+                    // Must come last so it doesn't shadow the other routes
+                    .service(get_member_profile),
+            )
+            .service(
+                scope("/housing")
+                    .service(grant_onfloor)
+                    .service(revoke_onfloor)
+                    .service(add_to_housing_queue)
+                    .service(remove_from_housing_queue)
+                    .service(get_housing_queue)
+                    .service(get_housing_points)
+                    .service(get_member_housing_points),
+            )
+            .service(
+                scope("/packet")
+                    .service(get_incomplete_packets)
+                    .service(get_packet_settings)
+                    .service(set_packet_settings)
+                    .service(get_packet_signatures),
             )
             .service(scope("/forms").service(get_intro_form_for_user)),
     )
@@ -164,15 +291,10 @@ pub async fn get_app_data() -> Data<AppState> {
         .await
         .expect("Could not connect to database");
     println!("Successfully opened packet db connection");
-    let ldap = LdapClient::new(
-        &env::var("CONDITIONAL_LDAP_BIND_DN")
-            .expect("CONDITIONAL_LDAP_BIND_DN not set")
-            .as_str(),
-        &env::var("CONDITIONAL_LDAP_BIND_PW")
-            .expect("CONDITIONAL_LDAP_BIND_PW not set")
-            .as_str(),
-    )
-    .await;
+    let ldap = directory::from_env()
+        .await
+        .expect("Could not create directory");
+    println!("Successfully created directory");
     Data::new(AppState {
         db: conditional_pool,
         packet_db: packet_pool,
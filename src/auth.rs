@@ -1,4 +1,5 @@
 use crate::app::AppState;
+use crate::error::AppError;
 use actix_web::{
     dev::{Service, ServiceRequest, ServiceResponse, Transform},
     web::Data,
@@ -99,16 +100,16 @@ impl FromRequest for User {
 }
 
 impl User {
-    fn admin(&self) -> bool {
+    pub fn admin(&self) -> bool {
         self.groups.contains(&String::from("/eboard"))
             || self.groups.contains(&String::from("/admins/rtp"))
     }
 
-    fn eboard(&self) -> bool {
+    pub fn eboard(&self) -> bool {
         self.groups.contains(&String::from("/eboard"))
     }
 
-    fn evals(&self) -> bool {
+    pub fn evals(&self) -> bool {
         self.groups.contains(&String::from("/eboard/evals"))
     }
 }
@@ -137,7 +138,7 @@ fn get_token_pieces(token: String) -> Result<(TokenHeader, String, User, String,
         token_header_base64.to_owned(),
         token_payload,
         token_payload_base64.to_owned(),
-        token_signature.into(),
+        token_signature,
     ))
 }
 
@@ -172,7 +173,7 @@ fn verify_token(
         None => return false,
     };
 
-    let mut verifier = Verifier::new(MessageDigest::sha256(), &pkey).unwrap();
+    let mut verifier = Verifier::new(MessageDigest::sha256(), pkey).unwrap();
     verifier.update(header_64.as_bytes());
     verifier.update(b".");
     verifier.update(payload_64.as_bytes());
@@ -201,7 +202,9 @@ where
         let _app_data: &Data<AppState> = req.app_data().unwrap();
         if self.enabled {
             let unauthorized = |req: ServiceRequest| -> Self::Future {
-                Box::pin(async { Ok(req.into_response(HttpResponse::Unauthorized().finish())) })
+                Box::pin(async {
+                    Ok(req.into_response(HttpResponse::from(AppError::Unauthorized)))
+                })
             };
 
             let token = match req.headers().get("Authorization").map(|x| x.to_str()) {
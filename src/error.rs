@@ -0,0 +1,296 @@
+use crate::ldap::client::LdapUnavailable;
+use crate::schema::api::ErrorBody;
+use actix_web::{
+    body::{EitherBody, MessageBody},
+    dev::{Service, ServiceRequest, ServiceResponse, Transform},
+    http::{
+        header::{HeaderName, HeaderValue},
+        StatusCode,
+    },
+    HttpMessage, HttpResponse, ResponseError,
+};
+use futures::future::LocalBoxFuture;
+use log::{log, Level};
+use sqlx::error::ErrorKind;
+use std::{
+    fmt,
+    future::{ready, Ready},
+    task::{Context, Poll},
+};
+
+/// Everything a request can fail with. Every error is sent to clients as an
+/// `ErrorBody`, and anything internal (database and LDAP errors) is only
+/// described in the logs.
+#[derive(Debug)]
+pub enum AppError {
+    BadRequest(String),
+    Unauthorized,
+    NotFound(String),
+    Conflict(String),
+    UnsupportedMediaType(String),
+    Unprocessable(String),
+    Database(sqlx::Error),
+    Ldap(anyhow::Error),
+    LdapUnavailable(anyhow::Error),
+    Internal(String),
+}
+
+impl AppError {
+    /// A stable identifier for the kind of error, for clients to match on
+    #[must_use]
+    pub fn code(&self) -> &'static str {
+        match self {
+            AppError::BadRequest(_) => "bad_request",
+            AppError::Unauthorized => "unauthorized",
+            AppError::NotFound(_) => "not_found",
+            AppError::Conflict(_) => "conflict",
+            AppError::UnsupportedMediaType(_) => "unsupported_media_type",
+            AppError::Unprocessable(_) => "unprocessable",
+            AppError::Database(e) => match database_kind(e) {
+                DatabaseKind::NotFound => "not_found",
+                DatabaseKind::Unique => "conflict",
+                DatabaseKind::ForeignKey => "invalid_reference",
+                DatabaseKind::Invalid => "invalid_value",
+                DatabaseKind::Other => "database_error",
+            },
+            AppError::Ldap(_) => "ldap_error",
+            AppError::LdapUnavailable(_) => "ldap_unavailable",
+            AppError::Internal(_) => "internal_error",
+        }
+    }
+
+    /// What went wrong, safe to show to clients
+    #[must_use]
+    pub fn message(&self) -> String {
+        match self {
+            AppError::BadRequest(message)
+            | AppError::NotFound(message)
+            | AppError::Conflict(message)
+            | AppError::UnsupportedMediaType(message)
+            | AppError::Unprocessable(message) => message.clone(),
+            AppError::Unauthorized => String::from("Unauthorized"),
+            AppError::Database(e) => String::from(match database_kind(e) {
+                DatabaseKind::NotFound => "Not found",
+                DatabaseKind::Unique => "Conflicts with an existing record",
+                DatabaseKind::ForeignKey => {
+                    "Refers to a record that doesn't exist, or is still referred to"
+                }
+                DatabaseKind::Invalid => "A value is missing or out of range",
+                DatabaseKind::Other => "Internal database error",
+            }),
+            AppError::Ldap(_) => String::from("Internal LDAP error"),
+            AppError::LdapUnavailable(_) => String::from("LDAP unavailable"),
+            AppError::Internal(_) => String::from("Internal server error"),
+        }
+    }
+
+    /// The body sent to clients for this error
+    #[must_use]
+    pub fn body(&self, request_id: Option<&str>) -> ErrorBody {
+        ErrorBody {
+            code: self.code().to_string(),
+            message: self.message(),
+            request_id: request_id.map(ToString::to_string),
+        }
+    }
+}
+
+enum DatabaseKind {
+    NotFound,
+    Unique,
+    ForeignKey,
+    Invalid,
+    Other,
+}
+
+fn database_kind(e: &sqlx::Error) -> DatabaseKind {
+    match e {
+        sqlx::Error::RowNotFound => DatabaseKind::NotFound,
+        sqlx::Error::Database(e) => match e.kind() {
+            ErrorKind::UniqueViolation => DatabaseKind::Unique,
+            ErrorKind::ForeignKeyViolation => DatabaseKind::ForeignKey,
+            ErrorKind::NotNullViolation | ErrorKind::CheckViolation => DatabaseKind::Invalid,
+            _ => DatabaseKind::Other,
+        },
+        _ => DatabaseKind::Other,
+    }
+}
+
+/// Describes the error in full, internal details included. Only for logs.
+impl fmt::Display for AppError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AppError::Database(e) => write!(f, "DB error: {e}"),
+            AppError::Ldap(e) | AppError::LdapUnavailable(e) => write!(f, "LDAP error: {e}"),
+            AppError::Internal(detail) => write!(f, "Internal error: {detail}"),
+            _ => write!(f, "{}", self.message()),
+        }
+    }
+}
+
+impl ResponseError for AppError {
+    fn status_code(&self) -> StatusCode {
+        match self {
+            AppError::BadRequest(_) => StatusCode::BAD_REQUEST,
+            AppError::Unauthorized => StatusCode::UNAUTHORIZED,
+            AppError::NotFound(_) => StatusCode::NOT_FOUND,
+            AppError::Conflict(_) => StatusCode::CONFLICT,
+            AppError::UnsupportedMediaType(_) => StatusCode::UNSUPPORTED_MEDIA_TYPE,
+            AppError::Unprocessable(_) => StatusCode::UNPROCESSABLE_ENTITY,
+            AppError::Database(e) => match database_kind(e) {
+                DatabaseKind::NotFound => StatusCode::NOT_FOUND,
+                DatabaseKind::Unique => StatusCode::CONFLICT,
+                DatabaseKind::ForeignKey | DatabaseKind::Invalid => {
+                    StatusCode::UNPROCESSABLE_ENTITY
+                }
+                DatabaseKind::Other => StatusCode::INTERNAL_SERVER_ERROR,
+            },
+            AppError::Ldap(_) | AppError::Internal(_) => StatusCode::INTERNAL_SERVER_ERROR,
+            AppError::LdapUnavailable(_) => StatusCode::SERVICE_UNAVAILABLE,
+        }
+    }
+
+    fn error_response(&self) -> HttpResponse {
+        HttpResponse::build(self.status_code()).json(self.body(None))
+    }
+}
+
+impl From<sqlx::Error> for AppError {
+    fn from(e: sqlx::Error) -> Self {
+        AppError::Database(e)
+    }
+}
+
+/// An LDAP error, which is a 503 if LDAP couldn't be reached or a 500
+/// otherwise
+pub fn ldap_error(e: anyhow::Error) -> AppError {
+    if e.is::<LdapUnavailable>() {
+        log!(Level::Error, "{}", e);
+        AppError::LdapUnavailable(e)
+    } else {
+        log!(Level::Warn, "LDAP Query failed: {}", e);
+        AppError::Ldap(e)
+    }
+}
+
+pub fn log_query_as<T>(query: Result<Vec<T>, sqlx::Error>) -> Result<Vec<T>, AppError> {
+    query.map_err(|e| {
+        log!(Level::Warn, "DB Query failed: {}", e);
+        AppError::Database(e)
+    })
+}
+
+pub fn log_query(query: Result<(), sqlx::Error>) -> Result<(), AppError> {
+    query.map_err(|e| {
+        log!(Level::Warn, "DB Query failed: {}", e);
+        AppError::Database(e)
+    })
+}
+
+/// Responds with the error, keeping it attached to the response so
+/// `RequestId` can add the request id to it
+impl From<AppError> for HttpResponse {
+    fn from(e: AppError) -> Self {
+        HttpResponse::from_error(e)
+    }
+}
+
+static REQUEST_ID_HEADER: HeaderName = HeaderName::from_static("x-request-id");
+
+/// Id of the request being handled, as sent back in `X-Request-Id`
+#[derive(Clone, Debug)]
+pub struct RequestIdValue(pub String);
+
+/// Gives every request an id, taken from its `X-Request-Id` header if it has a
+/// sensible one. The id is sent back in `X-Request-Id`, added to the body of
+/// any `AppError` and logged with the error's internal details.
+pub struct RequestId;
+
+impl<S, B> Transform<S, ServiceRequest> for RequestId
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = actix_web::Error>,
+    S::Future: 'static,
+    B: MessageBody + 'static,
+{
+    type Response = ServiceResponse<EitherBody<B>>;
+    type Error = actix_web::Error;
+    type Transform = RequestIdService<S>;
+    type InitError = ();
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(RequestIdService { service }))
+    }
+}
+
+pub struct RequestIdService<S> {
+    service: S,
+}
+
+fn request_id(req: &ServiceRequest) -> String {
+    match req
+        .headers()
+        .get(&REQUEST_ID_HEADER)
+        .and_then(|id| id.to_str().ok())
+    {
+        Some(id)
+            if !id.is_empty()
+                && id.len() <= 64
+                && id
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_') =>
+        {
+            id.to_string()
+        }
+        _ => format!("{:016x}", rand::random::<u64>()),
+    }
+}
+
+impl<S, B> Service<ServiceRequest> for RequestIdService<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = actix_web::Error>,
+    S::Future: 'static,
+    B: MessageBody + 'static,
+{
+    type Response = ServiceResponse<EitherBody<B>>;
+    type Error = actix_web::Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(&self, ctx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.service.poll_ready(ctx)
+    }
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        let id = request_id(&req);
+        req.extensions_mut().insert(RequestIdValue(id.clone()));
+        let future = self.service.call(req);
+        Box::pin(async move {
+            let response = future.await?;
+            let error = response
+                .response()
+                .error()
+                .and_then(|e| e.as_error::<AppError>())
+                .map(|e| (e.status_code(), e.to_string(), e.body(Some(&id))));
+            let mut response = match error {
+                Some((status, detail, body)) => {
+                    let level = if status.is_server_error() {
+                        Level::Error
+                    } else {
+                        Level::Debug
+                    };
+                    log!(level, "Request {id} failed with {status}: {detail}");
+                    response
+                        .into_response(HttpResponse::build(status).json(body))
+                        .map_into_right_body()
+                }
+                None => response.map_into_left_body(),
+            };
+            if let Ok(value) = HeaderValue::from_str(&id) {
+                response
+                    .headers_mut()
+                    .insert(REQUEST_ID_HEADER.clone(), value);
+            }
+            Ok(response)
+        })
+    }
+}
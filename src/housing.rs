@@ -0,0 +1,137 @@
+use crate::schema::api::{HousingPointComponent, HousingPointWeights, HousingPoints};
+use crate::schema::db::CoopSemester;
+use chrono::{Datelike, NaiveDate, NaiveDateTime};
+use lazy_static::lazy_static;
+use std::env;
+
+lazy_static! {
+    pub static ref HOUSING_WEIGHTS: HousingPointWeights = HousingPointWeights::from_env();
+}
+
+impl Default for HousingPointWeights {
+    fn default() -> Self {
+        HousingPointWeights {
+            semester_onfloor: 2,
+            coop_semester: -2,
+            passed_eval: 1,
+            seminar: 0,
+            directorship: 0,
+            missed_hm: -1,
+        }
+    }
+}
+
+impl HousingPointWeights {
+    /// Weights from `HOUSING_WEIGHT_*` environment variables, falling back to
+    /// the defaults for any that are unset or invalid
+    #[must_use]
+    pub fn from_env() -> Self {
+        let default = HousingPointWeights::default();
+        let weight = |var: &str, default: i64| {
+            env::var(var)
+                .ok()
+                .and_then(|w| w.parse().ok())
+                .unwrap_or(default)
+        };
+        HousingPointWeights {
+            semester_onfloor: weight("HOUSING_WEIGHT_SEMESTER_ONFLOOR", default.semester_onfloor),
+            coop_semester: weight("HOUSING_WEIGHT_COOP_SEMESTER", default.coop_semester),
+            passed_eval: weight("HOUSING_WEIGHT_PASSED_EVAL", default.passed_eval),
+            seminar: weight("HOUSING_WEIGHT_SEMINAR", default.seminar),
+            directorship: weight("HOUSING_WEIGHT_DIRECTORSHIP", default.directorship),
+            missed_hm: weight("HOUSING_WEIGHT_MISSED_HM", default.missed_hm),
+        }
+    }
+}
+
+/// Everything housing points are computed from for one member
+#[derive(Clone, Debug)]
+pub struct HousingPointInputs {
+    pub uid: String,
+    pub onfloor_granted: Option<NaiveDateTime>,
+    /// Co-op semesters since the member was granted on floor status
+    pub coop_semesters: i64,
+    /// Spring evals the member has passed, in any year
+    pub passed_evals: i64,
+    /// Seminars attended this year
+    pub seminars: i64,
+    /// Directorships attended this year
+    pub directorships: i64,
+    /// House meetings missed this year
+    pub missed_hms: i64,
+}
+
+/// Index of the semester containing `date`
+fn semester_index(date: NaiveDate) -> i64 {
+    i64::from(date.year()) * 2 + i64::from(CoopSemester::containing(&date) == CoopSemester::Fall)
+}
+
+/// Number of semesters from the one containing `granted` through the one
+/// containing `today`, inclusive
+#[must_use]
+pub fn semesters_onfloor(granted: Option<NaiveDateTime>, today: NaiveDate) -> i64 {
+    match granted {
+        Some(granted) => (semester_index(today) - semester_index(granted.date()) + 1).max(0),
+        None => 0,
+    }
+}
+
+/// Compute a member's housing points, keeping each term of the sum so the
+/// result can be explained
+#[must_use]
+pub fn compute(
+    inputs: &HousingPointInputs,
+    weights: &HousingPointWeights,
+    today: NaiveDate,
+) -> HousingPoints {
+    let component = |source: &str, count: i64, weight: i64| HousingPointComponent {
+        source: source.to_string(),
+        count,
+        weight,
+        points: count * weight,
+    };
+    let components = vec![
+        component(
+            "semesters_onfloor",
+            semesters_onfloor(inputs.onfloor_granted, today),
+            weights.semester_onfloor,
+        ),
+        component(
+            "coop_semesters",
+            inputs.coop_semesters,
+            weights.coop_semester,
+        ),
+        component("passed_evals", inputs.passed_evals, weights.passed_eval),
+        component("seminars", inputs.seminars, weights.seminar),
+        component("directorships", inputs.directorships, weights.directorship),
+        component("missed_hms", inputs.missed_hms, weights.missed_hm),
+    ];
+    HousingPoints {
+        uid: inputs.uid.clone(),
+        name: None,
+        rank: None,
+        onfloor_granted: inputs.onfloor_granted,
+        total: components.iter().map(|c| c.points).sum(),
+        components,
+    }
+}
+
+/// Rank members by total points, breaking ties by whoever has been on floor
+/// longest
+#[must_use]
+pub fn rank(mut points: Vec<HousingPoints>) -> Vec<HousingPoints> {
+    points.sort_by(|a, b| {
+        b.total
+            .cmp(&a.total)
+            .then_with(|| match (a.onfloor_granted, b.onfloor_granted) {
+                (Some(a), Some(b)) => a.cmp(&b),
+                (Some(_), None) => std::cmp::Ordering::Less,
+                (None, Some(_)) => std::cmp::Ordering::Greater,
+                (None, None) => std::cmp::Ordering::Equal,
+            })
+    });
+    for (i, p) in points.iter_mut().enumerate() {
+        p.rank = Some(i + 1);
+    }
+    points
+}
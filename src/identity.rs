@@ -0,0 +1,228 @@
+use crate::error::{log_query_as, AppError};
+use crate::ldap::user::LdapUser;
+use crate::packet::PacketReport;
+use crate::schema::api::{FreshmanIdentity, IdentityIssue, IdentityIssueKind, IdentityReport};
+use chrono::NaiveDateTime;
+use sqlx::{query_as, Pool, Postgres};
+use std::collections::BTreeMap;
+
+/// The key freshmen are matched on across packet, conditional and LDAP. RIT
+/// usernames are trimmed and lowercased, and LDAP's `ritDn` is reduced to
+/// the username it names (`uid=abc1234,ou=People,...` becomes `abc1234`).
+/// Returns None if there's no username left.
+#[must_use]
+pub fn rit_username_key(raw: &str) -> Option<String> {
+    let username = username_part(raw).trim().to_lowercase();
+    if username.is_empty() {
+        None
+    } else {
+        Some(username)
+    }
+}
+
+/// The part of `raw` holding the username, as it was written
+fn username_part(raw: &str) -> &str {
+    match raw.split_once('=') {
+        Some((attr, rest)) if attr.trim().eq_ignore_ascii_case("uid") => {
+            rest.split(',').next().unwrap_or_default()
+        }
+        _ => raw,
+    }
+}
+
+/// The identifying parts of a row in 'freshman_accounts'
+pub struct FreshmanAccountIdentity {
+    pub fid: i32,
+    pub name: String,
+    pub rit_username: Option<String>,
+}
+
+pub async fn get_freshman_account_identities(
+    db: &Pool<Postgres>,
+) -> Result<Vec<FreshmanAccountIdentity>, AppError> {
+    log_query_as(
+        query_as!(
+            FreshmanAccountIdentity,
+            "SELECT id AS fid, name, rit_username FROM freshman_accounts ORDER BY id"
+        )
+        .fetch_all(db)
+        .await,
+    )
+}
+
+#[derive(Default)]
+struct Linked<'a> {
+    accounts: Vec<&'a FreshmanAccountIdentity>,
+    members: Vec<&'a LdapUser>,
+    /// Name and start of each packet
+    packets: Vec<(&'a str, Option<NaiveDateTime>)>,
+    raw: Vec<&'a str>,
+}
+
+fn issue(
+    kind: IdentityIssueKind,
+    rit_username: Option<&str>,
+    fids: Vec<i32>,
+    uids: Vec<String>,
+    detail: String,
+) -> IdentityIssue {
+    IdentityIssue {
+        kind,
+        rit_username: rit_username.map(ToString::to_string),
+        fids,
+        uids,
+        detail,
+    }
+}
+
+/// Link freshman accounts, intro members and packets by RIT username, and
+/// report everyone who can't be linked cleanly. Only packets started after
+/// `year_start` are reported as orphaned, since packets from past years
+/// belong to people who are members now.
+#[must_use]
+pub fn reconcile(
+    accounts: &[FreshmanAccountIdentity],
+    intro_members: &[LdapUser],
+    packets: &PacketReport,
+    year_start: &NaiveDateTime,
+) -> IdentityReport {
+    let mut linked: BTreeMap<String, Linked> = BTreeMap::new();
+    let mut issues = Vec::new();
+
+    for account in accounts {
+        match account.rit_username.as_deref().and_then(rit_username_key) {
+            Some(key) => {
+                let entry = linked.entry(key).or_default();
+                entry.accounts.push(account);
+                entry
+                    .raw
+                    .extend(account.rit_username.as_deref().map(username_part));
+            }
+            None => issues.push(issue(
+                IdentityIssueKind::MissingRitUsername,
+                None,
+                vec![account.fid],
+                vec![],
+                format!(
+                    "Freshman account {} ({}) has no RIT username",
+                    account.fid, account.name
+                ),
+            )),
+        }
+    }
+    for member in intro_members {
+        match rit_username_key(&member.rit_username) {
+            Some(key) => {
+                let entry = linked.entry(key).or_default();
+                entry.members.push(member);
+                entry.raw.push(username_part(&member.rit_username));
+            }
+            None => issues.push(issue(
+                IdentityIssueKind::MissingRitUsername,
+                None,
+                vec![],
+                vec![member.uid.clone()],
+                format!(
+                    "Intro member {} ({}) has no RIT username",
+                    member.uid, member.cn
+                ),
+            )),
+        }
+    }
+    for packet in &packets.packets {
+        if let Some(key) = rit_username_key(&packet.username) {
+            let entry = linked.entry(key).or_default();
+            entry.packets.push((&packet.name, packet.start));
+            entry.raw.push(username_part(&packet.username));
+        }
+    }
+    for incomplete in &packets.incomplete {
+        issues.push(issue(
+            IdentityIssueKind::IncompletePacket,
+            incomplete.username.as_deref(),
+            vec![],
+            vec![],
+            format!(
+                "Packet {} is missing {}",
+                incomplete.packet_id,
+                incomplete.missing.join(", ")
+            ),
+        ));
+    }
+
+    let mut identities = Vec::with_capacity(linked.len());
+    for (key, entry) in &linked {
+        let fids: Vec<i32> = entry.accounts.iter().map(|a| a.fid).collect();
+        let uids: Vec<String> = entry.members.iter().map(|m| m.uid.clone()).collect();
+        let has_person = !fids.is_empty() || !uids.is_empty();
+        let mut push = |kind, detail| {
+            issues.push(issue(kind, Some(key), fids.clone(), uids.clone(), detail));
+        };
+
+        if entry.packets.is_empty() {
+            push(
+                IdentityIssueKind::MissingPacket,
+                format!("No packet for {key}"),
+            );
+        } else if !has_person {
+            if entry
+                .packets
+                .iter()
+                .any(|(_, start)| start.is_none_or(|start| start >= *year_start))
+            {
+                push(
+                    IdentityIssueKind::OrphanedPacket,
+                    format!(
+                        "Packet for {key} doesn't belong to any freshman account or intro member"
+                    ),
+                );
+            } else {
+                // Packets from past years are expected to outlive the people
+                // they belonged to being intro members
+                continue;
+            }
+        }
+        if fids.len() > 1 {
+            push(
+                IdentityIssueKind::DuplicateAccount,
+                format!("{} freshman accounts share RIT username {key}", fids.len()),
+            );
+        }
+        if uids.len() > 1 {
+            push(
+                IdentityIssueKind::DuplicateAccount,
+                format!("{} intro members share RIT username {key}", uids.len()),
+            );
+        }
+        if !fids.is_empty() && !uids.is_empty() {
+            push(
+                IdentityIssueKind::UnconvertedAccount,
+                format!("{key} has both a freshman account and a CSH account"),
+            );
+        }
+        if has_person && entry.raw.iter().any(|raw| *raw != key) {
+            let mut forms: Vec<String> = entry.raw.iter().map(|raw| format!("{raw:?}")).collect();
+            forms.sort();
+            forms.dedup();
+            push(
+                IdentityIssueKind::InexactMatch,
+                format!("Matched {} as {key}", forms.join(", ")),
+            );
+        }
+
+        identities.push(FreshmanIdentity {
+            rit_username: key.clone(),
+            name: entry
+                .packets
+                .first()
+                .map(|(name, _)| name.to_string())
+                .or_else(|| entry.accounts.first().map(|a| a.name.clone()))
+                .or_else(|| entry.members.first().map(|m| m.cn.clone())),
+            fids,
+            uids,
+            has_packet: !entry.packets.is_empty(),
+        });
+    }
+
+    IdentityReport { identities, issues }
+}
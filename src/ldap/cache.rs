@@ -0,0 +1,148 @@
+use super::{group::Group, search::SearchAttrs, user::LdapUser};
+use log::{log, Level};
+use serde::Serialize;
+use std::{
+    collections::HashMap,
+    env, fmt,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Mutex,
+    },
+    time::{Duration, Instant},
+};
+use utoipa::ToSchema;
+
+/// A cacheable LDAP lookup. Results are cached separately for each set of
+/// attributes they were fetched with.
+pub enum CacheKey {
+    Group(Group),
+    ActiveUpperclassmen,
+    User(String),
+    Users(Vec<String>),
+    Search(String),
+}
+
+impl fmt::Display for CacheKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CacheKey::Group(group) => write!(f, "group:{}", group.dn()),
+            CacheKey::ActiveUpperclassmen => write!(f, "active_upperclassmen"),
+            CacheKey::User(uid) => write!(f, "user:{}", uid),
+            CacheKey::Users(uids) => write!(f, "users:{}", uids.join(",")),
+            CacheKey::Search(query) => write!(f, "search:{}", query),
+        }
+    }
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct LdapCacheStats {
+    /// Lookups answered from the cache
+    pub hits: u64,
+    /// Lookups that had to go to LDAP
+    pub misses: u64,
+    /// Number of cached lookups, including expired ones not yet evicted
+    pub entries: usize,
+}
+
+/// In-process cache of LDAP lookups. Group lookups and user lookups have
+/// separate TTLs, set in seconds with `LDAP_CACHE_GROUP_TTL` (default 300)
+/// and `LDAP_CACHE_USER_TTL` (default 60). A TTL of 0 disables caching for
+/// that kind of lookup.
+pub struct LdapCache {
+    entries: Mutex<HashMap<String, (Instant, Vec<LdapUser>)>>,
+    group_ttl: Duration,
+    user_ttl: Duration,
+    hits: AtomicU64,
+    misses: AtomicU64,
+}
+
+impl LdapCache {
+    #[must_use]
+    pub fn from_env() -> Self {
+        LdapCache::new(
+            Duration::from_secs(ttl_from_env("LDAP_CACHE_GROUP_TTL", 300)),
+            Duration::from_secs(ttl_from_env("LDAP_CACHE_USER_TTL", 60)),
+        )
+    }
+
+    #[must_use]
+    pub fn new(group_ttl: Duration, user_ttl: Duration) -> Self {
+        LdapCache {
+            entries: Mutex::new(HashMap::new()),
+            group_ttl,
+            user_ttl,
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+        }
+    }
+
+    fn ttl(&self, key: &CacheKey) -> Duration {
+        match key {
+            CacheKey::Group(_) | CacheKey::ActiveUpperclassmen => self.group_ttl,
+            CacheKey::User(_) | CacheKey::Users(_) | CacheKey::Search(_) => self.user_ttl,
+        }
+    }
+
+    /// The cached result of a lookup, if it hasn't expired
+    pub fn get(&self, key: &CacheKey, attrs: &SearchAttrs) -> Option<Vec<LdapUser>> {
+        let key = format!("{key}|{attrs}");
+        let cached = self.entries.lock().ok().and_then(|entries| {
+            entries
+                .get(&key)
+                .filter(|(expires, _)| Instant::now() < *expires)
+                .map(|(_, users)| users.clone())
+        });
+        if cached.is_some() {
+            self.hits.fetch_add(1, Ordering::Relaxed);
+            log!(Level::Debug, "LDAP cache hit for {key}");
+        } else {
+            self.misses.fetch_add(1, Ordering::Relaxed);
+            log!(Level::Debug, "LDAP cache miss for {key}");
+        }
+        cached
+    }
+
+    pub fn insert(&self, key: &CacheKey, attrs: &SearchAttrs, users: &[LdapUser]) {
+        let ttl = self.ttl(key);
+        if ttl.is_zero() {
+            return;
+        }
+        if let Ok(mut entries) = self.entries.lock() {
+            let now = Instant::now();
+            // Searches are keyed by arbitrary user input, so drop anything
+            // expired to keep the cache from growing without bound
+            entries.retain(|_, (expires, _)| now < *expires);
+            entries.insert(format!("{key}|{attrs}"), (now + ttl, users.to_vec()));
+        }
+    }
+
+    pub fn invalidate(&self, key: &CacheKey) {
+        let prefix = format!("{key}|");
+        if let Ok(mut entries) = self.entries.lock() {
+            entries.retain(|k, _| !k.starts_with(&prefix));
+        }
+    }
+
+    /// Drop every cached lookup, e.g. after a write that changes group
+    /// membership
+    pub fn invalidate_all(&self) {
+        if let Ok(mut entries) = self.entries.lock() {
+            entries.clear();
+        }
+    }
+
+    pub fn stats(&self) -> LdapCacheStats {
+        LdapCacheStats {
+            hits: self.hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
+            entries: self.entries.lock().map(|e| e.len()).unwrap_or_default(),
+        }
+    }
+}
+
+fn ttl_from_env(var: &str, default: u64) -> u64 {
+    env::var(var)
+        .ok()
+        .and_then(|ttl| ttl.parse().ok())
+        .unwrap_or(default)
+}
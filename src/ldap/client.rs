@@ -1,11 +1,15 @@
 #![allow(unused)]
 
+use super::cache::LdapCache;
 use async_trait::async_trait;
-use deadpool::managed::{self, Metrics};
-use ldap3::{drive, Ldap, LdapConnAsync, LdapError};
-use rand::prelude::SliceRandom;
-use rand::SeedableRng;
-use std::sync::Arc;
+use deadpool::managed::{self, Metrics, PoolError};
+use ldap3::{drive, Ldap, LdapConnAsync, LdapConnSettings, LdapError};
+use log::{log, Level};
+use std::{
+    env, fmt, io,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
 use trust_dns_resolver::{
     config::{ResolverConfig, ResolverOpts},
     AsyncResolver,
@@ -13,27 +17,121 @@ use trust_dns_resolver::{
 
 type Pool = managed::Pool<LdapManager>;
 
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(5);
+const BIND_TIMEOUT: Duration = Duration::from_secs(5);
+/// Backoff after a server's first failure, doubled for each consecutive one
+const BASE_BACKOFF: Duration = Duration::from_secs(5);
+const MAX_BACKOFF: Duration = Duration::from_secs(300);
+
 #[derive(Clone)]
 pub struct LdapClient {
     pub(super) ldap: Arc<Pool>,
+    pub cache: Arc<LdapCache>,
+}
+
+/// Returned when no LDAP server could be reached, so callers can respond
+/// with a 503 rather than a generic 500
+#[derive(Debug)]
+pub struct LdapUnavailable(pub String);
+
+impl fmt::Display for LdapUnavailable {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "LDAP unavailable: {}", self.0)
+    }
+}
+
+impl std::error::Error for LdapUnavailable {}
+
+impl From<PoolError<LdapError>> for LdapUnavailable {
+    fn from(e: PoolError<LdapError>) -> Self {
+        LdapUnavailable(e.to_string())
+    }
+}
+
+#[derive(Default)]
+struct ServerHealth {
+    failures: u32,
+    retry_after: Option<Instant>,
+}
+
+struct LdapServer {
+    url: String,
+    health: Mutex<ServerHealth>,
+}
+
+impl LdapServer {
+    fn new(url: String) -> Self {
+        LdapServer {
+            url,
+            health: Mutex::new(ServerHealth::default()),
+        }
+    }
+
+    /// Whether this server is not currently backing off after a failure
+    fn available(&self) -> bool {
+        match self.health.lock() {
+            Ok(health) => health.retry_after.is_none_or(|t| Instant::now() >= t),
+            Err(_) => true,
+        }
+    }
+
+    fn mark_healthy(&self) {
+        if let Ok(mut health) = self.health.lock() {
+            if health.failures > 0 {
+                log!(Level::Info, "LDAP server {} recovered", self.url);
+            }
+            *health = ServerHealth::default();
+        }
+    }
+
+    fn mark_failed(&self) {
+        if let Ok(mut health) = self.health.lock() {
+            health.failures = health.failures.saturating_add(1);
+            let backoff = BASE_BACKOFF
+                .saturating_mul(2u32.saturating_pow(health.failures - 1))
+                .min(MAX_BACKOFF);
+            health.retry_after = Some(Instant::now() + backoff);
+            log!(
+                Level::Warn,
+                "LDAP server {} failed {} time(s), backing off for {}s",
+                self.url,
+                health.failures,
+                backoff.as_secs()
+            );
+        }
+    }
 }
 
-#[derive(Clone)]
 pub(super) struct LdapManager {
-    ldap_servers: Vec<String>,
+    ldap_servers: Vec<LdapServer>,
     bind_dn: String,
     bind_pw: String,
 }
 
 impl LdapManager {
-    pub async fn new(bind_dn: &str, bind_pw: &str) -> Self {
-        let ldap_servers = get_ldap_servers().await;
+    pub async fn new(bind_dn: &str, bind_pw: &str) -> Result<Self, anyhow::Error> {
+        let ldap_servers = get_ldap_servers().await?;
+        log!(Level::Info, "Using LDAP servers {:?}", ldap_servers);
 
-        LdapManager {
-            ldap_servers,
+        Ok(LdapManager {
+            ldap_servers: ldap_servers.into_iter().map(LdapServer::new).collect(),
             bind_dn: bind_dn.to_string(),
             bind_pw: bind_pw.to_string(),
-        }
+        })
+    }
+
+    async fn connect(&self, server: &LdapServer) -> Result<Ldap, LdapError> {
+        let settings = LdapConnSettings::new().set_conn_timeout(CONNECT_TIMEOUT);
+        let (conn, mut ldap) = LdapConnAsync::with_settings(settings, &server.url).await?;
+
+        drive!(conn);
+
+        ldap.with_timeout(BIND_TIMEOUT)
+            .simple_bind(&self.bind_dn, &self.bind_pw)
+            .await?
+            .success()?;
+
+        Ok(ldap)
     }
 }
 
@@ -43,21 +141,30 @@ impl managed::Manager for LdapManager {
     type Error = LdapError;
 
     async fn create(&self) -> Result<Self::Type, Self::Error> {
-        let (conn, mut ldap) = LdapConnAsync::new(
-            self.ldap_servers
-                .choose(&mut rand::rngs::StdRng::from_entropy())
-                .unwrap(),
-        )
-        .await
-        .unwrap();
-
-        drive!(conn);
-
-        ldap.simple_bind(&self.bind_dn, &self.bind_pw)
-            .await
-            .unwrap();
+        // Servers are tried in order, skipping any that are backing off. If
+        // every server is backing off, try them all anyway rather than fail
+        // without making an attempt.
+        let (ready, backing_off): (Vec<&LdapServer>, Vec<&LdapServer>) =
+            self.ldap_servers.iter().partition(|s| s.available());
+
+        let mut last_error = None;
+        for server in ready.into_iter().chain(backing_off) {
+            match self.connect(server).await {
+                Ok(ldap) => {
+                    server.mark_healthy();
+                    return Ok(ldap);
+                }
+                Err(e) => {
+                    log!(Level::Warn, "Failed to connect to {}: {}", server.url, e);
+                    server.mark_failed();
+                    last_error = Some(e);
+                }
+            }
+        }
 
-        Ok(ldap)
+        Err(last_error.unwrap_or_else(|| LdapError::Io {
+            source: io::Error::new(io::ErrorKind::NotFound, "No LDAP servers configured"),
+        }))
     }
 
     async fn recycle(
@@ -65,28 +172,46 @@ impl managed::Manager for LdapManager {
         ldap: &mut Self::Type,
         _: &Metrics,
     ) -> managed::RecycleResult<Self::Error> {
-        ldap.extended(ldap3::exop::WhoAmI).await?;
+        ldap.with_timeout(BIND_TIMEOUT)
+            .extended(ldap3::exop::WhoAmI)
+            .await?;
         Ok(())
     }
 }
 
 impl LdapClient {
-    pub async fn new(bind_dn: &str, bind_pw: &str) -> Self {
-        let ldap_manager = LdapManager::new(bind_dn, bind_pw).await;
-        let ldap_pool = Pool::builder(ldap_manager).max_size(5).build().unwrap();
+    pub async fn new(bind_dn: &str, bind_pw: &str) -> Result<Self, anyhow::Error> {
+        let ldap_manager = LdapManager::new(bind_dn, bind_pw).await?;
+        let ldap_pool = Pool::builder(ldap_manager).max_size(5).build()?;
 
-        LdapClient {
+        Ok(LdapClient {
             ldap: Arc::new(ldap_pool),
-        }
+            cache: Arc::new(LdapCache::from_env()),
+        })
     }
 }
 
-async fn get_ldap_servers() -> Vec<String> {
+/// Servers from the comma separated `LDAP_SERVERS` variable if it is set,
+/// otherwise from the `_ldap._tcp.csh.rit.edu` SRV record, ordered by
+/// priority and then weight
+async fn get_ldap_servers() -> Result<Vec<String>, anyhow::Error> {
+    let servers: Vec<String> = env::var("LDAP_SERVERS")
+        .unwrap_or_default()
+        .split(',')
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+        .map(str::to_string)
+        .collect();
+    if !servers.is_empty() {
+        return Ok(servers);
+    }
+
     let resolver = AsyncResolver::tokio(ResolverConfig::default(), ResolverOpts::default());
-    let response = resolver.srv_lookup("_ldap._tcp.csh.rit.edu").await.unwrap();
+    let response = resolver.srv_lookup("_ldap._tcp.csh.rit.edu").await?;
 
-    // TODO: Make sure servers are working
-    response
+    let mut records: Vec<_> = response.iter().collect();
+    records.sort_by_key(|record| (record.priority(), std::cmp::Reverse(record.weight())));
+    let servers: Vec<String> = records
         .iter()
         .map(|record| {
             format!(
@@ -94,5 +219,9 @@ async fn get_ldap_servers() -> Vec<String> {
                 record.target().to_string().trim_end_matches('.')
             )
         })
-        .collect()
+        .collect();
+    if servers.is_empty() {
+        return Err(anyhow::anyhow!("No LDAP servers found in SRV record"));
+    }
+    Ok(servers)
 }
@@ -0,0 +1,256 @@
+use super::{
+    cache::LdapCache,
+    client::LdapClient,
+    group::Group,
+    membership::{GroupAction, GroupChange},
+    search::SearchAttrs,
+    user::LdapUser,
+};
+use async_trait::async_trait;
+use std::{
+    env, fs,
+    sync::{Arc, RwLock},
+};
+
+/// A source of CSH member accounts. `LdapClient` talks to the real
+/// directory, while `MemoryDirectory` serves a fixed set of users so the API
+/// can run without network access. Every lookup only fills in the optional
+/// `LdapUser` fields named in `attrs`.
+#[async_trait]
+pub trait Directory: Send + Sync {
+    /// Users whose uid exactly matches `uid`
+    async fn get_user(
+        &self,
+        uid: &str,
+        attrs: &SearchAttrs,
+    ) -> Result<Vec<LdapUser>, anyhow::Error>;
+
+    /// Users whose uid is one of `uids`, in no particular order
+    async fn get_users(
+        &self,
+        uids: &[String],
+        attrs: &SearchAttrs,
+    ) -> Result<Vec<LdapUser>, anyhow::Error>;
+
+    /// Users whose uid or name contains `query`
+    async fn search_users(
+        &self,
+        query: &str,
+        attrs: &SearchAttrs,
+    ) -> Result<Vec<LdapUser>, anyhow::Error>;
+
+    async fn get_group_members(
+        &self,
+        group: Group,
+        attrs: &SearchAttrs,
+    ) -> Result<Vec<LdapUser>, anyhow::Error>;
+
+    /// Active members who are not intro members
+    async fn get_active_upperclassmen(
+        &self,
+        attrs: &SearchAttrs,
+    ) -> Result<Vec<LdapUser>, anyhow::Error>;
+
+    async fn get_intro_members(&self, attrs: &SearchAttrs) -> Result<Vec<LdapUser>, anyhow::Error> {
+        self.get_group_members(Group::IntroMembers, attrs).await
+    }
+
+    /// Add a user to or remove them from a group. Applying a change that is
+    /// already in effect succeeds.
+    async fn apply_group_change(&self, change: &GroupChange) -> Result<(), anyhow::Error>;
+
+    /// The lookup cache in front of this directory, if it has one
+    fn cache(&self) -> Option<&LdapCache> {
+        None
+    }
+
+    /// Drop any cached lookups, e.g. after a write that changes group
+    /// membership
+    fn invalidate_cache(&self) {
+        if let Some(cache) = self.cache() {
+            cache.invalidate_all();
+        }
+    }
+}
+
+#[async_trait]
+impl Directory for LdapClient {
+    async fn get_user(
+        &self,
+        uid: &str,
+        attrs: &SearchAttrs,
+    ) -> Result<Vec<LdapUser>, anyhow::Error> {
+        super::get_user(self, uid, attrs).await
+    }
+
+    async fn get_users(
+        &self,
+        uids: &[String],
+        attrs: &SearchAttrs,
+    ) -> Result<Vec<LdapUser>, anyhow::Error> {
+        super::get_users(self, uids, attrs).await
+    }
+
+    async fn search_users(
+        &self,
+        query: &str,
+        attrs: &SearchAttrs,
+    ) -> Result<Vec<LdapUser>, anyhow::Error> {
+        super::search_users(self, query, attrs).await
+    }
+
+    async fn get_group_members(
+        &self,
+        group: Group,
+        attrs: &SearchAttrs,
+    ) -> Result<Vec<LdapUser>, anyhow::Error> {
+        super::get_group_members(self, group, attrs).await
+    }
+
+    async fn get_active_upperclassmen(
+        &self,
+        attrs: &SearchAttrs,
+    ) -> Result<Vec<LdapUser>, anyhow::Error> {
+        super::get_active_upperclassmen(self, attrs).await
+    }
+
+    async fn apply_group_change(&self, change: &GroupChange) -> Result<(), anyhow::Error> {
+        super::modify_group(self, change).await
+    }
+
+    fn cache(&self) -> Option<&LdapCache> {
+        Some(&self.cache)
+    }
+}
+
+/// A directory held entirely in memory. Group membership comes from each
+/// user's `groups`, which hold group cns such as `active`.
+#[derive(Default)]
+pub struct MemoryDirectory {
+    users: RwLock<Vec<LdapUser>>,
+}
+
+impl MemoryDirectory {
+    #[must_use]
+    pub fn new(users: Vec<LdapUser>) -> Self {
+        MemoryDirectory {
+            users: RwLock::new(users),
+        }
+    }
+
+    /// Load users from a JSON file containing a list of `LdapUser`s
+    pub fn from_file(path: &str) -> Result<Self, anyhow::Error> {
+        let users = serde_json::from_str(&fs::read_to_string(path)?)?;
+        Ok(MemoryDirectory::new(users))
+    }
+
+    fn in_group(user: &LdapUser, group: Group) -> bool {
+        user.groups.contains(&group.to_string())
+    }
+
+    fn find(
+        &self,
+        attrs: &SearchAttrs,
+        predicate: impl Fn(&LdapUser) -> bool,
+    ) -> Result<Vec<LdapUser>, anyhow::Error> {
+        let users = self
+            .users
+            .read()
+            .map_err(|_| anyhow::anyhow!("Directory lock poisoned"))?;
+        Ok(users
+            .iter()
+            .filter(|u| predicate(u))
+            .map(|u| u.clone().retain_attrs(attrs))
+            .collect())
+    }
+}
+
+#[async_trait]
+impl Directory for MemoryDirectory {
+    async fn get_user(
+        &self,
+        uid: &str,
+        attrs: &SearchAttrs,
+    ) -> Result<Vec<LdapUser>, anyhow::Error> {
+        self.find(attrs, |u| u.uid == uid)
+    }
+
+    async fn get_users(
+        &self,
+        uids: &[String],
+        attrs: &SearchAttrs,
+    ) -> Result<Vec<LdapUser>, anyhow::Error> {
+        self.find(attrs, |u| uids.contains(&u.uid))
+    }
+
+    async fn search_users(
+        &self,
+        query: &str,
+        attrs: &SearchAttrs,
+    ) -> Result<Vec<LdapUser>, anyhow::Error> {
+        let query = query.to_lowercase();
+        self.find(attrs, |u| {
+            u.uid.to_lowercase().contains(&query) || u.cn.to_lowercase().contains(&query)
+        })
+    }
+
+    async fn get_group_members(
+        &self,
+        group: Group,
+        attrs: &SearchAttrs,
+    ) -> Result<Vec<LdapUser>, anyhow::Error> {
+        self.find(attrs, |u| MemoryDirectory::in_group(u, group))
+    }
+
+    async fn get_active_upperclassmen(
+        &self,
+        attrs: &SearchAttrs,
+    ) -> Result<Vec<LdapUser>, anyhow::Error> {
+        self.find(attrs, |u| {
+            MemoryDirectory::in_group(u, Group::Active)
+                && !MemoryDirectory::in_group(u, Group::IntroMembers)
+        })
+    }
+
+    async fn apply_group_change(&self, change: &GroupChange) -> Result<(), anyhow::Error> {
+        let mut users = self
+            .users
+            .write()
+            .map_err(|_| anyhow::anyhow!("Directory lock poisoned"))?;
+        let user = users
+            .iter_mut()
+            .find(|u| u.uid == change.uid)
+            .ok_or_else(|| anyhow::anyhow!("No such user '{}'", change.uid))?;
+        let cn = change.group.to_string();
+        match change.action {
+            GroupAction::Add => {
+                if !user.groups.contains(&cn) {
+                    user.groups.push(cn);
+                }
+            }
+            GroupAction::Remove => user.groups.retain(|g| *g != cn),
+        }
+        Ok(())
+    }
+}
+
+/// Build the directory selected by `DIRECTORY_BACKEND`. `ldap` (the default)
+/// binds to CSH LDAP with `CONDITIONAL_LDAP_BIND_DN` and
+/// `CONDITIONAL_LDAP_BIND_PW`. `memory` serves the users in the JSON file at
+/// `DIRECTORY_FIXTURE`, or no users if it isn't set.
+pub async fn from_env() -> Result<Arc<dyn Directory>, anyhow::Error> {
+    match env::var("DIRECTORY_BACKEND").unwrap_or_default().as_str() {
+        "" | "ldap" => {
+            let bind_dn = env::var("CONDITIONAL_LDAP_BIND_DN")
+                .map_err(|_| anyhow::anyhow!("CONDITIONAL_LDAP_BIND_DN not set"))?;
+            let bind_pw = env::var("CONDITIONAL_LDAP_BIND_PW")
+                .map_err(|_| anyhow::anyhow!("CONDITIONAL_LDAP_BIND_PW not set"))?;
+            Ok(Arc::new(LdapClient::new(&bind_dn, &bind_pw).await?))
+        }
+        "memory" => match env::var("DIRECTORY_FIXTURE") {
+            Ok(path) if !path.is_empty() => Ok(Arc::new(MemoryDirectory::from_file(&path)?)),
+            _ => Ok(Arc::new(MemoryDirectory::default())),
+        },
+        other => Err(anyhow::anyhow!("Unknown DIRECTORY_BACKEND '{}'", other)),
+    }
+}
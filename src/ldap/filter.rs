@@ -0,0 +1,84 @@
+use ldap3::ldap_escape;
+use std::fmt::{self, Display};
+use std::ops::Not;
+
+/// An LDAP search filter. Attribute names are static so they can never come
+/// from user input, and every value is escaped per RFC 4515 when the filter
+/// is rendered.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Filter {
+    /// `(attr=value)`
+    Equal(&'static str, String),
+    /// `(attr=*value*)`
+    Contains(&'static str, String),
+    /// `(attr=*)`
+    Present(&'static str),
+    /// `(&(...)(...))`
+    And(Vec<Filter>),
+    /// `(|(...)(...))`
+    Or(Vec<Filter>),
+    /// `(!(...))`
+    Not(Box<Filter>),
+}
+
+impl Filter {
+    #[must_use]
+    pub fn equal(attr: &'static str, value: &str) -> Self {
+        Filter::Equal(attr, value.to_string())
+    }
+
+    #[must_use]
+    pub fn contains(attr: &'static str, value: &str) -> Self {
+        Filter::Contains(attr, value.to_string())
+    }
+
+    #[must_use]
+    pub fn present(attr: &'static str) -> Self {
+        Filter::Present(attr)
+    }
+
+    #[must_use]
+    pub fn and(filters: Vec<Filter>) -> Self {
+        Filter::And(filters)
+    }
+
+    #[must_use]
+    pub fn or(filters: Vec<Filter>) -> Self {
+        Filter::Or(filters)
+    }
+}
+
+impl Not for Filter {
+    type Output = Filter;
+
+    fn not(self) -> Self::Output {
+        Filter::Not(Box::new(self))
+    }
+}
+
+impl Display for Filter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Filter::Equal(attr, value) => write!(f, "({}={})", attr, ldap_escape(value.as_str())),
+            Filter::Contains(attr, value) => {
+                write!(f, "({}=*{}*)", attr, ldap_escape(value.as_str()))
+            }
+            Filter::Present(attr) => write!(f, "({}=*)", attr),
+            Filter::And(filters) => {
+                write!(f, "(&")?;
+                filters
+                    .iter()
+                    .try_for_each(|filter| write!(f, "{}", filter))?;
+                write!(f, ")")
+            }
+            Filter::Or(filters) => {
+                write!(f, "(|")?;
+                filters
+                    .iter()
+                    .try_for_each(|filter| write!(f, "{}", filter))?;
+                write!(f, ")")
+            }
+            Filter::Not(filter) => write!(f, "(!{})", filter),
+        }
+    }
+}
@@ -0,0 +1,77 @@
+use serde::{Deserialize, Serialize};
+use std::{env, fmt, str::FromStr};
+use utoipa::ToSchema;
+
+const GROUPS_OU: &str = "cn=groups,cn=accounts,dc=csh,dc=rit,dc=edu";
+
+/// A logical group of users in LDAP. Each group resolves to the full DN of
+/// its LDAP group, which can be overridden with its `LDAP_GROUP_*`
+/// environment variable, so membership is matched exactly rather than by
+/// substring.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum Group {
+    Active,
+    #[serde(rename = "intromembers")]
+    IntroMembers,
+    Member,
+    Eboard,
+    OnFloor,
+    CurrentStudent,
+    Rtp,
+}
+
+impl Group {
+    fn cn(&self) -> &'static str {
+        match self {
+            Group::Active => "active",
+            Group::IntroMembers => "intromembers",
+            Group::Member => "member",
+            Group::Eboard => "eboard",
+            Group::OnFloor => "onfloor",
+            Group::CurrentStudent => "current_student",
+            Group::Rtp => "rtp",
+        }
+    }
+
+    fn env_var(&self) -> &'static str {
+        match self {
+            Group::Active => "LDAP_GROUP_ACTIVE",
+            Group::IntroMembers => "LDAP_GROUP_INTROMEMBERS",
+            Group::Member => "LDAP_GROUP_MEMBER",
+            Group::Eboard => "LDAP_GROUP_EBOARD",
+            Group::OnFloor => "LDAP_GROUP_ONFLOOR",
+            Group::CurrentStudent => "LDAP_GROUP_CURRENT_STUDENT",
+            Group::Rtp => "LDAP_GROUP_RTP",
+        }
+    }
+
+    /// The full DN of this group
+    #[must_use]
+    pub fn dn(&self) -> String {
+        env::var(self.env_var()).unwrap_or_else(|_| format!("cn={},{}", self.cn(), GROUPS_OU))
+    }
+}
+
+impl fmt::Display for Group {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.cn())
+    }
+}
+
+impl FromStr for Group {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "active" => Ok(Group::Active),
+            "intromembers" => Ok(Group::IntroMembers),
+            "member" => Ok(Group::Member),
+            "eboard" => Ok(Group::Eboard),
+            "onfloor" => Ok(Group::OnFloor),
+            "current_student" => Ok(Group::CurrentStudent),
+            "rtp" => Ok(Group::Rtp),
+            _ => Err(anyhow::anyhow!("Unknown group '{}'", s)),
+        }
+    }
+}
@@ -0,0 +1,96 @@
+use super::{directory::Directory, group::Group};
+use log::{log, Level};
+use serde::{Deserialize, Serialize};
+use std::env;
+use utoipa::ToSchema;
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, ToSchema)]
+pub enum GroupAction {
+    Add,
+    Remove,
+}
+
+/// A single change to a user's group membership in the directory
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, ToSchema)]
+pub struct GroupChange {
+    /// CSH username of the user being changed
+    pub uid: String,
+    pub group: Group,
+    pub action: GroupAction,
+}
+
+/// Outcome of writing group changes back to the directory
+#[derive(Serialize, Deserialize, Clone, Debug, ToSchema)]
+pub struct GroupChangeReport {
+    /// Whether the changes were only planned and not applied
+    pub dry_run: bool,
+    /// Whether write-back is enabled with `LDAP_WRITE_BACK`
+    pub enabled: bool,
+    /// Changes that were applied, or would be applied in a dry run
+    pub changes: Vec<GroupChange>,
+    /// Changes that failed to apply
+    pub failed: Vec<FailedGroupChange>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, ToSchema)]
+pub struct FailedGroupChange {
+    pub change: GroupChange,
+    pub error: String,
+}
+
+/// Whether group changes should be written back to the directory. Off unless
+/// `LDAP_WRITE_BACK` is `true`, since the bind user needs write access.
+#[must_use]
+pub fn write_back_enabled() -> bool {
+    env::var("LDAP_WRITE_BACK")
+        .map(|x| x.parse::<bool>().unwrap_or(false))
+        .unwrap_or(false)
+}
+
+/// Group changes for an intro member who passed intro evals: they leave
+/// `intromembers` and join `member` and `active`
+#[must_use]
+pub fn passed_intro_evals(uid: &str) -> Vec<GroupChange> {
+    vec![
+        GroupChange {
+            uid: uid.to_string(),
+            group: Group::IntroMembers,
+            action: GroupAction::Remove,
+        },
+        GroupChange {
+            uid: uid.to_string(),
+            group: Group::Member,
+            action: GroupAction::Add,
+        },
+        GroupChange {
+            uid: uid.to_string(),
+            group: Group::Active,
+            action: GroupAction::Add,
+        },
+    ]
+}
+
+/// Apply `changes` to `directory` in order, carrying on past failures so one
+/// bad user doesn't block the rest
+pub async fn apply(directory: &dyn Directory, changes: Vec<GroupChange>) -> GroupChangeReport {
+    let mut applied = Vec::new();
+    let mut failed = Vec::new();
+    for change in changes {
+        match directory.apply_group_change(&change).await {
+            Ok(()) => applied.push(change),
+            Err(e) => {
+                log!(Level::Error, "Failed to apply {:?}: {}", change, e);
+                failed.push(FailedGroupChange {
+                    change,
+                    error: e.to_string(),
+                });
+            }
+        }
+    }
+    GroupChangeReport {
+        dry_run: false,
+        enabled: true,
+        changes: applied,
+        failed,
+    }
+}
@@ -1,109 +1,192 @@
+use self::cache::CacheKey;
+use self::membership::{GroupAction, GroupChange};
 use self::user::LdapUser;
-use crate::ldap::client::LdapClient;
+use crate::ldap::client::{LdapClient, LdapUnavailable};
+use crate::ldap::filter::Filter;
+use crate::ldap::group::Group;
 use crate::ldap::search::SearchAttrs;
-use ldap3::{ResultEntry, SearchEntry};
+use ldap3::{dn_escape, LdapError, Mod, ResultEntry, SearchEntry};
 use log::{log, Level};
+use std::collections::HashSet;
 
+pub mod cache;
 pub mod client;
+pub mod directory;
+pub mod filter;
+pub mod group;
+pub mod membership;
 pub mod search;
 pub mod user;
 
-pub async fn get_intro_members(client: &LdapClient) -> Result<Vec<LdapUser>, anyhow::Error> {
-    get_group_members(client, "intromembers").await
+const USERS_OU: &str = "cn=users,cn=accounts,dc=csh,dc=rit,dc=edu";
+
+pub async fn get_intro_members(
+    client: &LdapClient,
+    attrs: &SearchAttrs,
+) -> Result<Vec<LdapUser>, anyhow::Error> {
+    get_group_members(client, Group::IntroMembers, attrs).await
 }
 
-pub async fn get_active_upperclassmen(client: &LdapClient) -> Result<Vec<LdapUser>, anyhow::Error> {
-    let res = ldap_search(
+pub async fn get_active_upperclassmen(
+    client: &LdapClient,
+    attrs: &SearchAttrs,
+) -> Result<Vec<LdapUser>, anyhow::Error> {
+    cached_user_search(
         client,
-        "cn=users,cn=accounts,dc=csh,dc=rit,dc=edu",
-        format!("(&(memberOf=*active*)(!(memberOf=*intromember*)))").as_str(),
-        None,
+        CacheKey::ActiveUpperclassmen,
+        &Filter::and(vec![
+            Filter::equal("memberOf", &Group::Active.dn()),
+            !Filter::equal("memberOf", &Group::IntroMembers.dn()),
+        ]),
+        attrs,
     )
-    .await?;
-
-    Ok(res
-        .iter()
-        .map(|r| {
-            let user = SearchEntry::construct(r.to_owned());
-            LdapUser::from_entry(&user)
-        })
-        .collect())
+    .await
 }
 
 pub async fn get_group_members(
     client: &LdapClient,
-    group: &str,
+    group: Group,
+    attrs: &SearchAttrs,
 ) -> Result<Vec<LdapUser>, anyhow::Error> {
-    let res = ldap_search(
+    cached_user_search(
         client,
-        "cn=users,cn=accounts,dc=csh,dc=rit,dc=edu",
-        format!("memberOf=*{}*", group).as_str(),
-        None,
+        CacheKey::Group(group),
+        &Filter::equal("memberOf", &group.dn()),
+        attrs,
     )
-    .await?;
-
-    Ok(res
-        .iter()
-        .map(|r| {
-            let user = SearchEntry::construct(r.to_owned());
-            LdapUser::from_entry(&user)
-        })
-        .collect())
+    .await
 }
 
-pub async fn get_user(client: &LdapClient, user: &str) -> Result<Vec<LdapUser>, anyhow::Error> {
-    let res = ldap_search(
+pub async fn get_user(
+    client: &LdapClient,
+    user: &str,
+    attrs: &SearchAttrs,
+) -> Result<Vec<LdapUser>, anyhow::Error> {
+    cached_user_search(
         client,
-        "cn=users,cn=accounts,dc=csh,dc=rit,dc=edu",
-        format!("(uid={})", user).as_str(),
-        None,
+        CacheKey::User(user.to_string()),
+        &Filter::equal("uid", user),
+        attrs,
     )
-    .await?;
+    .await
+}
 
-    Ok(res
-        .iter()
-        .map(|r| {
-            let user = SearchEntry::construct(r.to_owned());
-            LdapUser::from_entry(&user)
-        })
-        .collect())
+/// Users whose uid is one of `uids`, found with a single search
+pub async fn get_users(
+    client: &LdapClient,
+    uids: &[String],
+    attrs: &SearchAttrs,
+) -> Result<Vec<LdapUser>, anyhow::Error> {
+    if uids.is_empty() {
+        return Ok(Vec::new());
+    }
+    cached_user_search(
+        client,
+        CacheKey::Users(uids.to_vec()),
+        &Filter::or(uids.iter().map(|uid| Filter::equal("uid", uid)).collect()),
+        attrs,
+    )
+    .await
 }
 
 pub async fn search_users(
     client: &LdapClient,
     query: &str,
+    attrs: &SearchAttrs,
 ) -> Result<Vec<LdapUser>, anyhow::Error> {
-    let res = ldap_search(
+    cached_user_search(
         client,
-        "cn=users,cn=accounts,dc=csh,dc=rit,dc=edu",
-        format!("(|(uid=*{query}*)(cn=*{query}*))").as_str(),
-        None,
+        CacheKey::Search(query.to_string()),
+        &Filter::or(vec![
+            Filter::contains("uid", query),
+            Filter::contains("cn", query),
+        ]),
+        attrs,
     )
-    .await?;
+    .await
+}
+
+async fn cached_user_search(
+    client: &LdapClient,
+    key: CacheKey,
+    filter: &Filter,
+    attrs: &SearchAttrs,
+) -> Result<Vec<LdapUser>, anyhow::Error> {
+    if let Some(users) = client.cache.get(&key, attrs) {
+        return Ok(users);
+    }
+
+    let res = ldap_search(client, USERS_OU, filter, attrs).await?;
 
-    Ok(res
+    let users: Vec<LdapUser> = res
         .iter()
         .map(|r| {
             let user = SearchEntry::construct(r.to_owned());
-            LdapUser::from_entry(&user)
+            LdapUser::from_entry(&user, attrs)
         })
-        .collect())
+        .collect();
+    client.cache.insert(&key, attrs, &users);
+    Ok(users)
+}
+
+/// Add a user to or remove them from a group. Adding a user who is already a
+/// member, or removing one who isn't, succeeds without changing anything.
+pub async fn modify_group(client: &LdapClient, change: &GroupChange) -> Result<(), anyhow::Error> {
+    let user_dn = format!("uid={},{}", dn_escape(change.uid.as_str()), USERS_OU);
+    let member = HashSet::from([user_dn]);
+    let modification = match change.action {
+        GroupAction::Add => Mod::Add(String::from("member"), member),
+        GroupAction::Remove => Mod::Delete(String::from("member"), member),
+    };
+    log!(
+        Level::Info,
+        "LDAP {:?} {} for group {}",
+        change.action,
+        change.uid,
+        change.group
+    );
+
+    let mut ldap = client.ldap.get().await.map_err(LdapUnavailable::from)?;
+    ldap.with_timeout(std::time::Duration::from_secs(5));
+    let result = match ldap.modify(&change.group.dn(), vec![modification]).await {
+        Ok(result) => result,
+        Err(e @ LdapError::LdapResult { .. }) => return Err(e.into()),
+        Err(e) => {
+            let _ = deadpool::managed::Object::take(ldap);
+            return Err(LdapUnavailable(e.to_string()).into());
+        }
+    };
+    client.cache.invalidate_all();
+
+    match result.rc {
+        // success, noSuchAttribute, attributeOrValueExists
+        0 | 16 | 20 => Ok(()),
+        _ => Err(LdapError::LdapResult { result }.into()),
+    }
 }
 
 async fn ldap_search(
     client: &LdapClient,
     ou: &str,
-    query: &str,
-    attrs: Option<SearchAttrs>,
+    filter: &Filter,
+    attrs: &SearchAttrs,
 ) -> Result<Vec<ResultEntry>, anyhow::Error> {
+    let query = filter.to_string();
     log!(Level::Debug, "LDAP Search with query {query} from {ou}");
-    let attrs = attrs.unwrap_or_default().finalize();
-    let mut ldap = client.ldap.get().await.unwrap();
+    let attrs = attrs.clone().finalize();
+    let mut ldap = client.ldap.get().await.map_err(LdapUnavailable::from)?;
     ldap.with_timeout(std::time::Duration::from_secs(5));
-    let (results, _result) = ldap
-        .search(ou, ldap3::Scope::Subtree, query, attrs)
-        .await?
-        .success()?;
+    let (results, _result) = match ldap.search(ou, ldap3::Scope::Subtree, &query, attrs).await {
+        Ok(res) => res.success()?,
+        // Anything other than an error result from the server means the
+        // connection itself is broken, so drop it rather than return it to
+        // the pool
+        Err(e @ LdapError::LdapResult { .. }) => return Err(e.into()),
+        Err(e) => {
+            let _ = deadpool::managed::Object::take(ldap);
+            return Err(LdapUnavailable(e.to_string()).into());
+        }
+    };
 
     Ok(results)
 }
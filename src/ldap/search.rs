@@ -1,5 +1,13 @@
+use std::fmt;
 use std::string::ToString;
 
+/// Attributes whose values are only shown to eboard and admins
+pub const SENSITIVE_ATTRS: [&str; 3] = ["mobile", "ibutton", "drinkBalance"];
+
+/// The set of attributes to fetch in an LDAP search. The default set is
+/// everything in `LdapUser` except the sensitive attributes, which must be
+/// added with `with_sensitive`.
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct SearchAttrs {
     attrs: Vec<String>,
 }
@@ -15,9 +23,11 @@ impl Default for SearchAttrs {
                 String::from("memberOf"),
                 String::from("krbPrincipalName"),
                 String::from("mail"),
-                String::from("mobile"),
-                String::from("ibutton"),
-                String::from("drinkBalance"),
+                String::from("onfloor"),
+                String::from("roomNumber"),
+                String::from("ritYear"),
+                String::from("memberSince"),
+                String::from("housingPoints"),
             ],
         }
     }
@@ -30,7 +40,22 @@ impl SearchAttrs {
         }
     }
 
+    /// Just enough to identify a user, for endpoints that only need names and
+    /// usernames
+    #[must_use]
+    pub fn minimal() -> Self {
+        SearchAttrs::new(&["cn", "dn", "uid", "ritDn", "krbPrincipalName"])
+    }
+
+    #[must_use]
+    pub fn with_sensitive(self) -> Self {
+        SENSITIVE_ATTRS
+            .iter()
+            .fold(self, |attrs, attr| attrs.add(attr))
+    }
+
     #[must_use]
+    #[allow(clippy::should_implement_trait)]
     pub fn add(mut self, attr: &str) -> Self {
         if !(self.attrs.contains(&attr.to_string())) {
             self.attrs.push(attr.to_string());
@@ -46,8 +71,19 @@ impl SearchAttrs {
         self
     }
 
+    #[must_use]
+    pub fn contains(&self, attr: &str) -> bool {
+        self.attrs.iter().any(|a| a == attr)
+    }
+
     #[must_use]
     pub fn finalize(self) -> Vec<String> {
         self.attrs
     }
 }
+
+impl fmt::Display for SearchAttrs {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.attrs.join(","))
+    }
+}
@@ -1,3 +1,5 @@
+use super::search::SearchAttrs;
+use chrono::NaiveDateTime;
 use lazy_static::lazy_static;
 use ldap3::SearchEntry;
 use regex::Regex;
@@ -17,14 +19,33 @@ pub struct LdapUser {
     pub groups: Vec<String>,
     pub krb_principal_name: String,
     pub mail: Vec<String>,
-    pub mobile: Vec<String>,
+    /// Only present for eboard and admins
+    #[serde(default)]
+    pub mobile: Option<Vec<String>>,
+    /// Only present for eboard and admins
+    #[serde(default)]
     pub drink_balance: Option<i64>,
-    pub ibutton: Vec<String>,
+    /// Only present for eboard and admins
+    #[serde(default)]
+    pub ibutton: Option<Vec<String>>,
+    #[serde(default)]
+    pub onfloor: Option<bool>,
+    #[serde(default)]
+    pub room_number: Option<String>,
+    /// Year of study at RIT
+    #[serde(default)]
+    pub rit_year: Option<i32>,
+    #[serde(default)]
+    pub member_since: Option<NaiveDateTime>,
+    #[serde(default)]
+    pub housing_points: Option<i32>,
 }
 
 impl LdapUser {
+    /// Build a user from a search entry. Optional fields are only set if
+    /// their attribute was requested in `attrs`.
     #[must_use]
-    pub fn from_entry(entry: &SearchEntry) -> Self {
+    pub fn from_entry(entry: &SearchEntry, attrs: &SearchAttrs) -> Self {
         let user_attrs = &entry.attrs;
         LdapUser {
             dn: entry.dn.clone(),
@@ -34,11 +55,58 @@ impl LdapUser {
             groups: get_groups(get_vec(user_attrs, "memberOf")),
             krb_principal_name: get_one(user_attrs, "krbPrincipalName").unwrap(),
             mail: get_vec(user_attrs, "mail"),
-            mobile: get_vec(user_attrs, "mobile"),
-            ibutton: get_vec(user_attrs, "ibutton"),
+            mobile: Some(get_vec(user_attrs, "mobile")),
+            ibutton: Some(get_vec(user_attrs, "ibutton")),
             drink_balance: get_one(user_attrs, "drinkBalance"),
+            onfloor: get_one::<String>(user_attrs, "onfloor")
+                .map(|o| o.eq_ignore_ascii_case("TRUE")),
+            room_number: get_one(user_attrs, "roomNumber"),
+            rit_year: get_one(user_attrs, "ritYear"),
+            member_since: get_one::<String>(user_attrs, "memberSince")
+                .and_then(|t| parse_generalized_time(&t)),
+            housing_points: get_one(user_attrs, "housingPoints"),
         }
+        .retain_attrs(attrs)
     }
+
+    /// Clear any optional fields whose attribute isn't in `attrs`, e.g. to
+    /// redact sensitive fields
+    #[must_use]
+    pub fn retain_attrs(mut self, attrs: &SearchAttrs) -> Self {
+        if !attrs.contains("mobile") {
+            self.mobile = None;
+        }
+        if !attrs.contains("ibutton") {
+            self.ibutton = None;
+        }
+        if !attrs.contains("drinkBalance") {
+            self.drink_balance = None;
+        }
+        if !attrs.contains("onfloor") {
+            self.onfloor = None;
+        }
+        if !attrs.contains("roomNumber") {
+            self.room_number = None;
+        }
+        if !attrs.contains("ritYear") {
+            self.rit_year = None;
+        }
+        if !attrs.contains("memberSince") {
+            self.member_since = None;
+        }
+        if !attrs.contains("housingPoints") {
+            self.housing_points = None;
+        }
+        self
+    }
+}
+
+/// Parse an LDAP GeneralizedTime such as `20190825153000Z`, ignoring any
+/// fractional seconds
+fn parse_generalized_time(time: &str) -> Option<NaiveDateTime> {
+    let time = time.trim_end_matches('Z');
+    let time = time.split(['.', ',']).next().unwrap_or(time);
+    NaiveDateTime::parse_from_str(time, "%Y%m%d%H%M%S").ok()
 }
 
 fn get_one<T>(entry: &HashMap<String, Vec<String>>, field: &str) -> Option<T>
@@ -46,7 +114,7 @@ where
     T: FromStr,
     <T as FromStr>::Err: Debug,
 {
-    match entry.get(field).map(|f| f.get(0).unwrap().parse::<T>()) {
+    match entry.get(field).map(|f| f.first().unwrap().parse::<T>()) {
         Some(Ok(r)) => Some(r),
         _ => None,
     }
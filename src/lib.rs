@@ -10,6 +10,18 @@ pub mod schema {
 
 pub mod api;
 
+pub mod repo;
+
+pub mod error;
+
+pub mod transaction;
+
 pub mod ldap;
 
+pub mod packet;
+
+pub mod identity;
+
+pub mod housing;
+
 pub mod auth;
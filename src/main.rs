@@ -1,6 +1,7 @@
 use actix_cors::Cors;
 use actix_web::{middleware::Logger, App, HttpServer};
 use conditional_backend::app::{configure_app, get_app_data};
+use conditional_backend::error::RequestId;
 use dotenv::dotenv;
 use lazy_static::lazy_static;
 use log::{log, Level};
@@ -39,8 +40,9 @@ async fn main() -> std::io::Result<()> {
         };
         App::new()
             .wrap(cors)
+            .wrap(RequestId)
             .wrap(Logger::new(
-                "%a \"%r\" %s %b \"%{Referer}i\" \"%{User-Agent}i\" %T",
+                "%a \"%r\" %s %b \"%{Referer}i\" \"%{User-Agent}i\" %T %{x-request-id}o",
             ))
             .configure(configure_app)
             .app_data(app_data.clone())
@@ -0,0 +1,179 @@
+use crate::error::{log_query_as, AppError};
+use crate::schema::api::{
+    IncompletePacket, MiscSignatures, Packet, PacketSignatures, UpperclassmenSignatures,
+};
+use crate::schema::db::{PacketRounding, PacketSettings};
+use chrono::NaiveDate;
+use log::{log, Level};
+use sqlx::{Pool, Postgres};
+
+// Queries on the packet app's database live in the packet-db crate, which
+// checks them against the packet schema
+pub use packet_db::PacketRow;
+
+impl Default for PacketSettings {
+    fn default() -> Self {
+        PacketSettings {
+            year_start: NaiveDate::MIN,
+            misc_cap: 10,
+            rounding: PacketRounding::Floor,
+        }
+    }
+}
+
+impl PacketRounding {
+    /// Round a completion percentage to a whole percentage
+    #[must_use]
+    pub fn round(self, completion: f64) -> i64 {
+        let rounded = match self {
+            PacketRounding::Floor => completion.floor(),
+            PacketRounding::Nearest => completion.round(),
+            PacketRounding::Ceiling => completion.ceil(),
+        };
+        rounded as i64
+    }
+}
+
+impl Packet {
+    /// Misc signatures that count towards the packet
+    #[must_use]
+    pub fn counted_misc(&self, settings: &PacketSettings) -> i64 {
+        self.misc_signatures.min(i64::from(settings.misc_cap))
+    }
+
+    /// Signatures counted towards the packet
+    #[must_use]
+    pub fn signatures(&self, settings: &PacketSettings) -> i64 {
+        self.upper_signatures + self.counted_misc(settings)
+    }
+
+    /// Signatures needed for 100%
+    #[must_use]
+    pub fn max_signatures(&self, settings: &PacketSettings) -> i64 {
+        self.max_upper + i64::from(settings.misc_cap)
+    }
+
+    /// Percentage of the packet completed. A packet with nothing to sign is
+    /// 0% complete rather than a division by zero.
+    #[must_use]
+    pub fn completion(&self, settings: &PacketSettings) -> f64 {
+        completion(self.signatures(settings), self.max_signatures(settings))
+    }
+}
+
+/// Percentage of `max_signatures` that `signatures` makes up, or 0 if
+/// `max_signatures` isn't positive
+#[must_use]
+pub fn completion(signatures: i64, max_signatures: i64) -> f64 {
+    if max_signatures <= 0 {
+        0.0
+    } else {
+        100.0 * signatures as f64 / max_signatures as f64
+    }
+}
+
+impl TryFrom<PacketRow> for Packet {
+    type Error = IncompletePacket;
+
+    fn try_from(row: PacketRow) -> Result<Self, Self::Error> {
+        let username = row
+            .username
+            .map(|u| u.trim().to_owned())
+            .filter(|u| !u.is_empty());
+        let name = row.name.filter(|n| !n.trim().is_empty());
+        match (username, name) {
+            (Some(username), Some(name)) => Ok(Packet {
+                username,
+                name,
+                start: row.start,
+                upper_signatures: row.upper_signatures,
+                max_upper: row.max_upper,
+                misc_signatures: row.misc_signatures,
+            }),
+            (username, name) => Err(IncompletePacket {
+                packet_id: row.id,
+                missing: [("username", username.is_none()), ("name", name.is_none())]
+                    .into_iter()
+                    .filter(|(_, missing)| *missing)
+                    .map(|(field, _)| field.to_string())
+                    .collect(),
+                username,
+            }),
+        }
+    }
+}
+
+/// Packets that can be evaluated, and the ones that were skipped
+pub struct PacketReport {
+    pub packets: Vec<Packet>,
+    pub incomplete: Vec<IncompletePacket>,
+}
+
+impl From<Vec<PacketRow>> for PacketReport {
+    fn from(rows: Vec<PacketRow>) -> Self {
+        let mut report = PacketReport {
+            packets: Vec::with_capacity(rows.len()),
+            incomplete: Vec::new(),
+        };
+        for row in rows {
+            match Packet::try_from(row) {
+                Ok(packet) => report.packets.push(packet),
+                Err(incomplete) => {
+                    log!(
+                        Level::Warn,
+                        "Skipping packet {} ({}), missing {}",
+                        incomplete.packet_id,
+                        incomplete.username.as_deref().unwrap_or("no username"),
+                        incomplete.missing.join(", ")
+                    );
+                    report.incomplete.push(incomplete);
+                }
+            }
+        }
+        report
+    }
+}
+
+pub async fn get_all_packets(packet_db: &Pool<Postgres>) -> Result<PacketReport, AppError> {
+    let rows = log_query_as(packet_db::get_packets(packet_db, None).await)?;
+    Ok(PacketReport::from(rows))
+}
+
+/// Who has and hasn't signed the most recent packet belonging to
+/// `rit_username`
+pub async fn get_packet_signatures(
+    rit_username: &str,
+    settings: &PacketSettings,
+    packet_db: &Pool<Postgres>,
+) -> Result<Option<Result<PacketSignatures, IncompletePacket>>, AppError> {
+    let rows = log_query_as(packet_db::get_packets(packet_db, Some(rit_username)).await)?;
+    let (packet_id, packet) = match rows.into_iter().next() {
+        Some(row) => (row.id, Packet::try_from(row)),
+        None => return Ok(None),
+    };
+    let packet = match packet {
+        Ok(packet) => packet,
+        Err(incomplete) => return Ok(Some(Err(incomplete))),
+    };
+
+    let upper = log_query_as(packet_db::get_upper_signatures(packet_db, packet_id).await)?;
+    let misc = log_query_as(packet_db::get_misc_signatures(packet_db, packet_id).await)?;
+
+    let (signed, unsigned): (Vec<_>, Vec<_>) = upper.into_iter().partition(|s| s.signed);
+    Ok(Some(Ok(PacketSignatures {
+        upperclassmen: UpperclassmenSignatures {
+            signed: signed.into_iter().map(|s| s.member).collect(),
+            unsigned: unsigned.into_iter().map(|s| s.member).collect(),
+        },
+        misc: MiscSignatures {
+            members: misc,
+            counted: packet.counted_misc(settings),
+            cap: i64::from(settings.misc_cap),
+        },
+        signatures: packet.signatures(settings),
+        max_signatures: packet.max_signatures(settings),
+        completion: packet.completion(settings),
+        username: packet.username,
+        name: packet.name,
+    })))
+}
@@ -0,0 +1,187 @@
+use crate::error::{log_query, log_query_as, AppError};
+use crate::repo::Person;
+use crate::schema::api::ID;
+use crate::schema::db::CheckInSession;
+use chrono::NaiveDateTime;
+use log::{log, Level};
+use sqlx::{query, query_as, PgConnection};
+
+/// The member who created a meeting, if it was created with a host
+struct MeetingHost {
+    host: Option<String>,
+}
+
+/// Host of either seminar `seminar_id` or directorship `meeting_id`
+pub async fn get_meeting_host(
+    conn: &mut PgConnection,
+    seminar_id: Option<i32>,
+    meeting_id: Option<i32>,
+) -> Result<Option<String>, AppError> {
+    let hosts = if let Some(id) = seminar_id {
+        query_as!(
+            MeetingHost,
+            "SELECT host FROM technical_seminars WHERE id = $1",
+            id
+        )
+        .fetch_all(&mut *conn)
+        .await
+    } else {
+        query_as!(
+            MeetingHost,
+            "SELECT host FROM committee_meetings WHERE id = $1",
+            meeting_id
+        )
+        .fetch_all(&mut *conn)
+        .await
+    };
+    match log_query_as(hosts)?.pop() {
+        Some(meeting) => Ok(meeting.host),
+        None => Err(AppError::NotFound(String::from("Meeting not found"))),
+    }
+}
+
+/// Open a check-in session with `code` for either a seminar or a
+/// directorship. Checking in only records attendance, which counts once the
+/// meeting is approved like any other.
+pub async fn open_session(
+    conn: &mut PgConnection,
+    seminar_id: Option<i32>,
+    meeting_id: Option<i32>,
+    code: &str,
+    host: &str,
+    expires: &NaiveDateTime,
+) -> Result<(), AppError> {
+    log_query(
+        query!(
+            "INSERT INTO attendance_checkin_sessions (code, seminar_id, meeting_id, host, expires)
+                VALUES ($1, $2, $3, $4, $5)",
+            code,
+            seminar_id,
+            meeting_id,
+            host,
+            expires
+        )
+        .execute(&mut *conn)
+        .await
+        .map(|_| ()),
+    )?;
+    log!(Level::Debug, "Opened check-in session {code}");
+    Ok(())
+}
+
+/// Close the open session with `code`, if `host` opened it
+pub async fn close_session(
+    conn: &mut PgConnection,
+    code: &str,
+    host: &str,
+) -> Result<(), AppError> {
+    let ids = log_query_as(
+        query_as!(
+            ID,
+            "UPDATE attendance_checkin_sessions SET closed = true
+                WHERE code = $1 AND host = $2 AND NOT closed RETURNING id",
+            code.to_ascii_uppercase(),
+            host
+        )
+        .fetch_all(conn)
+        .await,
+    )?;
+    if ids.is_empty() {
+        return Err(AppError::NotFound(String::from("Session not found")));
+    }
+    Ok(())
+}
+
+/// The session with `code`, if it's still open at `now`. The session is
+/// locked until the caller's transaction ends, so it can't be closed while
+/// someone is checking in to it.
+pub async fn get_open_session(
+    conn: &mut PgConnection,
+    code: &str,
+    now: &NaiveDateTime,
+) -> Result<CheckInSession, AppError> {
+    match log_query_as(
+        query_as!(
+            CheckInSession,
+            "SELECT * FROM attendance_checkin_sessions
+                WHERE code = $1 AND NOT closed AND expires > $2
+                FOR UPDATE",
+            code.to_ascii_uppercase(),
+            now
+        )
+        .fetch_all(conn)
+        .await,
+    )?
+    .pop()
+    {
+        Some(session) => Ok(session),
+        None => Err(AppError::NotFound(String::from("Invalid or expired code"))),
+    }
+}
+
+/// Record `person` as attending the meeting `session` is for. Attendance is
+/// only inserted if it doesn't already exist, so checking in twice is
+/// harmless.
+pub async fn check_in(
+    conn: &mut PgConnection,
+    session: &CheckInSession,
+    person: &Person,
+) -> Result<(), AppError> {
+    let result = match (person, session.seminar_id, session.meeting_id) {
+        (Person::Freshman(fid), Some(seminar_id), _) => {
+            query!(
+                "INSERT INTO freshman_seminar_attendance (fid, seminar_id)
+                    SELECT $1::int4, $2::int4 WHERE NOT EXISTS (
+                        SELECT 1 FROM freshman_seminar_attendance
+                        WHERE fid = $1 AND seminar_id = $2)",
+                fid,
+                seminar_id
+            )
+            .execute(&mut *conn)
+            .await
+        }
+        (Person::Freshman(fid), _, Some(meeting_id)) => {
+            query!(
+                "INSERT INTO freshman_committee_attendance (fid, meeting_id)
+                    SELECT $1::int4, $2::int4 WHERE NOT EXISTS (
+                        SELECT 1 FROM freshman_committee_attendance
+                        WHERE fid = $1 AND meeting_id = $2)",
+                fid,
+                meeting_id
+            )
+            .execute(&mut *conn)
+            .await
+        }
+        (Person::Member(uid), Some(seminar_id), _) => {
+            query!(
+                "INSERT INTO member_seminar_attendance (uid, seminar_id)
+                    SELECT $1::varchar, $2::int4 WHERE NOT EXISTS (
+                        SELECT 1 FROM member_seminar_attendance
+                        WHERE uid = $1 AND seminar_id = $2)",
+                uid,
+                seminar_id
+            )
+            .execute(&mut *conn)
+            .await
+        }
+        (Person::Member(uid), _, Some(meeting_id)) => {
+            query!(
+                "INSERT INTO member_committee_attendance (uid, meeting_id)
+                    SELECT $1::varchar, $2::int4 WHERE NOT EXISTS (
+                        SELECT 1 FROM member_committee_attendance
+                        WHERE uid = $1 AND meeting_id = $2)",
+                uid,
+                meeting_id
+            )
+            .execute(&mut *conn)
+            .await
+        }
+        (_, None, None) => {
+            return Err(AppError::Internal(format!(
+                "Check-in session {} has no meeting",
+                session.id
+            )));
+        }
+    };
+    log_query(result.map(|_| ()))
+}
@@ -0,0 +1,200 @@
+use crate::error::{log_query, log_query_as, AppError};
+use crate::repo::Person;
+use crate::schema::api::{Directorship, ID};
+use crate::schema::db::CommitteeType;
+use chrono::NaiveDateTime;
+use log::{log, Level};
+use sqlx::{query, query_as, PgConnection, Pool, Postgres};
+
+/// Add a directorship meeting hosted by `host`, returning its id
+pub async fn create_directorship(
+    conn: &mut PgConnection,
+    committee: CommitteeType,
+    timestamp: &NaiveDateTime,
+    approved: bool,
+    host: Option<&str>,
+) -> Result<i32, AppError> {
+    Ok(log_query_as(
+        query_as!(
+            ID,
+            "INSERT INTO committee_meetings (committee, \"timestamp\", active, approved, host)
+                VALUES ($1::committees_enum, $2, $3, $4, $5) RETURNING id",
+            committee as CommitteeType,
+            timestamp,
+            true,
+            approved,
+            host
+        )
+        .fetch_all(conn)
+        .await,
+    )?[0]
+        .id)
+}
+
+/// Record `frosh` and `members` as having attended directorship `id`
+pub async fn add_directorship_attendance(
+    conn: &mut PgConnection,
+    id: i32,
+    frosh: &[i32],
+    members: &[String],
+) -> Result<(), AppError> {
+    let frosh_ids = vec![id; frosh.len()];
+    let member_ids = vec![id; members.len()];
+
+    // Add frosh/directorship relation
+    log_query(
+        query!(
+            "INSERT INTO freshman_committee_attendance (fid, meeting_id)
+                SELECT fid, meeting_id
+                FROM UNNEST($1::int4[], $2::int4[]) AS a(fid, meeting_id)",
+            frosh,
+            frosh_ids.as_slice()
+        )
+        .execute(&mut *conn)
+        .await
+        .map(|_| ()),
+    )?;
+
+    // Add member/directorship relation
+    log_query(
+        query!(
+            "INSERT INTO member_committee_attendance (uid, meeting_id)
+                SELECT uid, meeting_id
+                FROM UNNEST($1::TEXT[], $2::int4[]) AS a(uid, meeting_id)",
+            members,
+            member_ids.as_slice()
+        )
+        .execute(&mut *conn)
+        .await
+        .map(|_| ()),
+    )?;
+    log!(Level::Trace, "Added directorship attendance");
+    Ok(())
+}
+
+/// Remove everyone's attendance at directorship `id`
+pub async fn delete_directorship_attendance(
+    conn: &mut PgConnection,
+    id: i32,
+) -> Result<(), AppError> {
+    log_query(
+        query!(
+            "DELETE FROM freshman_committee_attendance WHERE meeting_id = $1",
+            id
+        )
+        .execute(&mut *conn)
+        .await
+        .map(|_| ()),
+    )?;
+    log_query(
+        query!(
+            "DELETE FROM member_committee_attendance WHERE meeting_id = $1",
+            id
+        )
+        .execute(&mut *conn)
+        .await
+        .map(|_| ()),
+    )?;
+    log!(Level::Trace, "Deleted directorship attendance");
+    Ok(())
+}
+
+/// Delete directorship `id` and its attendance
+pub async fn delete_directorship(conn: &mut PgConnection, id: i32) -> Result<(), AppError> {
+    delete_directorship_attendance(conn, id).await?;
+    log_query(
+        query!("DELETE FROM committee_meetings WHERE id = $1", id)
+            .execute(&mut *conn)
+            .await
+            .map(|_| ()),
+    )
+}
+
+/// Approved directorships `person` attended after `since`, without
+/// attendance
+pub async fn get_directorships_attended(
+    db: &Pool<Postgres>,
+    person: &Person,
+    since: &NaiveDateTime,
+) -> Result<Vec<Directorship>, AppError> {
+    match person {
+        Person::Freshman(fid) => log_query_as(
+            query_as!(
+                Directorship,
+                "SELECT cm.committee AS \"committee:_\",
+                        cm.\"timestamp\",
+                        ARRAY[]::varchar[] AS members,
+                        ARRAY[]::integer[] AS frosh,
+                        cm.approved
+                    FROM committee_meetings cm
+                    LEFT JOIN freshman_committee_attendance fca ON
+                        fca.meeting_id = cm.id
+                    WHERE cm.approved
+                    AND timestamp > $1::timestamp
+                    AND fca.fid = $2::int4",
+                since,
+                fid
+            )
+            .fetch_all(db)
+            .await,
+        ),
+        Person::Member(uid) => log_query_as(
+            query_as!(
+                Directorship,
+                "SELECT cm.committee AS \"committee: _\",
+                        cm.\"timestamp\",
+                        ARRAY[]::varchar[] AS members,
+                        ARRAY[]::integer[] AS frosh,
+                        cm.approved
+                    FROM committee_meetings cm
+                    LEFT JOIN member_committee_attendance mca ON
+                        mca.meeting_id = cm.id
+                    WHERE cm.approved
+                    AND timestamp > $1::timestamp
+                    AND mca.uid = $2",
+                since,
+                uid
+            )
+            .fetch_all(db)
+            .await,
+        ),
+    }
+}
+
+/// Directorships held after `since`, with everyone who attended
+pub async fn get_directorships(
+    db: &Pool<Postgres>,
+    since: &NaiveDateTime,
+) -> Result<Vec<Directorship>, AppError> {
+    log_query_as(
+        query_as!(
+            Directorship,
+            "SELECT member_seminars.committee AS \"committee: _\",
+                member_seminars.timestamp,
+                member_seminars.members,
+                array_agg(fsa.fid) AS frosh,
+                member_seminars.approved
+            FROM(
+                SELECT ts.id,
+                       ts.committee,
+                       ts.timestamp,
+                       array_agg(msa.uid) AS members,
+                       ts.approved
+                FROM committee_meetings ts
+                INNER JOIN member_committee_attendance msa ON
+                    msa.meeting_id = ts.id
+                WHERE timestamp > $1
+                GROUP BY ts.id, ts.committee, ts.timestamp, ts.approved) AS member_seminars
+                INNER JOIN freshman_committee_attendance fsa ON
+                    fsa.meeting_id = member_seminars.id
+                GROUP BY member_seminars.id,
+                    member_seminars.committee,
+                    member_seminars.timestamp,
+                    member_seminars.members,
+                    member_seminars.approved",
+            since
+        )
+        .fetch_all(db)
+        .await,
+    )
+}
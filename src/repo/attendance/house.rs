@@ -0,0 +1,534 @@
+use crate::error::{log_query, log_query_as, AppError};
+use crate::repo::Person;
+use crate::schema::api::{
+    Date, EvalsHmAtt, ExcuseRequest, FreshmanHouseMeetingStatus, HouseAttendance,
+    HouseMeetingAttendanceEdit, HouseMeetingRoster, MemberHouseMeetingStatus, ID,
+};
+use crate::schema::db::{
+    AttendanceStatus, CoopSemester, ExcuseStatus, HouseMeeting, HouseMeetingExcuse,
+};
+use chrono::{NaiveDate, NaiveDateTime, Utc};
+use log::{log, Level};
+use sqlx::{query, query_as, PgConnection, Pool, Postgres};
+
+/// Add a house meeting with the attendance taken at it, returning its id
+pub async fn record_house_meeting(
+    conn: &mut PgConnection,
+    attendance: &HouseAttendance,
+) -> Result<i32, AppError> {
+    let id = log_query_as(
+        query_as!(
+            ID,
+            "INSERT INTO house_meetings(date, active) VALUES ($1, true) RETURNING id",
+            attendance.date
+        )
+        .fetch_all(&mut *conn)
+        .await,
+    )?[0]
+        .id;
+    log!(Level::Trace, "created new house meeting");
+
+    let frosh_id = vec![id; attendance.frosh.len()];
+    let member_id = vec![id; attendance.members.len()];
+    let frosh_names: Vec<i32> = attendance.frosh.iter().map(|a| a.fid).collect();
+    let frosh_statuses: Vec<AttendanceStatus> =
+        attendance.frosh.iter().map(|a| a.att_status).collect();
+    let member_names: Vec<String> = attendance.members.iter().map(|a| a.uid.clone()).collect();
+    let member_statuses: Vec<AttendanceStatus> =
+        attendance.members.iter().map(|a| a.att_status).collect();
+
+    log_query(
+        query!("INSERT INTO freshman_hm_attendance (fid, meeting_id, attendance_status) SELECT fid, meeting_id, attendance_status as \"attendance_status: AttendanceStatus\" FROM UNNEST($1::int4[], $2::int4[], $3::attendance_enum[]) as a(fid, meeting_id, attendance_status)", frosh_names.as_slice(), frosh_id.as_slice(), frosh_statuses.as_slice() as &[AttendanceStatus])
+        .execute(&mut *conn).await.map(|_| ()))?;
+
+    log_query(
+        query!("INSERT INTO member_hm_attendance (uid, meeting_id, attendance_status) SELECT uid, meeting_id, attendance_status as \"attendance_status: AttendanceStatus\" FROM UNNEST($1::varchar[], $2::int4[], $3::attendance_enum[]) as a(uid, meeting_id, attendance_status)", member_names.as_slice(), member_id.as_slice(), member_statuses.as_slice() as &[AttendanceStatus])
+        .execute(&mut *conn).await.map(|_| ()))?;
+    log!(Level::Trace, "added attendance to house meeting");
+    Ok(id)
+}
+
+/// Add a house meeting on `date` with `members` and every freshman evaluated
+/// this year marked absent. Members on co-op that semester are left off.
+pub async fn start_house_meeting(
+    conn: &mut PgConnection,
+    date: &NaiveDate,
+    members: &[String],
+    year_start: &NaiveDateTime,
+) -> Result<HouseMeeting, AppError> {
+    let meeting: HouseMeeting = log_query_as(
+        query_as!(
+            HouseMeeting,
+            "INSERT INTO house_meetings(date, active) VALUES ($1, true) RETURNING id, date, active",
+            date
+        )
+        .fetch_all(&mut *conn)
+        .await,
+    )?
+    .remove(0);
+    log!(Level::Debug, "Started house meeting ID={}", meeting.id);
+
+    // Members on co-op this semester aren't expected at house meetings
+    log_query(
+        query!(
+            "INSERT INTO member_hm_attendance (uid, meeting_id, attendance_status)
+                SELECT a.uid, $2, 'Absent'
+                FROM UNNEST($1::varchar[]) AS a(uid)
+                WHERE a.uid NOT IN (
+                    SELECT uid FROM current_coops
+                    WHERE date_created > $3 AND semester = $4)",
+            members,
+            meeting.id,
+            NaiveDate::from(*year_start),
+            CoopSemester::containing(&meeting.date) as CoopSemester
+        )
+        .execute(&mut *conn)
+        .await
+        .map(|_| ()),
+    )?;
+
+    log_query(
+        query!(
+            "INSERT INTO freshman_hm_attendance (fid, meeting_id, attendance_status)
+                SELECT id, $1, 'Absent' FROM freshman_accounts WHERE eval_date > $2",
+            meeting.id,
+            NaiveDate::from(*year_start)
+        )
+        .execute(&mut *conn)
+        .await
+        .map(|_| ()),
+    )?;
+    log!(Level::Trace, "Populated house meeting roster");
+    Ok(meeting)
+}
+
+/// Dates of the house meetings after `since` that `person` was absent from
+pub async fn get_absences(
+    db: &Pool<Postgres>,
+    person: &Person,
+    since: &NaiveDateTime,
+) -> Result<Vec<Date>, AppError> {
+    let since = NaiveDate::from(*since);
+    match person {
+        Person::Freshman(fid) => log_query_as(query_as!(Date, "SELECT date FROM house_meetings WHERE date > $1 AND active AND id IN (SELECT meeting_id FROM freshman_hm_attendance WHERE fid = $2 AND attendance_status = 'Absent')", &since, fid).fetch_all(db).await),
+        Person::Member(uid) => log_query_as(query_as!(Date, "SELECT date FROM house_meetings WHERE date > $1 AND active AND id IN (SELECT meeting_id FROM member_hm_attendance WHERE uid = $2 AND attendance_status = 'Absent')", &since, uid).fetch_all(db).await),
+    }
+}
+
+/// Every house meeting after `since` that `person` didn't attend, and why
+pub async fn get_missed_house_meetings(
+    db: &Pool<Postgres>,
+    person: &Person,
+    since: &NaiveDateTime,
+) -> Result<Vec<EvalsHmAtt>, AppError> {
+    let since = NaiveDate::from(*since);
+    match person {
+        Person::Freshman(fid) => log_query_as(query_as!(EvalsHmAtt, "select attendance_status as \"attendance_status:_\", excuse, date from (select * from freshman_hm_attendance where fid = $2) as mha left join house_meetings on mha.meeting_id = house_meetings.id where date > $1 and active and attendance_status != 'Attended'", since, fid).fetch_all(db).await),
+        Person::Member(uid) => log_query_as(query_as!(EvalsHmAtt, "select attendance_status as \"attendance_status:_\", excuse, date from (select * from member_hm_attendance where uid = $2) as mha left join house_meetings on mha.meeting_id = house_meetings.id where date > $1 and active and attendance_status != 'Attended'", since, uid).fetch_all(db).await),
+    }
+}
+
+/// Set `person`'s attendance at the house meetings held on `attendance.date`
+pub async fn set_attendance_on(
+    conn: &mut PgConnection,
+    person: &Person,
+    attendance: &EvalsHmAtt,
+) -> Result<(), AppError> {
+    let result = match person {
+        Person::Freshman(fid) => query!("UPDATE freshman_hm_attendance SET attendance_status = $1, excuse = $2 WHERE fid = $3 AND meeting_id IN (SELECT id FROM house_meetings WHERE date = $4)", attendance.attendance_status as AttendanceStatus, attendance.excuse, fid, attendance.date).execute(conn).await,
+        Person::Member(uid) => query!("UPDATE member_hm_attendance SET attendance_status = $1, excuse = $2 WHERE uid = $3 AND meeting_id IN (SELECT id FROM house_meetings WHERE date = $4)", attendance.attendance_status as AttendanceStatus, attendance.excuse, uid, attendance.date).execute(conn).await,
+    };
+    log_query(result.map(|_| ()))
+}
+
+/// House meetings held after `since`, most recent first
+pub async fn get_house_meetings(
+    db: &Pool<Postgres>,
+    since: &NaiveDateTime,
+) -> Result<Vec<HouseMeeting>, AppError> {
+    log_query_as(
+        query_as!(
+            HouseMeeting,
+            "SELECT id, date, active FROM house_meetings WHERE date > $1 ORDER BY date DESC",
+            NaiveDate::from(*since)
+        )
+        .fetch_all(db)
+        .await,
+    )
+}
+
+/// House meeting `id` with the status of everyone on its roster
+pub async fn get_house_meeting(
+    db: &Pool<Postgres>,
+    id: i32,
+) -> Result<HouseMeetingRoster, AppError> {
+    let meeting = match log_query_as(
+        query_as!(
+            HouseMeeting,
+            "SELECT id, date, active FROM house_meetings WHERE id = $1",
+            id
+        )
+        .fetch_all(db)
+        .await,
+    )?
+    .pop()
+    {
+        Some(hm) => hm,
+        None => return Err(AppError::NotFound(String::from("House meeting not found"))),
+    };
+
+    let members = log_query_as(
+        query_as!(
+            MemberHouseMeetingStatus,
+            "SELECT uid,
+                    attendance_status AS \"attendance_status!: AttendanceStatus\",
+                    excuse
+                FROM member_hm_attendance
+                WHERE meeting_id = $1
+                ORDER BY uid",
+            id
+        )
+        .fetch_all(db)
+        .await,
+    )?;
+
+    let frosh = log_query_as(
+        query_as!(
+            FreshmanHouseMeetingStatus,
+            "SELECT fha.fid,
+                    fa.name,
+                    fha.attendance_status AS \"attendance_status!: AttendanceStatus\",
+                    fha.excuse
+                FROM freshman_hm_attendance fha
+                INNER JOIN freshman_accounts fa ON
+                    fa.id = fha.fid
+                WHERE fha.meeting_id = $1
+                ORDER BY fa.name",
+            id
+        )
+        .fetch_all(db)
+        .await,
+    )?;
+
+    Ok(HouseMeetingRoster {
+        id: meeting.id,
+        date: meeting.date,
+        active: meeting.active,
+        members,
+        frosh,
+    })
+}
+
+/// Cancel or restore house meeting `id`
+pub async fn set_house_meeting_active(
+    conn: &mut PgConnection,
+    id: i32,
+    active: bool,
+) -> Result<(), AppError> {
+    let ids = log_query_as(
+        query_as!(
+            ID,
+            "UPDATE house_meetings SET active = $1 WHERE id = $2 RETURNING id",
+            active,
+            id
+        )
+        .fetch_all(conn)
+        .await,
+    )?;
+    if ids.is_empty() {
+        return Err(AppError::NotFound(String::from("House meeting not found")));
+    }
+    Ok(())
+}
+
+/// Delete house meeting `id` and all of its attendance
+pub async fn delete_house_meeting(conn: &mut PgConnection, id: i32) -> Result<(), AppError> {
+    log_query(
+        query!(
+            "DELETE FROM freshman_hm_attendance WHERE meeting_id = $1",
+            id
+        )
+        .execute(&mut *conn)
+        .await
+        .map(|_| ()),
+    )?;
+    log_query(
+        query!("DELETE FROM member_hm_attendance WHERE meeting_id = $1", id)
+            .execute(&mut *conn)
+            .await
+            .map(|_| ()),
+    )?;
+    let ids = log_query_as(
+        query_as!(
+            ID,
+            "DELETE FROM house_meetings WHERE id = $1 RETURNING id",
+            id
+        )
+        .fetch_all(&mut *conn)
+        .await,
+    )?;
+    if ids.is_empty() {
+        return Err(AppError::NotFound(String::from("House meeting not found")));
+    }
+    Ok(())
+}
+
+/// Request for `person` to be excused from house meeting `meeting_id`.
+/// Only one request per person can be pending for a meeting.
+pub async fn submit_excuse(
+    conn: &mut PgConnection,
+    meeting_id: i32,
+    person: &Person,
+    excuse: &str,
+) -> Result<(), AppError> {
+    let meetings = log_query_as(
+        query_as!(
+            ID,
+            "SELECT id FROM house_meetings WHERE id = $1",
+            meeting_id
+        )
+        .fetch_all(&mut *conn)
+        .await,
+    )?;
+    if meetings.is_empty() {
+        return Err(AppError::NotFound(String::from("House meeting not found")));
+    }
+
+    let (uid, fid) = match person {
+        Person::Freshman(fid) => (None, Some(*fid)),
+        Person::Member(uid) => (Some(uid.as_str()), None),
+    };
+    let inserted = log_query_as(
+        query_as!(
+            ID,
+            "INSERT INTO hm_excuse_requests (meeting_id, uid, fid, excuse, submitted)
+                SELECT $1, $2::varchar, $3::int4, $4, $5
+                WHERE NOT EXISTS (
+                    SELECT 1 FROM hm_excuse_requests
+                    WHERE meeting_id = $1 AND status = 'Pending'
+                    AND (uid = $2 OR fid = $3))
+                RETURNING id",
+            meeting_id,
+            uid,
+            fid,
+            excuse,
+            Utc::now().naive_utc()
+        )
+        .fetch_all(&mut *conn)
+        .await,
+    )?;
+    if inserted.is_empty() {
+        return Err(AppError::Conflict(String::from(
+            "An excuse request for this house meeting is already pending",
+        )));
+    }
+    Ok(())
+}
+
+/// Excuse requests awaiting a decision, oldest first
+pub async fn get_pending_excuses(db: &Pool<Postgres>) -> Result<Vec<ExcuseRequest>, AppError> {
+    log_query_as(
+        query_as!(
+            ExcuseRequest,
+            "SELECT er.id,
+                    er.meeting_id,
+                    hm.date,
+                    er.uid,
+                    er.fid,
+                    fa.name AS \"name?\",
+                    er.excuse,
+                    er.status AS \"status: ExcuseStatus\",
+                    er.submitted
+                FROM hm_excuse_requests er
+                INNER JOIN house_meetings hm ON
+                    hm.id = er.meeting_id
+                LEFT JOIN freshman_accounts fa ON
+                    fa.id = er.fid
+                WHERE er.status = 'Pending'
+                ORDER BY er.submitted"
+        )
+        .fetch_all(db)
+        .await,
+    )
+}
+
+/// Accept or deny pending excuse request `id`. Accepting it excuses the
+/// requester from the meeting.
+pub async fn decide_excuse(
+    conn: &mut PgConnection,
+    id: i32,
+    decision: ExcuseStatus,
+) -> Result<(), AppError> {
+    let request: HouseMeetingExcuse = match log_query_as(
+        query_as!(
+            HouseMeetingExcuse,
+            "UPDATE hm_excuse_requests SET status = $2
+                WHERE id = $1 AND status = 'Pending'
+                RETURNING id, meeting_id, uid, fid, excuse,
+                    status AS \"status: ExcuseStatus\", submitted",
+            id,
+            decision as ExcuseStatus
+        )
+        .fetch_all(&mut *conn)
+        .await,
+    )?
+    .pop()
+    {
+        Some(r) => r,
+        None => return Err(AppError::NotFound(String::from("Excuse request not found"))),
+    };
+
+    if decision != ExcuseStatus::Accepted {
+        return Ok(());
+    }
+
+    // Excuses may be submitted before the roster exists, so add the
+    // requester to the meeting if they aren't on it yet
+    let result = match (request.uid, request.fid) {
+        (Some(uid), _) => {
+            query!(
+                "WITH updated AS (
+                    UPDATE member_hm_attendance
+                    SET attendance_status = 'Excused', excuse = $3
+                    WHERE uid = $1 AND meeting_id = $2
+                    RETURNING id
+                ) INSERT INTO member_hm_attendance (uid, meeting_id, excuse, attendance_status)
+                SELECT $1, $2, $3, 'Excused' WHERE NOT EXISTS (SELECT 1 FROM updated)",
+                uid,
+                request.meeting_id,
+                request.excuse
+            )
+            .execute(&mut *conn)
+            .await
+        }
+        (None, Some(fid)) => {
+            query!(
+                "WITH updated AS (
+                    UPDATE freshman_hm_attendance
+                    SET attendance_status = 'Excused', excuse = $3
+                    WHERE fid = $1 AND meeting_id = $2
+                    RETURNING id
+                ) INSERT INTO freshman_hm_attendance (fid, meeting_id, excuse, attendance_status)
+                SELECT $1, $2, $3, 'Excused' WHERE NOT EXISTS (SELECT 1 FROM updated)",
+                fid,
+                request.meeting_id,
+                request.excuse
+            )
+            .execute(&mut *conn)
+            .await
+        }
+        (None, None) => {
+            return Err(AppError::Internal(format!(
+                "Excuse request {} has no requester",
+                request.id
+            )));
+        }
+    };
+    log_query(result.map(|_| ()))
+}
+
+/// Change `person`'s status at house meeting `meeting_id`
+pub async fn edit_attendee(
+    conn: &mut PgConnection,
+    meeting_id: i32,
+    person: &Person,
+    edit: &HouseMeetingAttendanceEdit,
+) -> Result<(), AppError> {
+    let updated = match person {
+        Person::Freshman(fid) => {
+            query_as!(
+                ID,
+                "UPDATE freshman_hm_attendance SET attendance_status = $1, excuse = $2
+                    WHERE meeting_id = $3 AND fid = $4 RETURNING id",
+                edit.attendance_status as AttendanceStatus,
+                edit.excuse,
+                meeting_id,
+                fid
+            )
+            .fetch_all(&mut *conn)
+            .await
+        }
+        Person::Member(uid) => {
+            query_as!(
+                ID,
+                "UPDATE member_hm_attendance SET attendance_status = $1, excuse = $2
+                    WHERE meeting_id = $3 AND uid = $4 RETURNING id",
+                edit.attendance_status as AttendanceStatus,
+                edit.excuse,
+                meeting_id,
+                uid
+            )
+            .fetch_all(&mut *conn)
+            .await
+        }
+    };
+    if log_query_as(updated)?.is_empty() {
+        return Err(AppError::NotFound(String::from(
+            "Attendee not on house meeting roster",
+        )));
+    }
+    Ok(())
+}
+
+/// Add `person` to house meeting `meeting_id`'s roster, if they aren't on it
+/// already
+pub async fn add_attendee(
+    conn: &mut PgConnection,
+    meeting_id: i32,
+    person: &Person,
+    edit: &HouseMeetingAttendanceEdit,
+) -> Result<(), AppError> {
+    let meetings = log_query_as(
+        query_as!(
+            ID,
+            "SELECT id FROM house_meetings WHERE id = $1 FOR UPDATE",
+            meeting_id
+        )
+        .fetch_all(&mut *conn)
+        .await,
+    )?;
+    if meetings.is_empty() {
+        return Err(AppError::NotFound(String::from("House meeting not found")));
+    }
+
+    let inserted = match person {
+        Person::Freshman(fid) => {
+            query_as!(
+                ID,
+                "INSERT INTO freshman_hm_attendance (fid, meeting_id, excuse, attendance_status)
+                    SELECT $1, $2, $3, $4
+                    WHERE NOT EXISTS (
+                        SELECT 1 FROM freshman_hm_attendance WHERE fid = $1 AND meeting_id = $2)
+                    RETURNING id",
+                fid,
+                meeting_id,
+                edit.excuse,
+                edit.attendance_status as AttendanceStatus
+            )
+            .fetch_all(&mut *conn)
+            .await
+        }
+        Person::Member(uid) => {
+            query_as!(
+                ID,
+                "INSERT INTO member_hm_attendance (uid, meeting_id, excuse, attendance_status)
+                    SELECT $1::varchar, $2, $3, $4
+                    WHERE NOT EXISTS (
+                        SELECT 1 FROM member_hm_attendance WHERE uid = $1 AND meeting_id = $2)
+                    RETURNING id",
+                uid,
+                meeting_id,
+                edit.excuse,
+                edit.attendance_status as AttendanceStatus
+            )
+            .fetch_all(&mut *conn)
+            .await
+        }
+    };
+    if log_query_as(inserted)?.is_empty() {
+        return Err(AppError::Conflict(String::from(
+            "Attendee already on house meeting roster",
+        )));
+    }
+    Ok(())
+}
@@ -0,0 +1,181 @@
+use crate::error::{log_query, log_query_as, AppError};
+use crate::repo::Person;
+use crate::schema::api::{Seminar, ID};
+use chrono::NaiveDateTime;
+use sqlx::{query, query_as, PgConnection, Pool, Postgres};
+
+/// Add an unapproved technical seminar hosted by `host`, returning its id
+pub async fn create_seminar(
+    conn: &mut PgConnection,
+    name: &str,
+    timestamp: &NaiveDateTime,
+    host: Option<&str>,
+) -> Result<i32, AppError> {
+    Ok(log_query_as(
+        query_as!(
+            ID,
+            "INSERT INTO technical_seminars (name, timestamp, active, approved, host)
+                VALUES ($1, $2, $3, $4, $5) RETURNING id",
+            name,
+            timestamp,
+            true,
+            false,
+            host
+        )
+        .fetch_all(conn)
+        .await,
+    )?[0]
+        .id)
+}
+
+/// Record `frosh` and `members` as having attended seminar `id`
+pub async fn add_seminar_attendance(
+    conn: &mut PgConnection,
+    id: i32,
+    frosh: &[i32],
+    members: &[String],
+) -> Result<(), AppError> {
+    let frosh_id = vec![id; frosh.len()];
+    let member_id = vec![id; members.len()];
+
+    // Add frosh, seminar relation
+    log_query(
+        query!(
+            "INSERT INTO freshman_seminar_attendance (fid, seminar_id)
+                SELECT fid, seminar_id
+                FROM UNNEST($1::int4[], $2::int4[]) AS a(fid, seminar_id)",
+            frosh,
+            frosh_id.as_slice()
+        )
+        .execute(&mut *conn)
+        .await
+        .map(|_| ()),
+    )?;
+
+    // Add member, seminar relation
+    log_query(
+        query!(
+            "INSERT INTO member_seminar_attendance (uid, seminar_id)
+                SELECT uid, seminar_id
+                FROM UNNEST($1::TEXT[], $2::int4[]) AS a(uid, seminar_id)",
+            members,
+            member_id.as_slice()
+        )
+        .execute(&mut *conn)
+        .await
+        .map(|_| ()),
+    )
+}
+
+/// Remove everyone's attendance at seminar `id`
+pub async fn delete_seminar_attendance(conn: &mut PgConnection, id: i32) -> Result<(), AppError> {
+    log_query(
+        query!(
+            "DELETE FROM freshman_seminar_attendance WHERE seminar_id = $1",
+            id
+        )
+        .execute(&mut *conn)
+        .await
+        .map(|_| ()),
+    )?;
+    log_query(
+        query!(
+            "DELETE FROM member_seminar_attendance WHERE seminar_id = $1",
+            id
+        )
+        .execute(&mut *conn)
+        .await
+        .map(|_| ()),
+    )
+}
+
+/// Delete seminar `id` and its attendance
+pub async fn delete_seminar(conn: &mut PgConnection, id: i32) -> Result<(), AppError> {
+    delete_seminar_attendance(conn, id).await?;
+    log_query(
+        query!("DELETE FROM technical_seminars WHERE id = $1", id)
+            .execute(&mut *conn)
+            .await
+            .map(|_| ()),
+    )
+}
+
+/// Approved seminars `person` attended after `since`, without attendance
+pub async fn get_seminars_attended(
+    db: &Pool<Postgres>,
+    person: &Person,
+    since: &NaiveDateTime,
+) -> Result<Vec<Seminar>, AppError> {
+    match person {
+        Person::Freshman(fid) => log_query_as(
+            query_as!(
+                Seminar,
+                "SELECT ts.name,
+                        ts.\"timestamp\",
+                        ARRAY[]::varchar[] AS members,
+                        ARRAY[]::integer[] AS frosh,
+                        ts.approved
+                    FROM technical_seminars ts
+                    LEFT JOIN freshman_seminar_attendance fsa ON
+                        fsa.seminar_id = ts.id
+                    WHERE ts.approved
+                    AND timestamp > $1::timestamp
+                    AND fsa.fid = $2::int4",
+                since,
+                fid
+            )
+            .fetch_all(db)
+            .await,
+        ),
+        Person::Member(uid) => log_query_as(
+            query_as!(
+                Seminar,
+                "SELECT ts.name,
+                        ts.\"timestamp\",
+                        ARRAY[]::varchar[] AS members,
+                        ARRAY[]::integer[] AS frosh,
+                        ts.approved
+                    FROM technical_seminars ts
+                    LEFT JOIN member_seminar_attendance msa ON
+                        msa.seminar_id = ts.id
+                    WHERE ts.approved
+                    AND timestamp > $1::timestamp
+                    AND msa.uid = $2",
+                since,
+                uid
+            )
+            .fetch_all(db)
+            .await,
+        ),
+    }
+}
+
+/// Seminars held after `since`, with everyone who attended
+pub async fn get_seminars(
+    db: &Pool<Postgres>,
+    since: &NaiveDateTime,
+) -> Result<Vec<Seminar>, AppError> {
+    log_query_as(
+        query_as!(
+            Seminar,
+            "SELECT member_seminars.name,
+                member_seminars.timestamp,
+                member_seminars.members,
+                array_agg(fsa.fid) AS frosh,
+                member_seminars.approved
+            FROM(SELECT ts.id, ts.name, ts.timestamp, array_agg(msa.uid) AS members, ts.approved
+                 FROM technical_seminars ts
+                 INNER JOIN member_seminar_attendance msa ON
+                     msa.seminar_id = ts.id
+                 WHERE timestamp > $1::timestamp
+                 GROUP BY ts.id, ts.name, ts.timestamp, ts.approved) AS member_seminars
+            INNER JOIN freshman_seminar_attendance fsa ON
+                fsa.seminar_id = member_seminars.id
+            GROUP BY member_seminars.id, member_seminars.name, member_seminars.timestamp, \
+             member_seminars.members, member_seminars.approved",
+            since
+        )
+        .fetch_all(db)
+        .await,
+    )
+}
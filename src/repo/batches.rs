@@ -0,0 +1,346 @@
+use crate::error::{log_query, log_query_as, AppError};
+use crate::repo::Person;
+use crate::schema::api::{
+    Batch, BatchSubmission, FreshmanPull, IntroStatus, MemberPull, PullRequests, ID,
+};
+use crate::schema::db::{BatchComparison, BatchConditionType, FreshmanEvalStatus, PacketRounding};
+use sqlx::{query, query_as, PgConnection, Pool, Postgres};
+
+/// What batch conditions are checked against for each intro member, a column
+/// at a time so they can be passed to UNNEST
+#[derive(Default)]
+struct IntroColumns {
+    name: Vec<String>,
+    uid: Vec<Option<String>>,
+    /// Freshman account id, or 0 for members
+    fid: Vec<i32>,
+    seminars: Vec<i64>,
+    directorships: Vec<i64>,
+    missed_hms: Vec<i64>,
+    /// Packet completion, rounded to a whole percentage
+    packet: Vec<i64>,
+}
+
+/// Every batch with the members of `intros` in it. A member is in a batch if
+/// they were added to it, or if they meet all of its conditions. Anyone
+/// pulled from batches is left out.
+pub async fn get_batches(
+    conn: &mut PgConnection,
+    intros: Vec<IntroStatus>,
+    rounding: PacketRounding,
+) -> Result<Vec<Batch>, AppError> {
+    let mut columns = IntroColumns::default();
+    for is in intros {
+        columns.name.push(is.name);
+        columns.uid.push(is.uid);
+        columns.fid.push(is.fid.unwrap_or(0));
+        columns.seminars.push(is.seminars);
+        columns.directorships.push(is.directorships);
+        columns.missed_hms.push(is.missed_hms);
+        columns.packet.push(rounding.round(is.packet_completion));
+    }
+    let IntroColumns {
+        name,
+        uid,
+        fid,
+        seminars,
+        directorships,
+        missed_hms,
+        packet,
+    } = columns;
+    log_query_as(
+        // I'm so sorry for anyone who needs to touch this ever
+        query_as!(
+            Batch,
+            "
+SELECT batch.id as \"id!\", batch.name AS \"name!\", batch.uid AS \"creator!\", bi.conditions AS \
+             \"conditions!\", bi.members AS \"members!\"
+FROM (SELECT cb.bid, cb.conditions, array_agg(DISTINCT concat(cb.mname, ',', cb.uid)) AS members
+FROM (
+SELECT batches.bid
+, array_agg(concat(batches.\"condition\", ' ', batches.comparison, ' ', batches.value)) AS \
+             conditions
+, batches.mname, batches.uid, batches.fid
+FROM (SELECT baid.bid, baid.mname, baid.fid, baid.uid, bc.\"condition\", bc.comparison, bc.value,
+CASE
+	WHEN baid.bu THEN TRUE
+	WHEN bc.\"condition\" = 'packet' AND bc.comparison = 'greater' THEN evals.packet > bc.value
+	WHEN bc.\"condition\" = 'packet' AND bc.comparison = 'equal' THEN evals.packet = bc.value
+	WHEN bc.\"condition\" = 'packet' AND bc.comparison = 'less' THEN evals.packet < bc.value
+	WHEN bc.\"condition\" = 'seminar' AND bc.comparison = 'greater' THEN evals.ss > bc.value
+	WHEN bc.\"condition\" = 'seminar' AND bc.comparison = 'equal' THEN evals.ss = bc.value
+	WHEN bc.\"condition\" = 'seminar' AND bc.comparison = 'less' THEN evals.ss < bc.value
+	WHEN bc.\"condition\" = 'committee' AND bc.comparison = 'greater' THEN evals.ds > bc.value
+	WHEN bc.\"condition\" = 'committee' AND bc.comparison = 'equal' THEN evals.ds = bc.value
+	WHEN bc.\"condition\" = 'committee' AND bc.comparison = 'less' THEN evals.ds < bc.value
+	WHEN bc.\"condition\" = 'house' AND bc.comparison = 'greater' THEN evals.hm > bc.value
+	WHEN bc.\"condition\" = 'house' AND bc.comparison = 'equal' THEN evals.hm = bc.value
+	WHEN bc.\"condition\" = 'house' AND bc.comparison = 'less' THEN evals.hm < bc.value
+	ELSE false
+END AS cond_passed
+FROM (SELECT baid.bid, baid.mname, baid.fid, baid.uid, bool_or(baid.bu) AS bu
+FROM (SELECT *
+FROM (SELECT fbu.batch_id, evals.name, fbu.fid, NULL AS uid, TRUE AS bu
+	FROM freshman_batch_users fbu
+	LEFT JOIN (
+	SELECT evals._ AS uid, evals.name, evals.fid
+	FROM (SELECT *
+	FROM UNNEST($1::varchar[], $2::varchar[], $3::int8[], $4::int8[], $5::int8[], $6::int8[], \
+             $7::int4[])) AS evals(\"name\", _, ss, ds, hm, packet, fid)
+	) evals
+	ON fbu.fid = evals.fid) AS frosh_info
+UNION (
+	SELECT mbu.batch_id, evals.name, NULL AS fid, mbu.uid, TRUE AS bu
+	FROM member_batch_users mbu 
+	LEFT JOIN (
+	SELECT evals._ AS uid, evals.name, evals.fid
+	FROM (SELECT *
+	FROM UNNEST($1::varchar[], $2::varchar[], $3::int8[], $4::int8[], $5::int8[], $6::int8[], \
+             $7::int4[])) AS evals(\"name\", _, ss, ds, hm, packet, fid)
+	) evals
+	ON mbu.uid = evals.uid)
+UNION (
+	SELECT batch.id, evals.name, CASE WHEN evals.fid != 0 THEN evals.fid ELSE NULL END, evals.uid, \
+             FALSE AS bu
+	FROM batch,
+		(SELECT * FROM UNNEST($1::varchar[], $2::varchar[], $3::int8[], $4::int8[], $5::int8[], \
+             $6::int8[], $7::int4[])) AS evals(\"name\", uid, ss, ds, hm, packet, fid)
+)) AS baid(bid, mname, fid, uid, bu)
+GROUP BY baid.bid, baid.mname, baid.fid, baid.uid) AS baid
+LEFT JOIN batch_conditions bc ON bc.batch_id=baid.bid
+LEFT JOIN (
+	SELECT evals.uid, evals.fid, evals.ss, evals.ds, evals.hm, evals.packet
+	FROM (SELECT *
+	FROM UNNEST($1::varchar[], $2::varchar[], $3::int8[], $4::int8[], $5::int8[], $6::int8[], \
+             $7::int4[])) AS evals(\"name\", uid, ss, ds, hm, packet, fid)
+	) evals ON evals.uid=baid.uid OR evals.fid=baid.fid
+WHERE NOT EXISTS (SELECT 1 FROM freshman_batch_pulls fbp WHERE fbp.approved AND fbp.fid=baid.fid)
+AND NOT EXISTS (SELECT 1 FROM member_batch_pulls mbp WHERE mbp.approved AND mbp.uid=baid.uid)) AS \
+             batches
+--WHERE cond_passed
+GROUP BY batches.bid, batches.mname, batches.uid, batches.fid
+HAVING bool_and(batches.cond_passed)) AS cb
+GROUP BY cb.bid, cb.conditions) AS bi --thats gay
+LEFT JOIN batch ON bi.bid=batch.id
+",
+            &name,
+            &uid as _,
+            &seminars,
+            &directorships,
+            &missed_hms,
+            &packet,
+            &fid
+        )
+        .fetch_all(conn)
+        .await,
+    )
+}
+
+/// Add an unapproved batch created by `creator`, returning its id
+pub async fn create_batch(
+    conn: &mut PgConnection,
+    creator: &str,
+    batch: &BatchSubmission,
+) -> Result<i32, AppError> {
+    let id = log_query_as(
+        query_as!(
+            ID,
+            "INSERT INTO batch(name, uid, approved) VALUES ($1, $2, $3) RETURNING id",
+            batch.name,
+            creator,
+            false
+        )
+        .fetch_all(&mut *conn)
+        .await,
+    )?[0]
+        .id;
+
+    // add conditions
+    let values = batch.conditions.iter().map(|a| a.value).collect::<Vec<_>>();
+    let conditions = batch
+        .conditions
+        .iter()
+        .map(|a| a.condition)
+        .collect::<Vec<_>>();
+    let comparisons = batch
+        .conditions
+        .iter()
+        .map(|a| a.comparison)
+        .collect::<Vec<_>>();
+    let batch_ids = vec![id; values.len()];
+
+    log_query(
+        query!(
+            "INSERT INTO batch_conditions(value, condition, comparison, batch_id) SELECT value as \
+             \"value!\", condition AS \"condition!:_\", comparison AS \"comparison!:_\", batch_id \
+             as \"batch_id!\" FROM UNNEST($1::int4[], $2::batch_ctype_enum[], \
+             $3::batch_comparison[], $4::int4[]) as a(value, condition, comparison, batch_id)",
+            values.as_slice(),
+            conditions.as_slice() as &[BatchConditionType],
+            comparisons.as_slice() as &[BatchComparison],
+            batch_ids.as_slice()
+        )
+        .execute(&mut *conn)
+        .await
+        .map(|_| ()),
+    )?;
+
+    // add users
+    let fids = batch
+        .freshman_users
+        .iter()
+        .map(|a| a.fid)
+        .collect::<Vec<_>>();
+    let batch_ids = vec![id; fids.len()];
+
+    log_query(
+        query!(
+            "INSERT INTO freshman_batch_users(fid, batch_id) SELECT fid, batch_id FROM \
+             UNNEST($1::int4[], $2::int4[]) as a(fid, batch_id)",
+            fids.as_slice(),
+            batch_ids.as_slice()
+        )
+        .execute(&mut *conn)
+        .await
+        .map(|_| ()),
+    )?;
+
+    let uids = batch
+        .member_users
+        .iter()
+        .map(|a| a.uid.clone())
+        .collect::<Vec<_>>();
+    let batch_ids = vec![id; uids.len()];
+
+    log_query(
+        query!(
+            "INSERT INTO member_batch_users(uid, batch_id) SELECT uid, batch_id FROM \
+             UNNEST($1::text[], $2::int4[]) as a(uid, batch_id)",
+            uids.as_slice(),
+            batch_ids.as_slice()
+        )
+        .execute(&mut *conn)
+        .await
+        .map(|_| ()),
+    )?;
+    Ok(id)
+}
+
+/// Pull `person` from every batch, replacing any pull request they had
+pub async fn pull(conn: &mut PgConnection, person: &Person) -> Result<(), AppError> {
+    match person {
+        Person::Freshman(fid) => {
+            log_query(
+                query!("DELETE FROM freshman_batch_pulls WHERE fid = $1", fid)
+                    .execute(&mut *conn)
+                    .await
+                    .map(|_| ()),
+            )?;
+            log_query(
+                query!(
+                    "INSERT INTO freshman_batch_pulls(fid, approved) VALUES ($1, true)",
+                    fid
+                )
+                .execute(&mut *conn)
+                .await
+                .map(|_| ()),
+            )
+        }
+        Person::Member(uid) => {
+            log_query(
+                query!("DELETE FROM member_batch_pulls WHERE uid = $1", uid)
+                    .execute(&mut *conn)
+                    .await
+                    .map(|_| ()),
+            )?;
+            log_query(
+                query!(
+                    "INSERT INTO member_batch_pulls(uid, approved) VALUES ($1, true)",
+                    uid
+                )
+                .execute(&mut *conn)
+                .await
+                .map(|_| ()),
+            )
+        }
+    }
+}
+
+/// Ask for `person` to be pulled from batches, unless they already have a
+/// pull or a request for one
+pub async fn request_pull(
+    conn: &mut PgConnection,
+    person: &Person,
+    puller: &str,
+    reason: &str,
+) -> Result<(), AppError> {
+    let result = match person {
+        Person::Freshman(fid) => {
+            query!(
+                "INSERT INTO freshman_batch_pulls(fid, approved, puller, reason) VALUES ($1, \
+                 false, $2, $3) ON CONFLICT DO NOTHING",
+                fid,
+                puller,
+                reason
+            )
+            .execute(conn)
+            .await
+        }
+        Person::Member(uid) => {
+            query!(
+                "INSERT INTO member_batch_pulls(uid, approved, puller, reason) VALUES ($1, false, \
+                 $2, $3) ON CONFLICT DO NOTHING",
+                uid,
+                puller,
+                reason
+            )
+            .execute(conn)
+            .await
+        }
+    };
+    log_query(result.map(|_| ()))
+}
+
+/// Pull requests that haven't been approved
+pub async fn get_pull_requests(db: &Pool<Postgres>) -> Result<PullRequests, AppError> {
+    let frosh = log_query_as(
+        query_as!(
+            FreshmanPull,
+            "select fid, reason, puller from freshman_batch_pulls where approved = false"
+        )
+        .fetch_all(db)
+        .await,
+    )?;
+    let members = log_query_as(
+        query_as!(
+            MemberPull,
+            "select uid, reason, puller from member_batch_pulls where approved = false"
+        )
+        .fetch_all(db)
+        .await,
+    )?;
+    Ok(PullRequests { frosh, members })
+}
+
+/// Set the intro evaluation result of each of `uids`
+pub async fn set_eval_results(
+    conn: &mut PgConnection,
+    uids: &[String],
+    result: FreshmanEvalStatus,
+) -> Result<(), AppError> {
+    log_query(
+        query!(
+            "
+    UPDATE freshman_eval_data
+    SET freshman_eval_result=$2
+    FROM UNNEST($1::varchar[]) as uids
+    WHERE freshman_eval_data.uid = uids
+    ",
+            uids,
+            result as FreshmanEvalStatus,
+        )
+        .execute(conn)
+        .await
+        .map(|_| ()),
+    )
+}
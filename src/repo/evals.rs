@@ -0,0 +1,210 @@
+use crate::error::{ldap_error, log_query_as, AppError};
+use crate::identity::rit_username_key;
+use crate::ldap::{directory::Directory, search::SearchAttrs, user::LdapUser};
+use crate::packet;
+use crate::schema::api::{IntroStatus, MemberStatus, Packet};
+use crate::schema::db::PacketSettings;
+use chrono::NaiveDateTime;
+use sqlx::{query_as, PgConnection, Pool, Postgres};
+use std::collections::HashMap;
+
+/// Attendance counted towards a member's evaluation
+struct MemberCounts {
+    uid: String,
+    seminars: i64,
+    directorships: i64,
+    missed_hms: i64,
+    major_projects: i64,
+}
+
+/// Attendance counted towards a freshman's evaluation
+struct FreshmanCounts {
+    fid: i32,
+    rit_username: Option<String>,
+    seminars: i64,
+    directorships: i64,
+    missed_hms: i64,
+}
+
+/// Approved seminars and directorships attended, active house meetings
+/// missed and major projects passed by each of `uids`, in the same order.
+/// Everything is counted if `since` is None.
+async fn get_member_counts(
+    conn: &mut PgConnection,
+    uids: &[String],
+    since: Option<&NaiveDateTime>,
+) -> Result<Vec<MemberCounts>, AppError> {
+    log_query_as(
+        query_as!(
+            MemberCounts,
+            "SELECT u.uid AS \"uid!\",
+                    (SELECT count(*) FROM member_seminar_attendance msa
+                        INNER JOIN technical_seminars ts ON ts.id = msa.seminar_id
+                        WHERE msa.uid = u.uid AND ts.approved
+                        AND ($2::timestamp IS NULL OR ts.timestamp > $2)) AS \"seminars!\",
+                    (SELECT count(*) FROM member_committee_attendance mca
+                        INNER JOIN committee_meetings cm ON cm.id = mca.meeting_id
+                        WHERE mca.uid = u.uid AND cm.approved
+                        AND ($2::timestamp IS NULL OR cm.timestamp > $2)) AS \"directorships!\",
+                    (SELECT count(*) FROM member_hm_attendance mha
+                        INNER JOIN house_meetings hm ON hm.id = mha.meeting_id
+                        WHERE mha.uid = u.uid AND mha.attendance_status = 'Absent' AND hm.active
+                        AND ($2::timestamp IS NULL OR hm.date > $2)) AS \"missed_hms!\",
+                    (SELECT count(*) FROM major_projects mp
+                        WHERE mp.uid = u.uid AND mp.status = 'Passed'
+                        AND ($2::timestamp IS NULL OR mp.date > $2)) AS \"major_projects!\"
+                FROM UNNEST($1::varchar[]) WITH ORDINALITY AS u(uid, n)
+                ORDER BY u.n",
+            uids,
+            since
+        )
+        .fetch_all(conn)
+        .await,
+    )
+}
+
+/// Approved seminars and directorships attended and active house meetings
+/// missed by every freshman account
+async fn get_freshman_counts(conn: &mut PgConnection) -> Result<Vec<FreshmanCounts>, AppError> {
+    log_query_as(
+        query_as!(
+            FreshmanCounts,
+            "SELECT fa.id AS fid,
+                    fa.rit_username,
+                    (SELECT count(*) FROM freshman_seminar_attendance fsa
+                        INNER JOIN technical_seminars ts ON ts.id = fsa.seminar_id
+                        WHERE fsa.fid = fa.id AND ts.approved) AS \"seminars!\",
+                    (SELECT count(*) FROM freshman_committee_attendance fca
+                        INNER JOIN committee_meetings cm ON cm.id = fca.meeting_id
+                        WHERE fca.fid = fa.id AND cm.approved) AS \"directorships!\",
+                    (SELECT count(*) FROM freshman_hm_attendance fha
+                        INNER JOIN house_meetings hm ON hm.id = fha.meeting_id
+                        WHERE fha.fid = fa.id AND fha.attendance_status = 'Absent'
+                        AND hm.active) AS \"missed_hms!\"
+                FROM freshman_accounts fa
+                ORDER BY fa.id"
+        )
+        .fetch_all(conn)
+        .await,
+    )
+}
+
+/// Evaluation status of each of `members`, counting everything after `since`
+pub async fn get_member_statuses(
+    db: &Pool<Postgres>,
+    members: &[LdapUser],
+    since: &NaiveDateTime,
+) -> Result<Vec<MemberStatus>, AppError> {
+    let uids: Vec<String> = members.iter().map(|m| m.uid.clone()).collect();
+    let counts = get_member_counts(&mut *db.acquire().await?, &uids, Some(since)).await?;
+    Ok(members
+        .iter()
+        .zip(counts)
+        .map(|(member, counts)| MemberStatus {
+            name: member.cn.clone(),
+            uid: counts.uid,
+            seminars: counts.seminars,
+            directorships: counts.directorships,
+            missed_hms: counts.missed_hms,
+            major_projects: counts.major_projects,
+        })
+        .collect())
+}
+
+/// Packets by the RIT username they belong to. Packets come newest first,
+/// so anyone with more than one is evaluated on their most recent.
+fn packets_by_username(packets: &[Packet]) -> HashMap<String, &Packet> {
+    let mut by_username = HashMap::new();
+    for packet in packets {
+        if let Some(key) = rit_username_key(&packet.username) {
+            by_username.entry(key).or_insert(packet);
+        }
+    }
+    by_username
+}
+
+fn intro_status(
+    fid: Option<i32>,
+    uid: Option<String>,
+    (seminars, directorships, missed_hms): (i64, i64, i64),
+    packet: &Packet,
+    settings: &PacketSettings,
+) -> IntroStatus {
+    IntroStatus {
+        fid,
+        name: packet.name.clone(),
+        uid,
+        seminars,
+        directorships,
+        missed_hms,
+        signatures: packet.signatures(settings),
+        max_signatures: packet.max_signatures(settings),
+        packet_completion: packet.completion(settings),
+    }
+}
+
+/// Evaluation status of every freshman account and every one of
+/// `intro_members`. Anyone without a packet is left out.
+pub async fn get_intro_statuses(
+    conn: &mut PgConnection,
+    intro_members: &[LdapUser],
+    packets: &[Packet],
+    settings: &PacketSettings,
+) -> Result<Vec<IntroStatus>, AppError> {
+    let packets = packets_by_username(packets);
+
+    let mut statuses: Vec<IntroStatus> = get_freshman_counts(&mut *conn)
+        .await?
+        .into_iter()
+        .filter_map(|counts| {
+            let packet = counts
+                .rit_username
+                .as_deref()
+                .and_then(rit_username_key)
+                .and_then(|key| packets.get(&key))?;
+            Some(intro_status(
+                Some(counts.fid),
+                None,
+                (counts.seminars, counts.directorships, counts.missed_hms),
+                packet,
+                settings,
+            ))
+        })
+        .collect();
+
+    let uids: Vec<String> = intro_members.iter().map(|m| m.uid.clone()).collect();
+    let counts = get_member_counts(conn, &uids, None).await?;
+    statuses.extend(
+        intro_members
+            .iter()
+            .zip(counts)
+            .filter_map(|(member, counts)| {
+                let packet =
+                    rit_username_key(&member.rit_username).and_then(|key| packets.get(&key))?;
+                Some(intro_status(
+                    None,
+                    Some(counts.uid),
+                    (counts.seminars, counts.directorships, counts.missed_hms),
+                    packet,
+                    settings,
+                ))
+            }),
+    );
+    Ok(statuses)
+}
+
+/// Evaluation status of everyone going through intro evals this year, from
+/// their packets, attendance and LDAP, with packets scored by `settings`
+pub async fn get_intro_evals(
+    conn: &mut PgConnection,
+    packet_db: &Pool<Postgres>,
+    directory: &dyn Directory,
+    settings: &PacketSettings,
+) -> Result<Vec<IntroStatus>, AppError> {
+    let report = packet::get_all_packets(packet_db).await?;
+    let intro_members = directory
+        .get_intro_members(&SearchAttrs::minimal())
+        .await
+        .map_err(ldap_error)?;
+    get_intro_statuses(conn, &intro_members, &report.packets, settings).await
+}
@@ -0,0 +1,20 @@
+use crate::error::{log_query_as, AppError};
+use crate::schema::api::IntroFormSubmission;
+use sqlx::{query_as, Pool, Postgres};
+
+/// Intro evaluation forms `uid` has submitted
+pub async fn get_intro_forms(
+    db: &Pool<Postgres>,
+    uid: &str,
+) -> Result<Vec<IntroFormSubmission>, AppError> {
+    log_query_as(
+        query_as!(
+            IntroFormSubmission,
+            "select uid, social_events, other_notes as comments from freshman_eval_data where uid \
+             = $1",
+            uid
+        )
+        .fetch_all(db)
+        .await,
+    )
+}
@@ -0,0 +1,553 @@
+use crate::error::{log_query_as, AppError};
+use crate::identity::rit_username_key;
+use crate::schema::api::{ConversionLogEntry, ConversionUndo, ConvertedData, NewIntroMember, ID};
+use crate::schema::db::{FreshmanAccount, FreshmanBatchPull, FreshmanConversion};
+use chrono::Utc;
+use log::{log, Level};
+use sqlx::{query, query_as, query_scalar, PgConnection, Pool, Postgres};
+use std::collections::HashSet;
+
+/// Add a freshman account, returning its id
+pub async fn create_freshman(
+    conn: &mut PgConnection,
+    freshman: &NewIntroMember,
+) -> Result<i32, AppError> {
+    Ok(log_query_as(
+        query_as!(
+            ID,
+            "INSERT INTO freshman_accounts (name, eval_date, onfloor_status, room_number, \
+             signatures_missed, rit_username)
+        VALUES ($1::varchar, $2::date, $3, $4::varchar, null, $5::varchar) RETURNING id",
+            freshman.name,
+            freshman.eval_date,
+            freshman.onfloor_status,
+            freshman.room_number,
+            freshman.rit_username
+        )
+        .fetch_all(conn)
+        .await,
+    )?[0]
+        .id)
+}
+
+/// Every freshman account
+pub async fn get_freshmen(db: &Pool<Postgres>) -> Result<Vec<FreshmanAccount>, AppError> {
+    log_query_as(
+        query_as!(
+            FreshmanAccount,
+            "SELECT id, name, eval_date, onfloor_status, room_number, signatures_missed,
+                rit_username
+            FROM freshman_accounts ORDER BY id"
+        )
+        .fetch_all(db)
+        .await,
+    )
+}
+
+/// Freshman account `fid`
+pub async fn get_freshman(conn: &mut PgConnection, fid: i32) -> Result<FreshmanAccount, AppError> {
+    match log_query_as(
+        query_as!(
+            FreshmanAccount,
+            "SELECT id, name, eval_date, onfloor_status, room_number, signatures_missed,
+                rit_username
+            FROM freshman_accounts WHERE id = $1",
+            fid
+        )
+        .fetch_all(conn)
+        .await,
+    )?
+    .pop()
+    {
+        Some(account) => Ok(account),
+        None => Err(AppError::NotFound(format!("No freshman account {fid}"))),
+    }
+}
+
+/// Freshman account `fid`, locked until the transaction on `conn` ends
+pub async fn lock_freshman(conn: &mut PgConnection, fid: i32) -> Result<FreshmanAccount, AppError> {
+    match log_query_as(
+        query_as!(
+            FreshmanAccount,
+            "SELECT id, name, eval_date, onfloor_status, room_number, signatures_missed,
+                rit_username
+            FROM freshman_accounts WHERE id = $1 FOR UPDATE",
+            fid
+        )
+        .fetch_all(conn)
+        .await,
+    )?
+    .pop()
+    {
+        Some(account) => Ok(account),
+        None => Err(AppError::NotFound(format!("No freshman account {fid}"))),
+    }
+}
+
+/// RIT usernames of every freshman account other than `except`, as given by
+/// `rit_username_key`
+pub async fn get_rit_usernames(
+    conn: &mut PgConnection,
+    except: Option<i32>,
+) -> Result<HashSet<String>, AppError> {
+    Ok(log_query_as(
+        query!(
+            "SELECT rit_username FROM freshman_accounts
+            WHERE ($1::int4 IS NULL OR id != $1) AND rit_username IS NOT NULL",
+            except
+        )
+        .fetch_all(conn)
+        .await,
+    )?
+    .into_iter()
+    .filter_map(|a| a.rit_username.as_deref().and_then(rit_username_key))
+    .collect())
+}
+
+/// Save `account` over the freshman account with its id, returning what
+/// was saved
+pub async fn update_freshman(
+    conn: &mut PgConnection,
+    account: &FreshmanAccount,
+) -> Result<FreshmanAccount, AppError> {
+    match log_query_as(
+        query_as!(
+            FreshmanAccount,
+            "UPDATE freshman_accounts
+            SET name = $2, eval_date = $3, onfloor_status = $4, room_number = $5,
+                rit_username = $6
+            WHERE id = $1
+            RETURNING id, name, eval_date, onfloor_status, room_number, signatures_missed,
+                rit_username",
+            account.id,
+            account.name,
+            account.eval_date,
+            account.onfloor_status,
+            account.room_number,
+            account.rit_username
+        )
+        .fetch_all(conn)
+        .await,
+    )?
+    .pop()
+    {
+        Some(account) => Ok(account),
+        None => Err(AppError::NotFound(format!(
+            "No freshman account {}",
+            account.id
+        ))),
+    }
+}
+
+/// Delete freshman account `fid` along with their attendance, excuse
+/// requests and batch data. Returns the deleted account.
+pub async fn delete_freshman(
+    conn: &mut PgConnection,
+    fid: i32,
+) -> Result<FreshmanAccount, AppError> {
+    // Every row referencing the account is deleted explicitly rather than
+    // relying on cascading deletes
+    let deleted = log_query_as(
+        query_as!(
+            FreshmanAccount,
+            "WITH fca_deleted AS (
+                DELETE FROM freshman_committee_attendance WHERE fid = $1
+            ), fha_deleted AS (
+                DELETE FROM freshman_hm_attendance WHERE fid = $1
+            ), fsa_deleted AS (
+                DELETE FROM freshman_seminar_attendance WHERE fid = $1
+            ), excuses_deleted AS (
+                DELETE FROM hm_excuse_requests WHERE fid = $1
+            ), fbps_deleted AS (
+                DELETE FROM freshman_batch_pulls WHERE fid = $1
+            ), fbus_deleted AS (
+                DELETE FROM freshman_batch_users WHERE fid = $1
+            )
+            DELETE FROM freshman_accounts WHERE id = $1
+            RETURNING id, name, eval_date, onfloor_status, room_number, signatures_missed,
+                rit_username",
+            fid
+        )
+        .fetch_all(conn)
+        .await,
+    )?;
+    match deleted.into_iter().next() {
+        Some(account) => Ok(account),
+        None => Err(AppError::NotFound(format!("No freshman account {fid}"))),
+    }
+}
+
+/// Move a freshman's attendance, excuse requests and batch data to `uid`,
+/// delete their freshman account and log the conversion. Returns the id of
+/// the logged conversion and what was moved.
+pub async fn convert_freshman(
+    conn: &mut PgConnection,
+    fid: i32,
+    uid: &str,
+) -> Result<(i32, ConvertedData), AppError> {
+    // Migrate directorship attendance
+    let committee_attendance = log_query_as(
+        query_scalar!(
+            "WITH moved AS (
+                DELETE FROM freshman_committee_attendance WHERE fid = $1 RETURNING meeting_id
+            ) INSERT INTO member_committee_attendance (uid, meeting_id)
+            SELECT $2, meeting_id FROM moved RETURNING id",
+            fid,
+            uid
+        )
+        .fetch_all(&mut *conn)
+        .await,
+    )?;
+
+    // Migrate house meeting attendance
+    let hm_attendance = log_query_as(
+        query_scalar!(
+            "WITH moved AS (
+                DELETE FROM freshman_hm_attendance WHERE fid = $1
+                RETURNING meeting_id, excuse, attendance_status
+            ) INSERT INTO member_hm_attendance (uid, meeting_id, excuse, attendance_status)
+            SELECT $2, meeting_id, excuse, attendance_status FROM moved RETURNING id",
+            fid,
+            uid
+        )
+        .fetch_all(&mut *conn)
+        .await,
+    )?;
+
+    // Migrate seminar attendance
+    let seminar_attendance = log_query_as(
+        query_scalar!(
+            "WITH moved AS (
+                DELETE FROM freshman_seminar_attendance WHERE fid = $1 RETURNING seminar_id
+            ) INSERT INTO member_seminar_attendance (uid, seminar_id)
+            SELECT $2, seminar_id FROM moved RETURNING id",
+            fid,
+            uid
+        )
+        .fetch_all(&mut *conn)
+        .await,
+    )?;
+
+    // Migrate house meeting excuse requests
+    let excuse_requests = log_query_as(
+        query_scalar!(
+            "UPDATE hm_excuse_requests SET uid = $2, fid = NULL WHERE fid = $1 RETURNING id",
+            fid,
+            uid
+        )
+        .fetch_all(&mut *conn)
+        .await,
+    )?;
+
+    // Migrate batch membership
+    let batch_users = log_query_as(
+        query_scalar!(
+            "WITH moved AS (
+                DELETE FROM freshman_batch_users WHERE fid = $1 RETURNING batch_id
+            ) INSERT INTO member_batch_users (uid, batch_id)
+            SELECT $2, batch_id FROM moved RETURNING id",
+            fid,
+            uid
+        )
+        .fetch_all(&mut *conn)
+        .await,
+    )?;
+
+    // Migrate the batch pull, unless the member already has one of their own
+    let batch_pull = match log_query_as(
+        query_as!(
+            FreshmanBatchPull,
+            "DELETE FROM freshman_batch_pulls WHERE fid = $1
+            RETURNING id, fid, approved, reason, puller",
+            fid
+        )
+        .fetch_all(&mut *conn)
+        .await,
+    ) {
+        Ok(pulls) => pulls.into_iter().next(),
+        Err(res) => return Err(res),
+    };
+    let mut moved_pull = Vec::new();
+    if let Some(pull) = &batch_pull {
+        moved_pull = log_query_as(
+            query_scalar!(
+                "INSERT INTO member_batch_pulls (uid, approved, reason, puller)
+                VALUES ($1, $2, $3, $4) ON CONFLICT (uid) DO NOTHING RETURNING id",
+                uid,
+                pull.approved,
+                pull.reason,
+                pull.puller
+            )
+            .fetch_all(&mut *conn)
+            .await,
+        )?;
+    }
+
+    let account = match log_query_as(
+        query_as!(
+            FreshmanAccount,
+            "DELETE FROM freshman_accounts WHERE id = $1
+            RETURNING id, name, eval_date, onfloor_status, room_number, signatures_missed,
+                rit_username",
+            fid
+        )
+        .fetch_all(&mut *conn)
+        .await,
+    ) {
+        Ok(accounts) => accounts.into_iter().next(),
+        Err(res) => return Err(res),
+    };
+    let account = match account {
+        Some(account) => account,
+        None => {
+            log!(Level::Warn, "No freshman account {fid} to convert");
+            return Err(AppError::NotFound(format!("No freshman account {fid}")));
+        }
+    };
+
+    let converted = ConvertedData {
+        seminars: seminar_attendance.len() as i64,
+        directorships: committee_attendance.len() as i64,
+        house_meetings: hm_attendance.len() as i64,
+        excuses: excuse_requests.len() as i64,
+        batches: batch_users.len() as i64,
+        batch_pulls: moved_pull.len() as i64,
+    };
+    let conversion = log_query_as(
+        query_scalar!(
+            "INSERT INTO freshman_conversions (fid, uid, converted, name, eval_date,
+                onfloor_status, room_number, signatures_missed, rit_username,
+                committee_attendance, hm_attendance, seminar_attendance, excuse_requests,
+                batch_users, batch_pull_approved, batch_pull_reason, batch_pull_puller,
+                batch_pull)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15, $16, $17,
+                $18)
+            RETURNING id",
+            fid,
+            uid,
+            Utc::now().naive_utc(),
+            account.name,
+            account.eval_date,
+            account.onfloor_status,
+            account.room_number,
+            account.signatures_missed,
+            account.rit_username,
+            &committee_attendance,
+            &hm_attendance,
+            &seminar_attendance,
+            &excuse_requests,
+            &batch_users,
+            batch_pull.as_ref().map(|p| p.approved),
+            batch_pull.as_ref().map(|p| p.reason.clone()),
+            batch_pull.as_ref().map(|p| p.puller.clone()),
+            moved_pull.first().copied()
+        )
+        .fetch_all(&mut *conn)
+        .await,
+    )?;
+    log!(
+        Level::Debug,
+        "Converted freshman {fid} to {uid}: {converted:?}"
+    );
+    Ok((conversion[0], converted))
+}
+
+/// Every freshman converted to a member, most recent first
+pub async fn get_conversions(db: &Pool<Postgres>) -> Result<Vec<ConversionLogEntry>, AppError> {
+    Ok(log_query_as(
+        query_as!(
+            FreshmanConversion,
+            "SELECT * FROM freshman_conversions ORDER BY converted DESC, id DESC"
+        )
+        .fetch_all(db)
+        .await,
+    )?
+    .into_iter()
+    .map(ConversionLogEntry::from)
+    .collect())
+}
+
+/// Restore the freshman account conversion `id` deleted and move everything
+/// that's left of what it moved back
+pub async fn undo_conversion(conn: &mut PgConnection, id: i32) -> Result<ConversionUndo, AppError> {
+    let mut conversion = match log_query_as(
+        query_as!(
+            FreshmanConversion,
+            "SELECT * FROM freshman_conversions WHERE id = $1 FOR UPDATE",
+            id
+        )
+        .fetch_all(&mut *conn)
+        .await,
+    )?
+    .pop()
+    {
+        Some(conversion) => conversion,
+        None => return Err(AppError::NotFound(format!("No conversion {id}"))),
+    };
+    if conversion.undone.is_some() {
+        return Err(AppError::Conflict(format!(
+            "Conversion {id} was already undone"
+        )));
+    }
+    let fid = conversion.fid;
+    let existing = log_query_as(
+        query_scalar!("SELECT id FROM freshman_accounts WHERE id = $1", fid)
+            .fetch_all(&mut *conn)
+            .await,
+    )?;
+    if !existing.is_empty() {
+        return Err(AppError::Conflict(format!("Freshman account {fid} exists")));
+    }
+
+    let uid = conversion.uid.clone();
+    let mut restored = ConvertedData::default();
+
+    log_query_as(
+        query_scalar!(
+            "INSERT INTO freshman_accounts (id, name, eval_date, onfloor_status, room_number,
+                signatures_missed, rit_username)
+            VALUES ($1, $2, $3, $4, $5, $6, $7) RETURNING id",
+            fid,
+            conversion.name,
+            conversion.eval_date,
+            conversion.onfloor_status,
+            conversion.room_number,
+            conversion.signatures_missed,
+            conversion.rit_username
+        )
+        .fetch_all(&mut *conn)
+        .await,
+    )?;
+
+    let ids = log_query_as(
+        query_scalar!(
+            "WITH moved AS (
+                DELETE FROM member_committee_attendance WHERE id = ANY($2) AND uid = $3
+                RETURNING meeting_id
+            ) INSERT INTO freshman_committee_attendance (fid, meeting_id)
+            SELECT $1, meeting_id FROM moved RETURNING id",
+            fid,
+            &conversion.committee_attendance,
+            &uid
+        )
+        .fetch_all(&mut *conn)
+        .await,
+    )?;
+    restored.directorships = ids.len() as i64;
+
+    let ids = log_query_as(
+        query_scalar!(
+            "WITH moved AS (
+                DELETE FROM member_hm_attendance WHERE id = ANY($2) AND uid = $3
+                RETURNING meeting_id, excuse, attendance_status
+            ) INSERT INTO freshman_hm_attendance (fid, meeting_id, excuse, attendance_status)
+            SELECT $1, meeting_id, excuse, attendance_status FROM moved RETURNING id",
+            fid,
+            &conversion.hm_attendance,
+            &uid
+        )
+        .fetch_all(&mut *conn)
+        .await,
+    )?;
+    restored.house_meetings = ids.len() as i64;
+
+    let ids = log_query_as(
+        query_scalar!(
+            "WITH moved AS (
+                DELETE FROM member_seminar_attendance WHERE id = ANY($2) AND uid = $3
+                RETURNING seminar_id
+            ) INSERT INTO freshman_seminar_attendance (fid, seminar_id)
+            SELECT $1, seminar_id FROM moved RETURNING id",
+            fid,
+            &conversion.seminar_attendance,
+            &uid
+        )
+        .fetch_all(&mut *conn)
+        .await,
+    )?;
+    restored.seminars = ids.len() as i64;
+
+    let ids = log_query_as(
+        query_scalar!(
+            "UPDATE hm_excuse_requests SET fid = $1, uid = NULL
+            WHERE id = ANY($2) AND uid = $3 RETURNING id",
+            fid,
+            &conversion.excuse_requests,
+            &uid
+        )
+        .fetch_all(&mut *conn)
+        .await,
+    )?;
+    restored.excuses = ids.len() as i64;
+
+    let ids = log_query_as(
+        query_scalar!(
+            "WITH moved AS (
+                DELETE FROM member_batch_users WHERE id = ANY($2) AND uid = $3
+                RETURNING batch_id
+            ) INSERT INTO freshman_batch_users (fid, batch_id)
+            SELECT $1, batch_id FROM moved RETURNING id",
+            fid,
+            &conversion.batch_users,
+            &uid
+        )
+        .fetch_all(&mut *conn)
+        .await,
+    )?;
+    restored.batches = ids.len() as i64;
+
+    // The freshman's batch pull is restored as it was, even if the member
+    // already had one and it was never moved
+    if let Some(batch_pull) = conversion.batch_pull {
+        log_query_as(
+            query_scalar!(
+                "DELETE FROM member_batch_pulls WHERE id = $1 AND uid = $2 RETURNING id",
+                batch_pull,
+                &uid
+            )
+            .fetch_all(&mut *conn)
+            .await,
+        )?;
+    }
+    // All three are recorded together, so the pull is only restored when the
+    // conversion recorded all of it
+    if let (Some(approved), Some(reason), Some(puller)) = (
+        conversion.batch_pull_approved,
+        &conversion.batch_pull_reason,
+        &conversion.batch_pull_puller,
+    ) {
+        let ids = log_query_as(
+            query_scalar!(
+                "INSERT INTO freshman_batch_pulls (fid, approved, reason, puller)
+                VALUES ($1, $2, $3, $4) RETURNING id",
+                fid,
+                approved,
+                reason,
+                puller
+            )
+            .fetch_all(&mut *conn)
+            .await,
+        )?;
+        restored.batch_pulls = ids.len() as i64;
+    }
+
+    let undone = Utc::now().naive_utc();
+    log_query_as(
+        query_scalar!(
+            "UPDATE freshman_conversions SET undone = $2 WHERE id = $1 RETURNING id",
+            conversion.id,
+            undone
+        )
+        .fetch_all(&mut *conn)
+        .await,
+    )?;
+    log!(
+        Level::Debug,
+        "Undid conversion of freshman {fid} to {uid}: {restored:?}"
+    );
+    conversion.undone = Some(undone);
+    Ok(ConversionUndo {
+        conversion: ConversionLogEntry::from(conversion),
+        restored,
+    })
+}
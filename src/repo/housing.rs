@@ -0,0 +1,154 @@
+use crate::error::{log_query_as, AppError};
+use crate::housing::HousingPointInputs;
+use crate::schema::db::{InHousingQueue, OnFloorDate};
+use chrono::NaiveDateTime;
+use sqlx::{query, query_as, PgConnection, Pool, Postgres};
+
+/// A member in the housing queue, with when they were granted on floor status
+/// if they have been
+pub struct QueuedMember {
+    pub uid: String,
+    pub onfloor_granted: Option<NaiveDateTime>,
+}
+
+/// Gather housing point inputs for each of `uids`
+pub async fn get_point_inputs(
+    db: &Pool<Postgres>,
+    uids: &[String],
+    year_start: &NaiveDateTime,
+) -> Result<Vec<HousingPointInputs>, AppError> {
+    log_query_as(
+        query_as!(
+            HousingPointInputs,
+            "SELECT u.uid AS \"uid!\",
+                od.onfloor_granted AS \"onfloor_granted?\",
+                (SELECT count(*) FROM current_coops cc
+                    WHERE cc.uid = u.uid AND cc.semester <> 'Neither'
+                    AND cc.date_created >= od.onfloor_granted::date) AS \"coop_semesters!\",
+                (SELECT count(*) FROM spring_evals se
+                    WHERE se.uid = u.uid AND se.status = 'Passed') AS \"passed_evals!\",
+                (SELECT count(*) FROM member_seminar_attendance msa
+                    JOIN technical_seminars ts ON ts.id = msa.seminar_id
+                    WHERE msa.uid = u.uid AND ts.approved AND ts.timestamp > $2::timestamp)
+                    AS \"seminars!\",
+                (SELECT count(*) FROM member_committee_attendance mca
+                    JOIN committee_meetings cm ON cm.id = mca.meeting_id
+                    WHERE mca.uid = u.uid AND cm.approved AND cm.timestamp > $2::timestamp)
+                    AS \"directorships!\",
+                (SELECT count(*) FROM member_hm_attendance mha
+                    JOIN house_meetings hm ON hm.id = mha.meeting_id
+                    WHERE mha.uid = u.uid AND mha.attendance_status = 'Absent' AND hm.active
+                    AND hm.date > $2::timestamp) AS \"missed_hms!\"
+            FROM UNNEST($1::varchar[]) AS u(uid)
+            LEFT JOIN onfloor_datetime od ON od.uid = u.uid",
+            uids,
+            year_start
+        )
+        .fetch_all(db)
+        .await,
+    )
+}
+
+/// Usernames of everyone in the housing queue
+pub async fn get_queue_uids(db: &Pool<Postgres>) -> Result<Vec<String>, AppError> {
+    Ok(log_query_as(
+        query!("SELECT uid FROM in_housing_queue")
+            .fetch_all(db)
+            .await,
+    )?
+    .into_iter()
+    .map(|entry| entry.uid)
+    .collect())
+}
+
+/// Everyone in the housing queue, longest on floor first
+pub async fn get_queue(db: &Pool<Postgres>) -> Result<Vec<QueuedMember>, AppError> {
+    log_query_as(
+        query_as!(
+            QueuedMember,
+            "SELECT hq.uid, od.onfloor_granted AS \"onfloor_granted?\" FROM in_housing_queue hq
+            LEFT JOIN onfloor_datetime od ON od.uid = hq.uid
+            ORDER BY od.onfloor_granted ASC NULLS LAST, hq.uid",
+        )
+        .fetch_all(db)
+        .await,
+    )
+}
+
+/// Grant `uid` on floor status as of `granted`
+pub async fn grant_onfloor(
+    conn: &mut PgConnection,
+    uid: &str,
+    granted: &NaiveDateTime,
+) -> Result<OnFloorDate, AppError> {
+    log_query_as(
+        query_as!(
+            OnFloorDate,
+            "INSERT INTO onfloor_datetime (uid, onfloor_granted) VALUES ($1, $2)
+            ON CONFLICT (uid) DO NOTHING RETURNING uid, onfloor_granted",
+            uid,
+            granted
+        )
+        .fetch_all(conn)
+        .await,
+    )?
+    .into_iter()
+    .next()
+    .ok_or_else(|| AppError::Conflict(String::from("Member is already on floor")))
+}
+
+/// Revoke `uid`'s on floor status
+pub async fn revoke_onfloor(conn: &mut PgConnection, uid: &str) -> Result<(), AppError> {
+    let revoked = log_query_as(
+        query_as!(
+            OnFloorDate,
+            "DELETE FROM onfloor_datetime WHERE uid = $1 RETURNING uid, onfloor_granted",
+            uid
+        )
+        .fetch_all(conn)
+        .await,
+    )?;
+    if revoked.is_empty() {
+        return Err(AppError::NotFound(String::from("Member is not on floor")));
+    }
+    Ok(())
+}
+
+/// Add `uid` to the housing queue
+pub async fn add_to_queue(conn: &mut PgConnection, uid: &str) -> Result<(), AppError> {
+    let added = log_query_as(
+        query_as!(
+            InHousingQueue,
+            "INSERT INTO in_housing_queue (uid) VALUES ($1) ON CONFLICT (uid) DO NOTHING
+            RETURNING uid",
+            uid
+        )
+        .fetch_all(conn)
+        .await,
+    )?;
+    if added.is_empty() {
+        return Err(AppError::Conflict(String::from(
+            "Member is already in the housing queue",
+        )));
+    }
+    Ok(())
+}
+
+/// Remove `uid` from the housing queue
+pub async fn remove_from_queue(conn: &mut PgConnection, uid: &str) -> Result<(), AppError> {
+    let removed = log_query_as(
+        query_as!(
+            InHousingQueue,
+            "DELETE FROM in_housing_queue WHERE uid = $1 RETURNING uid",
+            uid
+        )
+        .fetch_all(conn)
+        .await,
+    )?;
+    if removed.is_empty() {
+        return Err(AppError::NotFound(String::from(
+            "Member is not in the housing queue",
+        )));
+    }
+    Ok(())
+}
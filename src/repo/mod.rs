@@ -0,0 +1,33 @@
+//! Database access, one function per domain operation. Nothing here knows
+//! about HTTP, so jobs and other binaries can call the same functions the
+//! handlers do.
+//!
+//! Reads take the pool to run on and writes take a connection, which is
+//! usually a `UnitOfWork` so every write a caller makes commits together.
+//! Reads that writes are decided from take a connection too, so they can run
+//! in the same transaction as the writes.
+//! A function looking up a specific row returns `AppError::NotFound` if it
+//! doesn't exist.
+
+pub mod attendance {
+    pub mod checkin;
+    pub mod directorship;
+    pub mod house;
+    pub mod seminar;
+}
+
+pub mod batches;
+pub mod evals;
+pub mod forms;
+pub mod freshmen;
+pub mod housing;
+pub mod packet;
+pub mod users;
+
+/// Someone attendance and evaluations are recorded for: a freshman by the id
+/// of their freshman account, or a member by CSH username
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Person {
+    Freshman(i32),
+    Member(String),
+}
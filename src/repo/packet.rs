@@ -0,0 +1,48 @@
+use crate::error::{log_query_as, AppError};
+use crate::schema::db::{PacketRounding, PacketSettings};
+use chrono::NaiveDateTime;
+use sqlx::{query_as, PgConnection};
+
+/// Packet settings for the operating year starting at `year_start`
+pub async fn get_packet_settings(
+    conn: &mut PgConnection,
+    year_start: &NaiveDateTime,
+) -> Result<PacketSettings, AppError> {
+    let settings = log_query_as(
+        query_as!(
+            PacketSettings,
+            "SELECT year_start, misc_cap, rounding AS \"rounding: PacketRounding\"
+            FROM packet_settings WHERE year_start <= $1
+            ORDER BY year_start DESC LIMIT 1",
+            year_start.date()
+        )
+        .fetch_all(conn)
+        .await,
+    )?;
+    Ok(settings.into_iter().next().unwrap_or(PacketSettings {
+        year_start: year_start.date(),
+        ..PacketSettings::default()
+    }))
+}
+
+/// Set packet settings for the operating year starting at
+/// `settings.year_start`, replacing any already set for it
+pub async fn set_packet_settings(
+    conn: &mut PgConnection,
+    settings: &PacketSettings,
+) -> Result<PacketSettings, AppError> {
+    Ok(log_query_as(
+        query_as!(
+            PacketSettings,
+            "INSERT INTO packet_settings (year_start, misc_cap, rounding) VALUES ($1, $2, $3)
+            ON CONFLICT (year_start) DO UPDATE SET misc_cap = $2, rounding = $3
+            RETURNING year_start, misc_cap, rounding AS \"rounding: PacketRounding\"",
+            settings.year_start,
+            settings.misc_cap,
+            settings.rounding as PacketRounding
+        )
+        .fetch_all(conn)
+        .await,
+    )?
+    .remove(0))
+}
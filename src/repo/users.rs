@@ -0,0 +1,146 @@
+use crate::error::{log_query_as, AppError};
+use crate::schema::api::{ProfileConditional, ProfileMajorProject};
+use crate::schema::db::{ConditionalStatus, CoopSemester, MajorProjectStatus, SpringEvalStatus};
+use chrono::NaiveDateTime;
+use sqlx::{query, query_as, Pool, Postgres};
+
+/// Everything recorded about a member this year, for their profile
+pub struct MemberRecords {
+    pub onfloor_granted: Option<NaiveDateTime>,
+    pub in_housing_queue: bool,
+    pub coop: Option<CoopSemester>,
+    pub seminars: i64,
+    pub directorships: i64,
+    pub missed_hms: i64,
+    pub major_projects: Vec<ProfileMajorProject>,
+    pub conditionals: Vec<ProfileConditional>,
+    pub eval_result: Option<SpringEvalStatus>,
+}
+
+/// Gather everything recorded about `uid` since `year_start`. Conditionals
+/// aren't limited to this year, since old ones may still be pending.
+pub async fn get_member_records(
+    db: &Pool<Postgres>,
+    uid: &str,
+    year_start: &NaiveDateTime,
+) -> Result<MemberRecords, AppError> {
+    let onfloor = async {
+        log_query_as(
+            query!(
+                "SELECT onfloor_granted FROM onfloor_datetime WHERE uid = $1",
+                uid
+            )
+            .fetch_all(db)
+            .await,
+        )
+    };
+    let housing = async {
+        log_query_as(
+            query!(
+                "SELECT EXISTS(SELECT 1 FROM in_housing_queue WHERE uid = $1) AS \"queued!\"",
+                uid
+            )
+            .fetch_all(db)
+            .await,
+        )
+    };
+    let coop = async {
+        log_query_as(
+            query!(
+                "SELECT semester AS \"semester: CoopSemester\" FROM current_coops
+                WHERE uid = $1 AND date_created > $2::timestamp
+                ORDER BY date_created DESC LIMIT 1",
+                uid,
+                year_start
+            )
+            .fetch_all(db)
+            .await,
+        )
+    };
+    let counts = async {
+        log_query_as(
+            query!(
+                "SELECT
+                    (SELECT count(*) FROM member_seminar_attendance msa
+                        JOIN technical_seminars ts ON ts.id = msa.seminar_id
+                        WHERE msa.uid = $1 AND ts.approved AND ts.timestamp > $2::timestamp)
+                        AS \"seminars!\",
+                    (SELECT count(*) FROM member_committee_attendance mca
+                        JOIN committee_meetings cm ON cm.id = mca.meeting_id
+                        WHERE mca.uid = $1 AND cm.approved AND cm.timestamp > $2::timestamp)
+                        AS \"directorships!\",
+                    (SELECT count(*) FROM member_hm_attendance mha
+                        JOIN house_meetings hm ON hm.id = mha.meeting_id
+                        WHERE mha.uid = $1 AND mha.attendance_status = 'Absent' AND hm.active
+                        AND hm.date > $2::timestamp)
+                        AS \"missed_hms!\"",
+                uid,
+                year_start
+            )
+            .fetch_all(db)
+            .await,
+        )
+    };
+    let major_projects = async {
+        log_query_as(
+            query_as!(
+                ProfileMajorProject,
+                "SELECT id, name, description, date, status AS \"status: MajorProjectStatus\"
+                FROM major_projects WHERE uid = $1 AND date > $2::timestamp ORDER BY date",
+                uid,
+                year_start
+            )
+            .fetch_all(db)
+            .await,
+        )
+    };
+    let conditionals = async {
+        log_query_as(
+            query_as!(
+                ProfileConditional,
+                "SELECT id, description, date_created, date_due,
+                    status AS \"status: ConditionalStatus\"
+                FROM conditional WHERE uid = $1 ORDER BY date_created DESC",
+                uid
+            )
+            .fetch_all(db)
+            .await,
+        )
+    };
+    let eval_result = async {
+        log_query_as(
+            query!(
+                "SELECT status AS \"status: SpringEvalStatus\" FROM spring_evals
+                WHERE uid = $1 AND date_created > $2::timestamp
+                ORDER BY date_created DESC LIMIT 1",
+                uid,
+                year_start
+            )
+            .fetch_all(db)
+            .await,
+        )
+    };
+
+    let (onfloor, housing, coop, counts, major_projects, conditionals, eval_result) = futures::try_join!(
+        onfloor,
+        housing,
+        coop,
+        counts,
+        major_projects,
+        conditionals,
+        eval_result
+    )?;
+    let counts = &counts[0];
+
+    Ok(MemberRecords {
+        onfloor_granted: onfloor.into_iter().next().map(|o| o.onfloor_granted),
+        in_housing_queue: housing[0].queued,
+        coop: coop.into_iter().next().map(|c| c.semester),
+        seminars: counts.seminars,
+        directorships: counts.directorships,
+        missed_hms: counts.missed_hms,
+        major_projects,
+        conditionals,
+        eval_result: eval_result.into_iter().next().map(|e| e.status),
+    })
+}
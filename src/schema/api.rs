@@ -6,17 +6,36 @@
 
 use chrono::{NaiveDate, NaiveDateTime};
 use serde::{Deserialize, Serialize};
-use utoipa::ToSchema;
+use utoipa::{IntoParams, ToSchema};
 
 use super::db::{
-    AttendanceStatus, BatchComparison, BatchConditionType, CommitteeType, CoopSemester,
-    MajorProjectStatus, MemberBatchUser,
+    AttendanceStatus, BatchComparison, BatchConditionType, CommitteeType, ConditionalStatus,
+    CoopSemester, ExcuseStatus, FreshmanAccount, MajorProjectStatus, MemberBatchUser,
+    SpringEvalStatus,
 };
+use crate::ldap::user::LdapUser;
 
 pub struct ID {
     pub id: i32,
 }
 
+/// Body of every error response
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, ToSchema)]
+pub struct ErrorBody {
+    /// Stable identifier for the kind of error, such as `not_found`
+    pub code: String,
+    pub message: String,
+    /// Id of the failed request, also sent in the `X-Request-Id` header
+    pub request_id: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, IntoParams)]
+pub struct DryRun {
+    /// Report what would change without changing anything
+    #[serde(default)]
+    pub dry_run: bool,
+}
+
 #[derive(Serialize, Deserialize)]
 pub struct Date {
     pub date: NaiveDate,
@@ -47,18 +66,70 @@ pub struct IntroStatus {
     pub signatures: i64,
     /// Number of upperclassmen packet signatures for 100%
     pub max_signatures: i64,
+    /// Percentage of the packet completed
+    pub packet_completion: f64,
 }
 
-#[derive(Serialize, Deserialize, Clone, Debug, ToSchema, sqlx::FromRow)]
+#[derive(Serialize, Deserialize, Clone, Debug, ToSchema)]
 pub struct Packet {
     /// Intro member's rit username
-    pub username: Option<String>,
+    pub username: String,
     /// Name of the intro member
-    pub name: Option<String>,
+    pub name: String,
+    /// When the packet was opened
+    pub start: Option<NaiveDateTime>,
     /// Number of upperclassmen packet signatures recieved
-    pub signatures: Option<i64>,
-    /// Number of upperclassmen packet signatures for 100%
-    pub max_signatures: Option<i64>,
+    pub upper_signatures: i64,
+    /// Number of upperclassmen on the packet
+    pub max_upper: i64,
+    /// Number of misc signatures recieved, before the cap is applied
+    pub misc_signatures: i64,
+}
+
+/// A packet missing information needed to evaluate it, which is left out of
+/// evals and batches
+#[derive(Serialize, Deserialize, Clone, Debug, ToSchema)]
+pub struct IncompletePacket {
+    /// Packet ID in the packet database
+    pub packet_id: i32,
+    /// Intro member's rit username, if the packet has one
+    pub username: Option<String>,
+    /// Names of the missing fields
+    pub missing: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, ToSchema)]
+pub struct UpperclassmenSignatures {
+    /// Upperclassmen who have signed
+    pub signed: Vec<String>,
+    /// Upperclassmen who have not signed yet
+    pub unsigned: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, ToSchema)]
+pub struct MiscSignatures {
+    /// Everyone who has given a misc signature
+    pub members: Vec<String>,
+    /// Number of misc signatures that count towards the packet
+    pub counted: i64,
+    /// Most misc signatures that can count towards the packet
+    pub cap: i64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, ToSchema)]
+pub struct PacketSignatures {
+    /// Intro member's rit username
+    pub username: String,
+    /// Name of the intro member
+    pub name: String,
+    pub upperclassmen: UpperclassmenSignatures,
+    pub misc: MiscSignatures,
+    /// Number of signatures counted towards the packet
+    pub signatures: i64,
+    /// Number of signatures for 100%
+    pub max_signatures: i64,
+    /// Percentage of the packet completed
+    pub completion: f64,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, ToSchema)]
@@ -119,12 +190,128 @@ pub struct NewIntroMember {
     pub rit_username: String,
 }
 
+/// One freshman in a bulk import. Everything is optional so a bad row can be
+/// reported instead of failing the whole import.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, ToSchema)]
+pub struct FreshmanImportRow {
+    pub name: Option<String>,
+    pub eval_date: Option<chrono::NaiveDate>,
+    /// Defaults to false
+    pub onfloor_status: Option<bool>,
+    /// Required if the freshman is on floor
+    pub room_number: Option<String>,
+    pub rit_username: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, ToSchema)]
+pub struct FreshmanImportError {
+    /// Row of the import, starting at 1 for the first freshman
+    pub row: usize,
+    pub rit_username: Option<String>,
+    pub errors: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, ToSchema)]
+pub struct FreshmanImportReport {
+    pub dry_run: bool,
+    /// Number of rows in the import
+    pub rows: usize,
+    /// IDs of the freshman accounts created, in row order
+    pub created: Vec<i32>,
+    /// Rows that can't be imported. Nothing is imported if there are any.
+    pub errors: Vec<FreshmanImportError>,
+}
+
+/// Changes to a freshman account. Fields left out are unchanged, and an
+/// empty room number clears it.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, ToSchema)]
+pub struct FreshmanAccountEdit {
+    pub name: Option<String>,
+    pub eval_date: Option<chrono::NaiveDate>,
+    pub onfloor_status: Option<bool>,
+    pub room_number: Option<String>,
+    pub rit_username: Option<String>,
+}
+
 #[derive(Serialize, Deserialize, Clone, Debug, ToSchema)]
 pub struct FreshmanUpgrade {
     pub fid: i32,
     pub uid: String,
 }
 
+/// A freshman account and the CSH account it looks like it was converted to,
+/// matched by RIT username
+#[derive(Serialize, Deserialize, Clone, Debug, ToSchema)]
+pub struct ConversionProposal {
+    pub fid: i32,
+    pub name: String,
+    pub rit_username: Option<String>,
+    /// CSH account to convert to, if exactly one intro member matched
+    pub uid: Option<String>,
+    /// Name of the matched CSH account
+    pub member_name: Option<String>,
+    /// Every intro member that matched, when there's more than one
+    pub candidates: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, ToSchema)]
+pub struct ConversionPair {
+    pub fid: i32,
+    /// CSH account to convert to. Leave out to confirm the proposed match.
+    #[serde(default)]
+    pub uid: Option<String>,
+}
+
+/// How many rows were moved between a freshman account and a member
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq, ToSchema)]
+pub struct ConvertedData {
+    pub seminars: i64,
+    pub directorships: i64,
+    pub house_meetings: i64,
+    pub excuses: i64,
+    pub batches: i64,
+    pub batch_pulls: i64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, ToSchema)]
+pub struct ConversionResult {
+    pub fid: i32,
+    pub uid: Option<String>,
+    /// Whether the uid came from the proposed match rather than an override
+    pub proposed: bool,
+    /// What was moved, once the freshman has been converted
+    pub converted: Option<ConvertedData>,
+    /// Id of the logged conversion, to undo it with
+    pub conversion: Option<i32>,
+    pub errors: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, ToSchema)]
+pub struct ConversionReport {
+    pub dry_run: bool,
+    pub results: Vec<ConversionResult>,
+}
+
+/// A logged conversion of a freshman to a member
+#[derive(Serialize, Deserialize, Clone, Debug, ToSchema)]
+pub struct ConversionLogEntry {
+    pub id: i32,
+    pub uid: String,
+    pub converted: NaiveDateTime,
+    pub undone: Option<NaiveDateTime>,
+    /// The freshman account as it was before conversion
+    pub freshman: FreshmanAccount,
+    pub moved: ConvertedData,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, ToSchema)]
+pub struct ConversionUndo {
+    pub conversion: ConversionLogEntry,
+    /// How many rows were moved back. Rows deleted since the conversion
+    /// can't be restored.
+    pub restored: ConvertedData,
+}
+
 #[derive(Serialize, Deserialize, Clone, Debug, ToSchema)]
 pub struct MemberHouseAttendance {
     pub uid: String,
@@ -144,6 +331,201 @@ pub struct HouseAttendance {
     pub frosh: Vec<FroshHouseAttendance>,
 }
 
+#[derive(Serialize, Deserialize, Clone, Debug, ToSchema)]
+pub struct MemberHouseMeetingStatus {
+    /// CSH username of the member
+    pub uid: String,
+    /// Whether the member attended, was absent, or was excused
+    pub attendance_status: AttendanceStatus,
+    /// Excuse given, if the member was excused
+    pub excuse: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, ToSchema)]
+pub struct FreshmanHouseMeetingStatus {
+    /// Freshman ID of the intro member
+    pub fid: i32,
+    /// Name of the intro member
+    pub name: String,
+    /// Whether the freshman attended, was absent, or was excused
+    pub attendance_status: AttendanceStatus,
+    /// Excuse given, if the freshman was excused
+    pub excuse: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, ToSchema)]
+pub struct HouseMeetingRoster {
+    pub id: i32,
+    pub date: NaiveDate,
+    /// Whether the house meeting is active. Cancelled house meetings are
+    /// inactive.
+    pub active: bool,
+    pub members: Vec<MemberHouseMeetingStatus>,
+    pub frosh: Vec<FreshmanHouseMeetingStatus>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, ToSchema)]
+pub struct HouseMeetingAttendanceEdit {
+    pub attendance_status: AttendanceStatus,
+    /// Excuse given, if the attendee was excused
+    pub excuse: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, ToSchema)]
+pub struct HouseMeetingActive {
+    pub active: bool,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, ToSchema)]
+pub struct ExcuseSubmission {
+    /// Freshman ID of the requester, if they're asking for their own
+    /// freshman account. Otherwise they're identified by their token.
+    pub fid: Option<i32>,
+    /// Reason the requester could not attend
+    pub excuse: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, ToSchema)]
+pub struct ExcuseRequest {
+    pub id: i32,
+    /// Id of the house meeting the excuse is for
+    pub meeting_id: i32,
+    /// Date of the house meeting the excuse is for
+    pub date: NaiveDate,
+    /// CSH username of the requester, if they have one
+    pub uid: Option<String>,
+    /// Freshman ID of the requester, if they don't have an account
+    pub fid: Option<i32>,
+    /// Name of the requester, if they don't have an account
+    pub name: Option<String>,
+    pub excuse: String,
+    pub status: ExcuseStatus,
+    pub submitted: NaiveDateTime,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, ToSchema)]
+pub struct ExcuseDecision {
+    pub accepted: bool,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, ToSchema)]
+pub struct CheckInCode {
+    /// Code attendees submit to check in
+    pub code: String,
+    /// When the code stops being accepted
+    pub expires: NaiveDateTime,
+    /// URL containing the code, to be rendered as a QR code
+    pub qr_url: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, ToSchema)]
+pub struct CheckInSubmission {
+    /// Freshman ID of the attendee, if they're checking in to their own
+    /// freshman account. Otherwise they're identified by their token.
+    pub fid: Option<i32>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, ToSchema)]
+pub struct ProfileMajorProject {
+    pub id: i32,
+    pub name: String,
+    pub description: Option<String>,
+    /// Date this major project was submitted
+    pub date: NaiveDate,
+    pub status: MajorProjectStatus,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, ToSchema)]
+pub struct ProfileConditional {
+    pub id: i32,
+    /// The terms of this conditional
+    pub description: String,
+    pub date_created: NaiveDate,
+    pub date_due: NaiveDate,
+    pub status: ConditionalStatus,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, ToSchema)]
+pub struct MemberProfile {
+    pub user: LdapUser,
+    /// When the member was granted on floor status, if they are on floor
+    pub onfloor_granted: Option<NaiveDateTime>,
+    pub in_housing_queue: bool,
+    /// Semester the member is on co-op this year, if they submitted a co-op
+    /// form
+    pub coop: Option<CoopSemester>,
+    /// Number of seminars attended this year
+    pub seminars: i64,
+    /// Number of directorships attended this year
+    pub directorships: i64,
+    /// Number of house meetings missed this year
+    pub missed_hms: i64,
+    /// Major projects submitted this year
+    pub major_projects: Vec<ProfileMajorProject>,
+    pub conditionals: Vec<ProfileConditional>,
+    /// Result of this year's spring evals, if they have been held
+    pub eval_result: Option<SpringEvalStatus>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, ToSchema)]
+pub struct HousingQueueEntry {
+    /// CSH username of the member
+    pub uid: String,
+    /// Name of the member, if they are still in LDAP
+    pub name: Option<String>,
+    /// When the member was granted on floor status, if they are on floor
+    pub onfloor_granted: Option<NaiveDateTime>,
+    /// The member's current housing points
+    pub housing_points: i64,
+}
+
+/// How many points each thing counts for. Set with the
+/// `HOUSING_WEIGHT_*` environment variables.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, ToSchema)]
+pub struct HousingPointWeights {
+    /// Points per semester since being granted on floor status
+    pub semester_onfloor: i64,
+    /// Points per co-op semester since being granted on floor status
+    pub coop_semester: i64,
+    /// Points per spring evals passed
+    pub passed_eval: i64,
+    /// Points per seminar attended this year
+    pub seminar: i64,
+    /// Points per directorship attended this year
+    pub directorship: i64,
+    /// Points per house meeting missed this year
+    pub missed_hm: i64,
+}
+
+/// One term of a member's housing points: `count` times `weight`
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, ToSchema)]
+pub struct HousingPointComponent {
+    pub source: String,
+    pub count: i64,
+    pub weight: i64,
+    pub points: i64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, ToSchema)]
+pub struct HousingPoints {
+    /// CSH username of the member
+    pub uid: String,
+    /// Name of the member, if they are in LDAP
+    pub name: Option<String>,
+    /// Position in the housing queue ranking, if ranked
+    pub rank: Option<usize>,
+    pub onfloor_granted: Option<NaiveDateTime>,
+    pub total: i64,
+    pub components: Vec<HousingPointComponent>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, ToSchema)]
+pub struct HousingPointsRanking {
+    pub weights: HousingPointWeights,
+    /// Members of the housing queue, highest points first
+    pub members: Vec<HousingPoints>,
+}
+
 pub struct MajorProjectSubmission {
     /// Username of member who submitted this major project
     pub uid: String,
@@ -229,3 +611,55 @@ pub struct Batch {
     /// If the user doesn't have an account, the second value will be empty.
     pub members: Vec<String>,
 }
+
+/// Everything known about one freshman, linked by their RIT username
+#[derive(Serialize, Deserialize, Clone, Debug, ToSchema)]
+pub struct FreshmanIdentity {
+    /// RIT username, trimmed and lowercased
+    pub rit_username: String,
+    /// Name from the packet, freshman account or LDAP, in that order
+    pub name: Option<String>,
+    /// Freshman accounts with this RIT username
+    pub fids: Vec<i32>,
+    /// CSH accounts in intro members with this RIT username
+    pub uids: Vec<String>,
+    /// Whether the freshman has a packet
+    pub has_packet: bool,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, ToSchema)]
+pub enum IdentityIssueKind {
+    /// A freshman account or intro member has no RIT username
+    MissingRitUsername,
+    /// A packet is missing its username or name
+    IncompletePacket,
+    /// A packet from this year doesn't belong to any freshman account or
+    /// intro member
+    OrphanedPacket,
+    /// A freshman account or intro member has no packet
+    MissingPacket,
+    /// More than one freshman account, or more than one intro member, has the
+    /// same RIT username
+    DuplicateAccount,
+    /// A freshman has both a freshman account and a CSH account, so they
+    /// still need to be converted
+    UnconvertedAccount,
+    /// RIT usernames only matched after trimming and lowercasing them
+    InexactMatch,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, ToSchema)]
+pub struct IdentityIssue {
+    pub kind: IdentityIssueKind,
+    pub rit_username: Option<String>,
+    pub fids: Vec<i32>,
+    pub uids: Vec<String>,
+    /// What's wrong, for humans
+    pub detail: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, ToSchema)]
+pub struct IdentityReport {
+    pub identities: Vec<FreshmanIdentity>,
+    pub issues: Vec<IdentityIssue>,
+}
@@ -1,4 +1,4 @@
-use ::chrono::NaiveDate;
+use ::chrono::{Datelike, NaiveDate};
 use serde::{Deserialize, Serialize};
 use sqlx::postgres::{PgHasArrayType, PgTypeInfo};
 use sqlx::types::chrono;
@@ -34,7 +34,7 @@ pub enum CommitteeType {
 // ----------- ENTERING POSTGRES BULLSHIT. BLAME jmf FOR THIS -----------------
 
 /// Enum used for 'conditional' to indicate P/F status
-#[derive(sqlx::Type, Deserialize, Serialize, Clone, Debug, PartialEq, Eq, Copy)]
+#[derive(sqlx::Type, Deserialize, Serialize, Clone, Debug, PartialEq, Eq, Copy, ToSchema)]
 #[sqlx(type_name = "conditional_enum")]
 pub enum ConditionalStatus {
     Pending,
@@ -61,7 +61,7 @@ pub enum FreshmanEvalStatus {
 }
 
 /// Enum used for major project status in 'major_projecs'
-#[derive(sqlx::Type, Deserialize, Serialize, Clone, Debug, PartialEq, Eq, Copy)]
+#[derive(sqlx::Type, Deserialize, Serialize, Clone, Debug, PartialEq, Eq, Copy, ToSchema)]
 #[sqlx(type_name = "major_project_enum")]
 pub enum MajorProjectStatus {
     Pending,
@@ -69,8 +69,8 @@ pub enum MajorProjectStatus {
     Failed,
 }
 
-#[derive(sqlx::Type, Deserialize, Serialize, Clone, Debug, PartialEq, Eq, Copy)]
-#[sqlx(type_name = "spring_eval_emum")]
+#[derive(sqlx::Type, Deserialize, Serialize, Clone, Debug, PartialEq, Eq, Copy, ToSchema)]
+#[sqlx(type_name = "spring_eval_enum")]
 pub enum SpringEvalStatus {
     Pending,
     Passed,
@@ -109,7 +109,7 @@ impl PgHasArrayType for BatchComparison {
 // --------- END POSTGRES BULLSHIT. BLAME joeneil FOR THE REST OF THIS --------
 
 /// Enum used for coop semester in 'current_coops'
-#[derive(sqlx::Type, Deserialize, Serialize, Clone, Debug, PartialEq, Eq, Copy)]
+#[derive(sqlx::Type, Deserialize, Serialize, Clone, Debug, PartialEq, Eq, Copy, ToSchema)]
 #[sqlx(type_name = "co_op_enum")]
 pub enum CoopSemester {
     Fall,
@@ -117,6 +117,19 @@ pub enum CoopSemester {
     Neither,
 }
 
+impl CoopSemester {
+    /// The semester containing `date`. The year starts in June, so spring
+    /// runs January through May and fall runs June through December.
+    #[must_use]
+    pub fn containing(date: &NaiveDate) -> Self {
+        if date.month() < 6 {
+            CoopSemester::Spring
+        } else {
+            CoopSemester::Fall
+        }
+    }
+}
+
 /// Enum used to attendance in 'freshman_hm_attendance' and
 /// 'member_hm_attendance'
 #[derive(sqlx::Type, Deserialize, Serialize, Clone, Debug, PartialEq, Eq, Copy)]
@@ -127,6 +140,24 @@ pub enum AttendanceStatus {
     Excused,
 }
 
+/// Enum used for eboard's decision in 'hm_excuse_requests'
+#[derive(sqlx::Type, Deserialize, Serialize, Clone, Debug, PartialEq, Eq, Copy, ToSchema)]
+#[sqlx(type_name = "excuse_status_enum")]
+pub enum ExcuseStatus {
+    Pending,
+    Accepted,
+    Denied,
+}
+
+/// Enum used for how packet completion is rounded in 'packet_settings'
+#[derive(sqlx::Type, Deserialize, Serialize, Clone, Debug, PartialEq, Eq, Copy, ToSchema)]
+#[sqlx(type_name = "packet_rounding_enum")]
+pub enum PacketRounding {
+    Floor,
+    Nearest,
+    Ceiling,
+}
+
 impl PgHasArrayType for AttendanceStatus {
     fn array_type_info() -> sqlx::postgres::PgTypeInfo {
         PgTypeInfo::with_name("attendance_enum[]")
@@ -197,7 +228,7 @@ pub struct Coop {
 }
 
 /// Row in the freshman_accounts table
-#[derive(FromRow, Deserialize, Serialize, Clone, Debug, PartialEq, Eq)]
+#[derive(FromRow, Deserialize, Serialize, Clone, Debug, PartialEq, Eq, ToSchema)]
 pub struct FreshmanAccount {
     /// Unique id identifying this freshman account (may be the same as packet
     /// id? TODO: Confirm). lmao no bozo
@@ -220,6 +251,44 @@ pub struct FreshmanAccount {
     pub rit_username: Option<String>,
 }
 
+/// Row in 'freshman_conversions'
+#[derive(FromRow, Deserialize, Serialize, Clone, Debug, PartialEq, Eq)]
+pub struct FreshmanConversion {
+    /// Unique id identifying this conversion
+    pub id: i32,
+    /// Id the freshman account had
+    pub fid: i32,
+    /// Username of the member the freshman was converted to
+    pub uid: String,
+    /// When the freshman was converted
+    pub converted: chrono::NaiveDateTime,
+    /// When the conversion was undone, if it has been
+    pub undone: Option<chrono::NaiveDateTime>,
+    pub name: String,
+    pub eval_date: chrono::NaiveDate,
+    pub onfloor_status: Option<bool>,
+    pub room_number: Option<String>,
+    pub signatures_missed: Option<i32>,
+    pub rit_username: Option<String>,
+    /// Ids in 'member_committee_attendance' the freshman's attendance moved to
+    pub committee_attendance: Vec<i32>,
+    /// Ids in 'member_hm_attendance' the freshman's attendance moved to
+    pub hm_attendance: Vec<i32>,
+    /// Ids in 'member_seminar_attendance' the freshman's attendance moved to
+    pub seminar_attendance: Vec<i32>,
+    /// Ids in 'hm_excuse_requests' the freshman submitted
+    pub excuse_requests: Vec<i32>,
+    /// Ids in 'member_batch_users' the freshman's batches moved to
+    pub batch_users: Vec<i32>,
+    /// The freshman's batch pull, if they had one
+    pub batch_pull_approved: Option<bool>,
+    pub batch_pull_reason: Option<String>,
+    pub batch_pull_puller: Option<String>,
+    /// Id in 'member_batch_pulls' the freshman's batch pull moved to, if it
+    /// was moved
+    pub batch_pull: Option<i32>,
+}
+
 /// Row in the 'freshman_committee_attendance' table
 #[derive(FromRow, Deserialize, Serialize, Clone, Debug, PartialEq, Eq)]
 pub struct FreshmanCommitteeAttendance {
@@ -291,16 +360,37 @@ pub struct FreshmanSeminarAttendance {
 }
 
 /// Row in 'house_meetings'
-#[derive(FromRow, Deserialize, Serialize, Clone, Debug, PartialEq, Eq)]
+#[derive(FromRow, Deserialize, Serialize, Clone, Debug, PartialEq, Eq, ToSchema)]
 pub struct HouseMeeting {
     /// Unique id for this house meeting
     pub id: i32,
     /// Date of this house meeting
     pub date: chrono::NaiveDate,
-    /// Whether this house meeting is 'active' (?)
+    /// Whether this house meeting is active. Cancelled house meetings are
+    /// inactive, and absences from them are not counted.
     pub active: bool,
 }
 
+/// Row in 'hm_excuse_requests'
+#[derive(FromRow, Deserialize, Serialize, Clone, Debug, PartialEq, Eq)]
+pub struct HouseMeetingExcuse {
+    /// Unique id for this excuse request
+    pub id: i32,
+    /// Foreign key into 'house_meetings'
+    pub meeting_id: i32,
+    /// Username of the member requesting an excuse, if they have an account
+    pub uid: Option<String>,
+    /// Foreign key into 'freshman_accounts', if the requester doesn't have an
+    /// account
+    pub fid: Option<i32>,
+    /// Reason the requester could not attend
+    pub excuse: String,
+    /// Whether eboard has accepted or denied this request
+    pub status: ExcuseStatus,
+    /// When this request was submitted
+    pub submitted: chrono::NaiveDateTime,
+}
+
 /// Row in 'in_housing_queue'
 #[derive(FromRow, Deserialize, Serialize, Clone, Debug, PartialEq, Eq)]
 pub struct InHousingQueue {
@@ -365,8 +455,19 @@ pub struct MemberSeminarAttendance {
     pub seminar_id: i32,
 }
 
+/// Row in 'packet_settings'
+#[derive(FromRow, Deserialize, Serialize, Clone, Debug, PartialEq, Eq, ToSchema)]
+pub struct PacketSettings {
+    /// Start of the first operating year these settings apply to
+    pub year_start: NaiveDate,
+    /// Most misc signatures that count towards a packet
+    pub misc_cap: i32,
+    /// How packet completion is rounded to a whole percentage for batches
+    pub rounding: PacketRounding,
+}
+
 /// Row in 'onfloor_datetime'
-#[derive(FromRow, Deserialize, Serialize, Clone, Debug, PartialEq, Eq)]
+#[derive(FromRow, Deserialize, Serialize, Clone, Debug, PartialEq, Eq, ToSchema)]
 pub struct OnFloorDate {
     /// Username of member granted on floor status
     pub uid: String,
@@ -404,6 +505,26 @@ pub struct Seminar {
     pub approved: bool,
 }
 
+/// Row in 'attendance_checkin_sessions' table
+#[derive(FromRow, Deserialize, Serialize, Clone, Debug, PartialEq, Eq)]
+pub struct CheckInSession {
+    /// Unique id for this check-in session
+    pub id: i32,
+    /// Short code attendees submit to check themselves in
+    pub code: String,
+    /// Foreign key into 'technical_seminars', if this is a seminar session
+    pub seminar_id: Option<i32>,
+    /// Foreign key into 'committee_meetings', if this is a directorship
+    /// session
+    pub meeting_id: Option<i32>,
+    /// Username of the member who opened this session
+    pub host: String,
+    /// When the code stops being accepted
+    pub expires: chrono::NaiveDateTime,
+    /// Whether the host has closed this session
+    pub closed: bool,
+}
+
 pub struct Batch {
     pub id: i32,
     pub name: String,
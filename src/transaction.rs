@@ -0,0 +1,94 @@
+use crate::app::AppState;
+use crate::error::AppError;
+use actix_web::{dev::Payload, web::Data, FromRequest, HttpRequest};
+use futures::future::{BoxFuture, LocalBoxFuture};
+use log::{log, Level};
+use sqlx::{PgConnection, Pool, Postgres, Transaction};
+use std::ops::{Deref, DerefMut};
+
+/// A database transaction covering everything a handler writes. Take it as a
+/// handler argument and run every query on `&mut *uow`, then `commit` once
+/// the handler has succeeded. Returning without committing, whether through
+/// an error or a panic, rolls everything back.
+pub struct UnitOfWork(Transaction<'static, Postgres>);
+
+impl UnitOfWork {
+    pub async fn begin(db: &Pool<Postgres>) -> Result<Self, AppError> {
+        match db.begin().await {
+            Ok(transaction) => {
+                log!(Level::Trace, "Acquired transaction");
+                Ok(UnitOfWork(transaction))
+            }
+            Err(e) => {
+                log!(Level::Error, "Failed to open transaction");
+                Err(AppError::Database(e))
+            }
+        }
+    }
+
+    pub async fn commit(self) -> Result<(), AppError> {
+        self.0.commit().await.map_err(|e| {
+            log!(Level::Error, "Transaction failed to commit");
+            AppError::Database(e)
+        })
+    }
+
+    /// Roll back now rather than when dropped, so the connection is ready for
+    /// reuse as soon as this returns
+    pub async fn rollback(self) {
+        if let Err(e) = self.0.rollback().await {
+            log!(Level::Error, "Transaction failed to rollback: {}", e);
+        }
+    }
+
+    /// Run `work` in its own transaction, committing if it succeeds and rolling
+    /// back if it fails
+    pub async fn run<T, F>(db: &Pool<Postgres>, work: F) -> Result<T, AppError>
+    where
+        F: for<'c> FnOnce(&'c mut PgConnection) -> BoxFuture<'c, Result<T, AppError>>,
+    {
+        let mut uow = UnitOfWork::begin(db).await?;
+        match work(&mut uow).await {
+            Ok(value) => {
+                uow.commit().await?;
+                Ok(value)
+            }
+            Err(e) => {
+                uow.rollback().await;
+                Err(e)
+            }
+        }
+    }
+}
+
+impl Deref for UnitOfWork {
+    type Target = PgConnection;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl DerefMut for UnitOfWork {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.0
+    }
+}
+
+/// Begins a transaction on the conditional database
+impl FromRequest for UnitOfWork {
+    type Error = AppError;
+    type Future = LocalBoxFuture<'static, Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, _payload: &mut Payload) -> Self::Future {
+        let state = req.app_data::<Data<AppState>>().cloned();
+        Box::pin(async move {
+            match state {
+                Some(state) => UnitOfWork::begin(&state.db).await,
+                None => Err(AppError::Internal(String::from(
+                    "UnitOfWork used without AppState",
+                ))),
+            }
+        })
+    }
+}
@@ -0,0 +1,146 @@
+use chrono::{Duration, NaiveDateTime, Utc};
+use conditional_backend::error::AppError;
+use conditional_backend::repo::{
+    attendance::{checkin, seminar},
+    Person,
+};
+use conditional_backend::transaction::UnitOfWork;
+use sqlx::{Pool, Postgres};
+
+mod common;
+
+/// Add a seminar hosted by `host` with a session open until `expires`,
+/// returning the seminar's id
+async fn open_seminar(db: &Pool<Postgres>, code: &str, host: &str, expires: NaiveDateTime) -> i32 {
+    let code = code.to_string();
+    let host = host.to_string();
+    UnitOfWork::run(db, |conn| {
+        Box::pin(async move {
+            let now = Utc::now().naive_utc();
+            let id = seminar::create_seminar(conn, "check-in test", &now, Some(&host)).await?;
+            checkin::open_session(conn, Some(id), None, &code, &host, &expires).await?;
+            Ok(id)
+        })
+    })
+    .await
+    .unwrap()
+}
+
+async fn close(db: &Pool<Postgres>, code: &str, host: &str) -> Result<(), AppError> {
+    let mut uow = UnitOfWork::begin(db).await.unwrap();
+    checkin::close_session(&mut uow, code, host).await?;
+    uow.commit().await
+}
+
+async fn delete_seminar(db: &Pool<Postgres>, id: i32) {
+    UnitOfWork::run(db, |conn| {
+        Box::pin(async move { seminar::delete_seminar(conn, id).await })
+    })
+    .await
+    .unwrap();
+}
+
+#[actix_web::test]
+#[ignore = "needs DATABASE_URL"]
+async fn codes_expire() {
+    let db = common::database().await;
+    let code = common::unique_code();
+    let now = Utc::now().naive_utc();
+    let id = open_seminar(&db, &code, "host", now + Duration::minutes(10)).await;
+
+    let before = checkin::get_open_session(
+        &mut db.acquire().await.unwrap(),
+        &code.to_ascii_lowercase(),
+        &now,
+    )
+    .await;
+    let after = checkin::get_open_session(
+        &mut db.acquire().await.unwrap(),
+        &code,
+        &(now + Duration::minutes(11)),
+    )
+    .await;
+    delete_seminar(&db, id).await;
+
+    assert_eq!(before.unwrap().seminar_id, Some(id));
+    assert!(matches!(after, Err(AppError::NotFound(_))));
+}
+
+#[actix_web::test]
+#[ignore = "needs DATABASE_URL"]
+async fn only_the_host_can_close_a_session() {
+    let db = common::database().await;
+    let code = common::unique_code();
+    let now = Utc::now().naive_utc();
+    let id = open_seminar(&db, &code, "host", now + Duration::minutes(10)).await;
+
+    let by_other = close(&db, &code, "someone").await;
+    let still_open = checkin::get_open_session(&mut db.acquire().await.unwrap(), &code, &now).await;
+    let by_host = close(&db, &code, "host").await;
+    let closed = checkin::get_open_session(&mut db.acquire().await.unwrap(), &code, &now).await;
+    let again = close(&db, &code, "host").await;
+    delete_seminar(&db, id).await;
+
+    assert!(matches!(by_other, Err(AppError::NotFound(_))));
+    assert!(still_open.is_ok());
+    assert!(by_host.is_ok());
+    assert!(matches!(closed, Err(AppError::NotFound(_))));
+    assert!(matches!(again, Err(AppError::NotFound(_))));
+}
+
+#[actix_web::test]
+#[ignore = "needs DATABASE_URL"]
+async fn checking_in_twice_counts_once() {
+    let db = common::database().await;
+    let code = common::unique_code();
+    let uid = code.to_ascii_lowercase();
+    let now = Utc::now().naive_utc();
+    let id = open_seminar(&db, &code, "host", now + Duration::minutes(10)).await;
+
+    let session = checkin::get_open_session(&mut db.acquire().await.unwrap(), &code, &now)
+        .await
+        .unwrap();
+    for _ in 0..2 {
+        let mut uow = UnitOfWork::begin(&db).await.unwrap();
+        checkin::check_in(&mut uow, &session, &Person::Member(uid.clone()))
+            .await
+            .unwrap();
+        uow.commit().await.unwrap();
+    }
+    let attended: i64 = sqlx::query_scalar(
+        "SELECT COUNT(*) FROM member_seminar_attendance WHERE uid = $1 AND seminar_id = $2",
+    )
+    .bind(&uid)
+    .bind(id)
+    .fetch_one(&db)
+    .await
+    .unwrap();
+    delete_seminar(&db, id).await;
+
+    assert_eq!(attended, 1);
+}
+
+#[actix_web::test]
+#[ignore = "needs DATABASE_URL"]
+async fn sessions_cant_close_during_a_check_in() {
+    let db = common::database().await;
+    let code = common::unique_code();
+    let now = Utc::now().naive_utc();
+    let id = open_seminar(&db, &code, "host", now + Duration::minutes(10)).await;
+
+    let mut checking_in = UnitOfWork::begin(&db).await.unwrap();
+    checkin::get_open_session(&mut checking_in, &code, &now)
+        .await
+        .unwrap();
+    let mut closing = UnitOfWork::begin(&db).await.unwrap();
+    sqlx::query("SET LOCAL lock_timeout = '100ms'")
+        .execute(&mut *closing)
+        .await
+        .unwrap();
+    let closed = checkin::close_session(&mut closing, &code, "host").await;
+    closing.rollback().await;
+    checking_in.rollback().await;
+    delete_seminar(&db, id).await;
+
+    assert!(matches!(closed, Err(AppError::Database(_))));
+}
@@ -0,0 +1,44 @@
+//! Helpers shared by the tests that run against a database. Those tests are
+//! `#[ignore]`d so a plain `cargo test` doesn't need one; run them with
+//! `cargo test -- --include-ignored`.
+
+// Each test crate only uses some of these
+#![allow(dead_code)]
+
+use chrono::Utc;
+use sqlx::{postgres::PgPoolOptions, Pool, Postgres};
+use std::env;
+
+/// The conditional database named by `DATABASE_URL`
+pub async fn database() -> Pool<Postgres> {
+    connect("DATABASE_URL").await
+}
+
+/// The packet database named by `PACKET_DATABASE_URL`
+pub async fn packet_database() -> Pool<Postgres> {
+    connect("PACKET_DATABASE_URL").await
+}
+
+async fn connect(var: &str) -> Pool<Postgres> {
+    let url = env::var(var).unwrap_or_else(|_| panic!("{var} must be set for database tests"));
+    PgPoolOptions::new().connect(&url).await.unwrap()
+}
+
+fn nanos() -> i64 {
+    Utc::now().timestamp_nanos_opt().unwrap_or_default()
+}
+
+/// A uid starting with `prefix` no other test run will use
+pub fn unique_uid(prefix: &str) -> String {
+    format!("{prefix}{}", nanos() % 1_000_000_000)
+}
+
+/// A check-in code no other test run will use
+pub fn unique_code() -> String {
+    format!("T{:07}", nanos() % 10_000_000)
+}
+
+/// A seminar name for `test` no other test run will use
+pub fn unique_name(test: &str) -> String {
+    format!("{test} {}", nanos())
+}
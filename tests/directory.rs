@@ -0,0 +1,178 @@
+use conditional_backend::ldap::{
+    directory::{Directory, MemoryDirectory},
+    group::Group,
+    search::SearchAttrs,
+};
+
+fn fixture() -> MemoryDirectory {
+    MemoryDirectory::from_file(concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/fixtures/directory.json"
+    ))
+    .unwrap()
+}
+
+fn uids(users: Vec<conditional_backend::ldap::user::LdapUser>) -> Vec<String> {
+    users.into_iter().map(|u| u.uid).collect()
+}
+
+#[actix_web::test]
+async fn get_user_matches_exactly() {
+    let directory = fixture();
+    assert_eq!(
+        uids(
+            directory
+                .get_user("mom", &SearchAttrs::default())
+                .await
+                .unwrap()
+        ),
+        ["mom"]
+    );
+    assert!(directory
+        .get_user("mo", &SearchAttrs::default())
+        .await
+        .unwrap()
+        .is_empty());
+}
+
+#[actix_web::test]
+async fn get_users_matches_any_uid() {
+    let directory = fixture();
+    let mut found = uids(
+        directory
+            .get_users(
+                &[String::from("kid"), String::from("mom"), String::from("mo")],
+                &SearchAttrs::default(),
+            )
+            .await
+            .unwrap(),
+    );
+    found.sort();
+    assert_eq!(found, ["kid", "mom"]);
+    assert!(directory
+        .get_users(&[], &SearchAttrs::default())
+        .await
+        .unwrap()
+        .is_empty());
+}
+
+#[actix_web::test]
+async fn search_matches_uid_or_name() {
+    let directory = fixture();
+    assert_eq!(
+        uids(
+            directory
+                .search_users("DAD", &SearchAttrs::default())
+                .await
+                .unwrap()
+        ),
+        ["dad"]
+    );
+    assert_eq!(
+        uids(
+            directory
+                .search_users("child", &SearchAttrs::default())
+                .await
+                .unwrap()
+        ),
+        ["kid"]
+    );
+}
+
+#[actix_web::test]
+async fn group_members() {
+    let directory = fixture();
+    assert_eq!(
+        uids(
+            directory
+                .get_group_members(Group::Active, &SearchAttrs::default())
+                .await
+                .unwrap()
+        ),
+        ["mom", "dad", "kid"]
+    );
+    assert_eq!(
+        uids(
+            directory
+                .get_group_members(Group::Member, &SearchAttrs::default())
+                .await
+                .unwrap()
+        ),
+        ["mom", "dad", "kid", "alum"]
+    );
+    assert_eq!(
+        uids(
+            directory
+                .get_intro_members(&SearchAttrs::default())
+                .await
+                .unwrap()
+        ),
+        ["kid"]
+    );
+}
+
+#[actix_web::test]
+async fn active_upperclassmen_exclude_intro_members() {
+    let directory = fixture();
+    assert_eq!(
+        uids(
+            directory
+                .get_active_upperclassmen(&SearchAttrs::default())
+                .await
+                .unwrap()
+        ),
+        ["mom", "dad"]
+    );
+}
+
+#[actix_web::test]
+async fn memory_directory_has_no_cache() {
+    let directory = MemoryDirectory::default();
+    assert!(directory.cache().is_none());
+    directory.invalidate_cache();
+    assert!(directory
+        .get_user("mom", &SearchAttrs::default())
+        .await
+        .unwrap()
+        .is_empty());
+}
+
+#[actix_web::test]
+async fn sensitive_attributes_are_redacted_by_default() {
+    let directory = fixture();
+    let mom = directory
+        .get_user("mom", &SearchAttrs::default())
+        .await
+        .unwrap()
+        .remove(0);
+    assert_eq!(mom.drink_balance, None);
+    assert_eq!(mom.mobile, None);
+    assert_eq!(mom.ibutton, None);
+    assert_eq!(mom.room_number.as_deref(), Some("3013"));
+    assert_eq!(mom.onfloor, Some(true));
+}
+
+#[actix_web::test]
+async fn sensitive_attributes_can_be_requested() {
+    let directory = fixture();
+    let mom = directory
+        .get_user("mom", &SearchAttrs::default().with_sensitive())
+        .await
+        .unwrap()
+        .remove(0);
+    assert_eq!(mom.drink_balance, Some(500));
+    assert_eq!(mom.mobile, Some(vec![String::from("585-555-0100")]));
+}
+
+#[actix_web::test]
+async fn minimal_attributes_leave_optional_fields_empty() {
+    let directory = fixture();
+    let mom = directory
+        .get_user("mom", &SearchAttrs::minimal())
+        .await
+        .unwrap()
+        .remove(0);
+    assert_eq!(mom.cn, "Mom Mother");
+    assert_eq!(mom.room_number, None);
+    assert_eq!(mom.member_since, None);
+}
@@ -0,0 +1,155 @@
+use actix_web::test::{call_service, init_service, read_body_json, TestRequest};
+use actix_web::{http::StatusCode, web, App, HttpResponse, ResponseError};
+use conditional_backend::error::{AppError, RequestId};
+use conditional_backend::schema::api::ErrorBody;
+use sqlx::error::{DatabaseError, ErrorKind};
+use std::{error::Error, fmt};
+
+#[derive(Debug)]
+struct FakeDatabaseError(ErrorKind);
+
+impl fmt::Display for FakeDatabaseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "violates constraint secret_internal_name")
+    }
+}
+
+impl Error for FakeDatabaseError {}
+
+impl DatabaseError for FakeDatabaseError {
+    fn message(&self) -> &str {
+        "violates constraint secret_internal_name"
+    }
+
+    fn as_error(&self) -> &(dyn Error + Send + Sync + 'static) {
+        self
+    }
+
+    fn as_error_mut(&mut self) -> &mut (dyn Error + Send + Sync + 'static) {
+        self
+    }
+
+    fn into_error(self: Box<Self>) -> Box<dyn Error + Send + Sync + 'static> {
+        self
+    }
+
+    fn kind(&self) -> ErrorKind {
+        match self.0 {
+            ErrorKind::UniqueViolation => ErrorKind::UniqueViolation,
+            ErrorKind::ForeignKeyViolation => ErrorKind::ForeignKeyViolation,
+            ErrorKind::NotNullViolation => ErrorKind::NotNullViolation,
+            ErrorKind::CheckViolation => ErrorKind::CheckViolation,
+            _ => ErrorKind::Other,
+        }
+    }
+}
+
+fn database_error(kind: ErrorKind) -> AppError {
+    AppError::Database(sqlx::Error::Database(Box::new(FakeDatabaseError(kind))))
+}
+
+#[test]
+fn maps_database_errors() {
+    let cases = [
+        (
+            AppError::Database(sqlx::Error::RowNotFound),
+            StatusCode::NOT_FOUND,
+            "not_found",
+        ),
+        (
+            database_error(ErrorKind::UniqueViolation),
+            StatusCode::CONFLICT,
+            "conflict",
+        ),
+        (
+            database_error(ErrorKind::ForeignKeyViolation),
+            StatusCode::UNPROCESSABLE_ENTITY,
+            "invalid_reference",
+        ),
+        (
+            database_error(ErrorKind::CheckViolation),
+            StatusCode::UNPROCESSABLE_ENTITY,
+            "invalid_value",
+        ),
+        (
+            database_error(ErrorKind::Other),
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "database_error",
+        ),
+        (
+            AppError::Database(sqlx::Error::PoolTimedOut),
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "database_error",
+        ),
+    ];
+    for (error, status, code) in cases {
+        assert_eq!(error.status_code(), status);
+        assert_eq!(error.code(), code);
+    }
+}
+
+#[test]
+fn hides_internal_details() {
+    for error in [
+        database_error(ErrorKind::UniqueViolation),
+        database_error(ErrorKind::Other),
+        AppError::Ldap(anyhow::anyhow!("bind failed for secret_internal_name")),
+        AppError::Internal(String::from("secret_internal_name")),
+    ] {
+        assert!(!error.message().contains("secret_internal_name"));
+        // Logs still get the details
+        assert!(error.to_string().contains("secret_internal_name"));
+    }
+    assert_eq!(
+        AppError::NotFound(String::from("No freshman account 3")).message(),
+        "No freshman account 3"
+    );
+}
+
+async fn not_found() -> HttpResponse {
+    AppError::NotFound(String::from("Nothing here")).into()
+}
+
+#[actix_web::test]
+async fn error_bodies_carry_the_request_id() {
+    let app = init_service(
+        App::new()
+            .wrap(RequestId)
+            .route("/missing", web::get().to(not_found))
+            .route("/ok", web::get().to(HttpResponse::Ok)),
+    )
+    .await;
+
+    let req = TestRequest::get()
+        .uri("/missing")
+        .insert_header(("X-Request-Id", "abc-123"))
+        .to_request();
+    let res = call_service(&app, req).await;
+    assert_eq!(res.status(), StatusCode::NOT_FOUND);
+    assert_eq!(res.headers().get("x-request-id").unwrap(), "abc-123");
+    let body: ErrorBody = read_body_json(res).await;
+    assert_eq!(
+        body,
+        ErrorBody {
+            code: String::from("not_found"),
+            message: String::from("Nothing here"),
+            request_id: Some(String::from("abc-123")),
+        }
+    );
+
+    // Ids that could be used to forge log lines are replaced
+    let req = TestRequest::get()
+        .uri("/missing")
+        .insert_header(("X-Request-Id", "abc\tdef"))
+        .to_request();
+    let res = call_service(&app, req).await;
+    let id = res.headers().get("x-request-id").unwrap().to_owned();
+    let body: ErrorBody = read_body_json(res).await;
+    assert_ne!(id, "abc\tdef");
+    assert_eq!(body.request_id.as_deref(), id.to_str().ok());
+
+    let req = TestRequest::get().uri("/ok").to_request();
+    let res = call_service(&app, req).await;
+    assert_eq!(res.status(), StatusCode::OK);
+    assert!(res.headers().contains_key("x-request-id"));
+}
@@ -0,0 +1,161 @@
+use chrono::{NaiveDate, NaiveDateTime};
+use conditional_backend::ldap::user::LdapUser;
+use conditional_backend::repo::{attendance::seminar, evals};
+use conditional_backend::schema::api::Packet;
+use conditional_backend::schema::db::PacketSettings;
+use conditional_backend::transaction::UnitOfWork;
+use sqlx::{Pool, Postgres};
+
+mod common;
+
+fn member(uid: &str, rit_username: &str) -> LdapUser {
+    serde_json::from_value(serde_json::json!({
+        "dn": format!("uid={uid},cn=users,cn=accounts,dc=csh,dc=rit,dc=edu"),
+        "cn": format!("Member {uid}"),
+        "uid": uid,
+        "rit_username": rit_username,
+        "groups": ["member"],
+        "krb_principal_name": format!("{uid}@CSH.RIT.EDU"),
+        "mail": [],
+    }))
+    .unwrap()
+}
+
+fn date(year: i32, month: u32, day: u32) -> NaiveDateTime {
+    NaiveDate::from_ymd_opt(year, month, day)
+        .unwrap()
+        .and_hms_opt(18, 0, 0)
+        .unwrap()
+}
+
+/// Add an approved seminar on `timestamp` attended by `uid`
+async fn attended_seminar(db: &Pool<Postgres>, uid: &str, timestamp: NaiveDateTime) -> i32 {
+    let uid = uid.to_string();
+    let id = UnitOfWork::run(db, |conn| {
+        Box::pin(async move {
+            let id = seminar::create_seminar(conn, "evals test", &timestamp, None).await?;
+            seminar::add_seminar_attendance(conn, id, &[], &[uid]).await?;
+            Ok(id)
+        })
+    })
+    .await
+    .unwrap();
+    sqlx::query("UPDATE technical_seminars SET approved = true WHERE id = $1")
+        .bind(id)
+        .execute(db)
+        .await
+        .unwrap();
+    id
+}
+
+async fn delete_seminars(db: &Pool<Postgres>, ids: Vec<i32>) {
+    UnitOfWork::run(db, |conn| {
+        Box::pin(async move {
+            for id in ids {
+                seminar::delete_seminar(conn, id).await?;
+            }
+            Ok(())
+        })
+    })
+    .await
+    .unwrap();
+}
+
+#[actix_web::test]
+#[ignore = "needs DATABASE_URL"]
+async fn member_statuses_only_count_since() {
+    let db = common::database().await;
+    let uid = common::unique_uid("evals");
+    let ids = vec![
+        attended_seminar(&db, &uid, date(2020, 10, 1)).await,
+        attended_seminar(&db, &uid, date(2021, 10, 1)).await,
+    ];
+
+    let statuses = evals::get_member_statuses(&db, &[member(&uid, "abc1234")], &date(2021, 6, 1))
+        .await
+        .unwrap();
+    delete_seminars(&db, ids).await;
+
+    assert_eq!(statuses.len(), 1);
+    assert_eq!(statuses[0].uid, uid);
+    assert_eq!(statuses[0].name, format!("Member {uid}"));
+    assert_eq!(statuses[0].seminars, 1);
+    assert_eq!(statuses[0].directorships, 0);
+}
+
+#[actix_web::test]
+#[ignore = "needs DATABASE_URL"]
+async fn intro_statuses_count_everything_and_need_a_packet() {
+    let db = common::database().await;
+    let uid = common::unique_uid("evals");
+    let other = common::unique_uid("evals");
+    let ids = vec![
+        attended_seminar(&db, &uid, date(2020, 10, 1)).await,
+        attended_seminar(&db, &uid, date(2021, 10, 1)).await,
+    ];
+    let packets = vec![Packet {
+        username: String::from(" ABC1234 "),
+        name: String::from("Intro Member"),
+        start: None,
+        upper_signatures: 3,
+        max_upper: 10,
+        misc_signatures: 2,
+    }];
+
+    let statuses = evals::get_intro_statuses(
+        &mut db.acquire().await.unwrap(),
+        &[
+            member(&uid, "uid=abc1234,ou=People,dc=rit,dc=edu"),
+            member(&other, "xyz9876"),
+        ],
+        &packets,
+        &PacketSettings::default(),
+    )
+    .await
+    .unwrap();
+    delete_seminars(&db, ids).await;
+
+    let ours: Vec<_> = statuses
+        .iter()
+        .filter(|s| s.uid.as_deref() == Some(uid.as_str()))
+        .collect();
+    assert_eq!(ours.len(), 1);
+    assert_eq!(ours[0].name, "Intro Member");
+    assert_eq!(ours[0].seminars, 2);
+    assert_eq!(ours[0].signatures, 5);
+    assert!(!statuses
+        .iter()
+        .any(|s| s.uid.as_deref() == Some(other.as_str())));
+}
+
+#[actix_web::test]
+#[ignore = "needs DATABASE_URL"]
+async fn intro_statuses_use_the_newest_packet() {
+    let db = common::database().await;
+    let uid = common::unique_uid("evals");
+    let packet = |upper_signatures| Packet {
+        username: String::from("abc1234"),
+        name: String::from("Intro Member"),
+        start: None,
+        upper_signatures,
+        max_upper: 10,
+        misc_signatures: 0,
+    };
+    // Packets come from the packet database newest first
+    let packets = vec![packet(7), packet(1)];
+
+    let statuses = evals::get_intro_statuses(
+        &mut db.acquire().await.unwrap(),
+        &[member(&uid, "abc1234")],
+        &packets,
+        &PacketSettings::default(),
+    )
+    .await
+    .unwrap();
+
+    let ours = statuses
+        .iter()
+        .find(|s| s.uid.as_deref() == Some(uid.as_str()))
+        .unwrap();
+    assert_eq!(ours.signatures, 7);
+}
@@ -0,0 +1,92 @@
+use chrono::NaiveDate;
+use conditional_backend::api::users::freshmen::apply_edit;
+use conditional_backend::schema::api::FreshmanAccountEdit;
+use conditional_backend::schema::db::FreshmanAccount;
+use std::collections::HashSet;
+
+fn account() -> FreshmanAccount {
+    FreshmanAccount {
+        id: 1,
+        name: String::from("Freshman"),
+        eval_date: NaiveDate::from_ymd_opt(2024, 10, 1).unwrap(),
+        onfloor_status: Some(true),
+        room_number: Some(String::from("3013")),
+        signatures_missed: None,
+        rit_username: Some(String::from("abc1234")),
+    }
+}
+
+fn taken() -> HashSet<String> {
+    HashSet::from([String::from("xyz1234")])
+}
+
+#[test]
+fn applies_edits() {
+    let edited = apply_edit(
+        &account(),
+        FreshmanAccountEdit {
+            name: Some(String::from(" New Name ")),
+            eval_date: NaiveDate::from_ymd_opt(2024, 11, 1),
+            onfloor_status: Some(false),
+            room_number: Some(String::new()),
+            rit_username: Some(String::from("DEF1234")),
+        },
+        &taken(),
+    )
+    .unwrap();
+    assert_eq!(edited.name, "New Name");
+    assert_eq!(
+        edited.eval_date,
+        NaiveDate::from_ymd_opt(2024, 11, 1).unwrap()
+    );
+    assert_eq!(edited.onfloor_status, Some(false));
+    assert_eq!(edited.room_number, None);
+    assert_eq!(edited.rit_username.as_deref(), Some("def1234"));
+    assert_eq!(edited.id, 1);
+}
+
+#[test]
+fn rejects_empty_edits() {
+    assert!(apply_edit(&account(), FreshmanAccountEdit::default(), &taken()).is_err());
+}
+
+#[test]
+fn on_floor_freshmen_need_a_room() {
+    let errors = apply_edit(
+        &account(),
+        FreshmanAccountEdit {
+            room_number: Some(String::from("  ")),
+            ..FreshmanAccountEdit::default()
+        },
+        &taken(),
+    )
+    .unwrap_err();
+    assert_eq!(errors.len(), 1);
+}
+
+#[test]
+fn rejects_invalid_fields() {
+    let errors = apply_edit(
+        &account(),
+        FreshmanAccountEdit {
+            name: Some(String::from(" ")),
+            rit_username: Some(String::from("Xyz1234")),
+            ..FreshmanAccountEdit::default()
+        },
+        &taken(),
+    )
+    .unwrap_err();
+    assert_eq!(errors.len(), 2);
+
+    let errors = apply_edit(
+        &account(),
+        FreshmanAccountEdit {
+            name: Some("x".repeat(65)),
+            rit_username: Some(String::from("abcdefghijk")),
+            ..FreshmanAccountEdit::default()
+        },
+        &taken(),
+    )
+    .unwrap_err();
+    assert_eq!(errors.len(), 2);
+}
@@ -0,0 +1,163 @@
+use chrono::NaiveDate;
+use conditional_backend::api::users::convert::{propose, resolve};
+use conditional_backend::identity::FreshmanAccountIdentity;
+use conditional_backend::ldap::user::LdapUser;
+use conditional_backend::schema::api::{
+    ConversionLogEntry, ConversionPair, ConversionProposal, ConvertedData,
+};
+use conditional_backend::schema::db::FreshmanConversion;
+use std::collections::HashSet;
+
+fn account(fid: i32, rit_username: Option<&str>) -> FreshmanAccountIdentity {
+    FreshmanAccountIdentity {
+        fid,
+        name: format!("Freshman {fid}"),
+        rit_username: rit_username.map(String::from),
+    }
+}
+
+fn member(uid: &str, rit_dn: &str) -> LdapUser {
+    serde_json::from_value(serde_json::json!({
+        "dn": format!("uid={uid},cn=users,cn=accounts,dc=csh,dc=rit,dc=edu"),
+        "cn": format!("Member {uid}"),
+        "uid": uid,
+        "rit_username": rit_dn,
+        "groups": ["member", "intromembers"],
+        "krb_principal_name": format!("{uid}@CSH.RIT.EDU"),
+        "mail": [],
+    }))
+    .unwrap()
+}
+
+fn pair(fid: i32, uid: Option<&str>) -> ConversionPair {
+    ConversionPair {
+        fid,
+        uid: uid.map(String::from),
+    }
+}
+
+fn proposals() -> Vec<ConversionProposal> {
+    propose(
+        &[
+            account(1, Some("abc1234")),
+            account(2, Some("dup1234")),
+            account(3, None),
+        ],
+        &[
+            member("alice", "uid=ABC1234,ou=People,dc=rit,dc=edu"),
+            member("bob", "dup1234"),
+            member("carol", "dup1234"),
+        ],
+    )
+}
+
+fn known_uids() -> HashSet<String> {
+    ["alice", "bob", "carol", "dave"]
+        .into_iter()
+        .map(String::from)
+        .collect()
+}
+
+#[test]
+fn proposes_unique_matches() {
+    let proposals = proposals();
+    assert_eq!(proposals.len(), 3);
+
+    assert_eq!(proposals[0].uid.as_deref(), Some("alice"));
+    assert_eq!(proposals[0].member_name.as_deref(), Some("Member alice"));
+    assert!(proposals[0].candidates.is_empty());
+
+    assert_eq!(proposals[1].uid, None);
+    assert_eq!(proposals[1].candidates, vec!["bob", "carol"]);
+
+    assert_eq!(proposals[2].uid, None);
+    assert!(proposals[2].candidates.is_empty());
+}
+
+#[test]
+fn resolves_proposed_and_overridden_pairs() {
+    let results = resolve(
+        &[pair(1, None), pair(2, Some(" carol "))],
+        &proposals(),
+        &known_uids(),
+    );
+    assert!(results.iter().all(|r| r.errors.is_empty()));
+    assert_eq!(results[0].uid.as_deref(), Some("alice"));
+    assert!(results[0].proposed);
+    assert_eq!(results[1].uid.as_deref(), Some("carol"));
+    assert!(!results[1].proposed);
+}
+
+#[test]
+fn rejects_unresolvable_pairs() {
+    let results = resolve(
+        &[
+            pair(2, None),
+            pair(3, None),
+            pair(4, Some("dave")),
+            pair(1, Some("eve")),
+        ],
+        &proposals(),
+        &known_uids(),
+    );
+    // Ambiguous match, no match, unknown freshman, unknown member
+    assert!(results.iter().all(|r| r.errors.len() == 1));
+    assert!(results[0].errors[0].contains("bob, carol"));
+}
+
+#[test]
+fn rejects_duplicates() {
+    let results = resolve(
+        &[pair(1, None), pair(1, Some("bob")), pair(2, Some("alice"))],
+        &proposals(),
+        &known_uids(),
+    );
+    assert!(results[0].errors.is_empty());
+    assert_eq!(results[1].errors.len(), 1);
+    assert_eq!(results[2].errors.len(), 1);
+}
+
+#[test]
+fn logged_conversions_count_what_moved() {
+    let converted = NaiveDate::from_ymd_opt(2024, 10, 1)
+        .unwrap()
+        .and_hms_opt(12, 0, 0)
+        .unwrap();
+    let entry = ConversionLogEntry::from(FreshmanConversion {
+        id: 7,
+        fid: 3,
+        uid: String::from("alice"),
+        converted,
+        undone: None,
+        name: String::from("Freshman 3"),
+        eval_date: converted.date(),
+        onfloor_status: Some(false),
+        room_number: None,
+        signatures_missed: None,
+        rit_username: Some(String::from("abc1234")),
+        committee_attendance: vec![1, 2],
+        hm_attendance: vec![3],
+        seminar_attendance: vec![],
+        excuse_requests: vec![4],
+        batch_users: vec![5, 6, 7],
+        // The member already had a batch pull, so the freshman's wasn't moved
+        batch_pull_approved: Some(true),
+        batch_pull_reason: Some(String::from("Reason")),
+        batch_pull_puller: Some(String::from("bob")),
+        batch_pull: None,
+    });
+    assert_eq!(entry.id, 7);
+    assert_eq!(entry.freshman.id, 3);
+    assert_eq!(entry.freshman.rit_username.as_deref(), Some("abc1234"));
+    assert_eq!(
+        entry.moved,
+        ConvertedData {
+            seminars: 0,
+            directorships: 2,
+            house_meetings: 1,
+            excuses: 1,
+            batches: 3,
+            batch_pulls: 0,
+        }
+    );
+}
@@ -0,0 +1,79 @@
+use conditional_backend::api::users::import::{parse_csv, parse_json, validate};
+use std::collections::HashSet;
+
+const CSV: &str = "name,rit_username,eval_date,onfloor_status,room_number
+Kid Child, ABC1234 ,2024-10-01,true,3013
+Other Kid,def5678,2024-10-01,false,
+";
+
+#[test]
+fn parses_csv_with_trimmed_cells_and_empty_as_missing() {
+    let rows = parse_csv(CSV.as_bytes());
+    assert_eq!(rows.len(), 2);
+    let first = rows[0].as_ref().unwrap();
+    assert_eq!(first.name.as_deref(), Some("Kid Child"));
+    assert_eq!(first.rit_username.as_deref(), Some("ABC1234"));
+    assert_eq!(first.onfloor_status, Some(true));
+    assert_eq!(rows[1].as_ref().unwrap().room_number, None);
+
+    let freshmen = validate(rows, &HashSet::new()).unwrap();
+    assert_eq!(freshmen[0].rit_username, "abc1234");
+    assert_eq!(freshmen[0].room_number.as_deref(), Some("3013"));
+    assert!(!freshmen[1].onfloor_status);
+}
+
+#[test]
+fn bad_csv_cells_are_row_errors() {
+    let rows = parse_csv(
+        "name,rit_username,eval_date\nKid Child,abc1234,not a date\nOther Kid,def5678,2024-10-01\n"
+            .as_bytes(),
+    );
+    let errors = validate(rows, &HashSet::new()).unwrap_err();
+    assert_eq!(errors.len(), 1);
+    assert_eq!(errors[0].row, 1);
+}
+
+#[test]
+fn json_rows_are_parsed_independently() {
+    let rows = parse_json(
+        br#"[
+            {"name": "Kid Child", "rit_username": "abc1234", "eval_date": "2024-10-01"},
+            {"name": "Other Kid", "rit_username": "def5678", "eval_date": 12}
+        ]"#,
+    )
+    .unwrap();
+    assert!(rows[0].is_ok());
+    assert!(rows[1].is_err());
+    assert!(parse_json(br#"{"name": "Kid Child"}"#).is_err());
+}
+
+#[test]
+fn validation_reports_every_problem_by_row() {
+    let rows = parse_json(
+        br#"[
+            {"name": "Kid Child", "rit_username": "abc1234", "eval_date": "2024-10-01"},
+            {"name": "Copy Kid", "rit_username": "ABC1234", "eval_date": "2024-10-01"},
+            {"name": " ", "rit_username": "ghi9012", "onfloor_status": true},
+            {"name": "Old Kid", "rit_username": "old1234", "eval_date": "2024-10-01"}
+        ]"#,
+    )
+    .unwrap();
+    let existing = HashSet::from([String::from("old1234")]);
+    let errors = validate(rows, &existing).unwrap_err();
+    let rows: Vec<usize> = errors.iter().map(|e| e.row).collect();
+    assert_eq!(rows, [2, 3, 4]);
+    assert_eq!(errors[0].errors, ["abc1234 is already in row 1"]);
+    assert_eq!(
+        errors[1].errors,
+        [
+            "name is required",
+            "eval_date is required",
+            "room_number is required for on floor freshmen"
+        ]
+    );
+    assert_eq!(errors[2].rit_username.as_deref(), Some("old1234"));
+    assert_eq!(
+        errors[2].errors,
+        ["a freshman account already exists for old1234"]
+    );
+}
@@ -0,0 +1,259 @@
+use chrono::NaiveDate;
+use conditional_backend::error::AppError;
+use conditional_backend::repo::{attendance::house, Person};
+use conditional_backend::schema::api::{
+    HouseAttendance, HouseMeetingAttendanceEdit, MemberHouseAttendance, MemberHouseMeetingStatus,
+};
+use conditional_backend::schema::db::{AttendanceStatus, ExcuseStatus};
+use conditional_backend::transaction::UnitOfWork;
+use sqlx::{Pool, Postgres};
+
+mod common;
+
+fn date(year: i32, month: u32, day: u32) -> NaiveDate {
+    NaiveDate::from_ymd_opt(year, month, day).unwrap()
+}
+
+/// Add a house meeting on `date` with `uid` on the roster as `status`
+async fn house_meeting(
+    db: &Pool<Postgres>,
+    date: NaiveDate,
+    uid: &str,
+    status: AttendanceStatus,
+) -> i32 {
+    let attendance = HouseAttendance {
+        date,
+        members: vec![MemberHouseAttendance {
+            uid: uid.to_string(),
+            att_status: status,
+        }],
+        frosh: vec![],
+    };
+    UnitOfWork::run(db, |conn| {
+        Box::pin(async move { house::record_house_meeting(conn, &attendance).await })
+    })
+    .await
+    .unwrap()
+}
+
+async fn set_active(db: &Pool<Postgres>, id: i32, active: bool) {
+    UnitOfWork::run(db, |conn| {
+        Box::pin(async move { house::set_house_meeting_active(conn, id, active).await })
+    })
+    .await
+    .unwrap();
+}
+
+async fn submit_excuse(db: &Pool<Postgres>, id: i32, uid: &str) -> Result<(), AppError> {
+    let mut uow = UnitOfWork::begin(db).await.unwrap();
+    house::submit_excuse(&mut uow, id, &Person::Member(uid.to_string()), "sick").await?;
+    uow.commit().await
+}
+
+async fn decide_excuse(
+    db: &Pool<Postgres>,
+    id: i32,
+    decision: ExcuseStatus,
+) -> Result<(), AppError> {
+    let mut uow = UnitOfWork::begin(db).await.unwrap();
+    house::decide_excuse(&mut uow, id, decision).await?;
+    uow.commit().await
+}
+
+async fn edit_attendee(
+    db: &Pool<Postgres>,
+    id: i32,
+    uid: &str,
+    attendance_status: AttendanceStatus,
+    excuse: Option<&str>,
+) -> Result<(), AppError> {
+    let edit = HouseMeetingAttendanceEdit {
+        attendance_status,
+        excuse: excuse.map(ToString::to_string),
+    };
+    let mut uow = UnitOfWork::begin(db).await.unwrap();
+    house::edit_attendee(&mut uow, id, &Person::Member(uid.to_string()), &edit).await?;
+    uow.commit().await
+}
+
+/// Id of `uid`'s pending excuse request for house meeting `meeting_id`
+async fn pending_excuse(db: &Pool<Postgres>, meeting_id: i32, uid: &str) -> Option<i32> {
+    house::get_pending_excuses(db)
+        .await
+        .unwrap()
+        .into_iter()
+        .find(|r| r.meeting_id == meeting_id && r.uid.as_deref() == Some(uid))
+        .map(|r| r.id)
+}
+
+/// `uid`'s status on house meeting `id`'s roster, if they're on it
+async fn roster_status(
+    db: &Pool<Postgres>,
+    id: i32,
+    uid: &str,
+) -> Option<MemberHouseMeetingStatus> {
+    house::get_house_meeting(db, id)
+        .await
+        .unwrap()
+        .members
+        .into_iter()
+        .find(|m| m.uid == uid)
+}
+
+async fn delete_house_meeting(db: &Pool<Postgres>, id: i32) {
+    UnitOfWork::run(db, |conn| {
+        Box::pin(async move { house::delete_house_meeting(conn, id).await })
+    })
+    .await
+    .unwrap();
+}
+
+#[actix_web::test]
+#[ignore = "needs DATABASE_URL"]
+async fn cancelled_meetings_are_not_missed() {
+    let db = common::database().await;
+    let uid = common::unique_uid("hm");
+    let person = Person::Member(uid.clone());
+    let since = date(2021, 6, 1).and_hms_opt(0, 0, 0).unwrap();
+    let id = house_meeting(&db, date(2021, 10, 4), &uid, AttendanceStatus::Absent).await;
+
+    let absences = house::get_absences(&db, &person, &since).await.unwrap();
+    let missed = house::get_missed_house_meetings(&db, &person, &since)
+        .await
+        .unwrap();
+    set_active(&db, id, false).await;
+    let cancelled_absences = house::get_absences(&db, &person, &since).await.unwrap();
+    let cancelled_missed = house::get_missed_house_meetings(&db, &person, &since)
+        .await
+        .unwrap();
+    let roster = house::get_house_meeting(&db, id).await.unwrap();
+    delete_house_meeting(&db, id).await;
+
+    assert_eq!(absences.len(), 1);
+    assert_eq!(missed.len(), 1);
+    assert!(cancelled_absences.is_empty());
+    assert!(cancelled_missed.is_empty());
+    assert!(!roster.active);
+    assert_eq!(roster.members.len(), 1);
+}
+
+#[actix_web::test]
+#[ignore = "needs DATABASE_URL"]
+async fn accepted_excuses_excuse_the_requester() {
+    let db = common::database().await;
+    let uid = common::unique_uid("hm");
+    let id = house_meeting(&db, date(2021, 10, 4), &uid, AttendanceStatus::Absent).await;
+
+    let submitted = submit_excuse(&db, id, &uid).await;
+    let duplicate = submit_excuse(&db, id, &uid).await;
+    let request = pending_excuse(&db, id, &uid).await.unwrap();
+    let accepted = decide_excuse(&db, request, ExcuseStatus::Accepted).await;
+    let decided_again = decide_excuse(&db, request, ExcuseStatus::Denied).await;
+    let status = roster_status(&db, id, &uid).await.unwrap();
+    let pending = pending_excuse(&db, id, &uid).await;
+    delete_house_meeting(&db, id).await;
+
+    assert!(submitted.is_ok());
+    assert!(matches!(duplicate, Err(AppError::Conflict(_))));
+    assert!(accepted.is_ok());
+    assert!(matches!(decided_again, Err(AppError::NotFound(_))));
+    assert_eq!(status.attendance_status, AttendanceStatus::Excused);
+    assert_eq!(status.excuse.as_deref(), Some("sick"));
+    assert!(pending.is_none());
+}
+
+#[actix_web::test]
+#[ignore = "needs DATABASE_URL"]
+async fn accepted_excuses_add_the_requester_to_the_roster() {
+    let db = common::database().await;
+    let uid = common::unique_uid("hm");
+    let other = common::unique_uid("hm");
+    let id = house_meeting(&db, date(2021, 10, 4), &other, AttendanceStatus::Attended).await;
+
+    submit_excuse(&db, id, &uid).await.unwrap();
+    let request = pending_excuse(&db, id, &uid).await.unwrap();
+    decide_excuse(&db, request, ExcuseStatus::Accepted)
+        .await
+        .unwrap();
+    let status = roster_status(&db, id, &uid).await;
+    delete_house_meeting(&db, id).await;
+
+    assert_eq!(
+        status.map(|s| s.attendance_status),
+        Some(AttendanceStatus::Excused)
+    );
+}
+
+#[actix_web::test]
+#[ignore = "needs DATABASE_URL"]
+async fn denied_excuses_leave_attendance_alone() {
+    let db = common::database().await;
+    let uid = common::unique_uid("hm");
+    let id = house_meeting(&db, date(2021, 10, 4), &uid, AttendanceStatus::Absent).await;
+
+    submit_excuse(&db, id, &uid).await.unwrap();
+    let request = pending_excuse(&db, id, &uid).await.unwrap();
+    decide_excuse(&db, request, ExcuseStatus::Denied)
+        .await
+        .unwrap();
+    let status = roster_status(&db, id, &uid).await.unwrap();
+    // Once the first request is decided, another can be submitted
+    let resubmitted = submit_excuse(&db, id, &uid).await;
+    delete_house_meeting(&db, id).await;
+
+    assert_eq!(status.attendance_status, AttendanceStatus::Absent);
+    assert!(resubmitted.is_ok());
+}
+
+#[actix_web::test]
+#[ignore = "needs DATABASE_URL"]
+async fn excuses_need_a_house_meeting() {
+    let db = common::database().await;
+
+    let submitted = submit_excuse(&db, -1, &common::unique_uid("hm")).await;
+
+    assert!(matches!(submitted, Err(AppError::NotFound(_))));
+}
+
+#[actix_web::test]
+#[ignore = "needs DATABASE_URL"]
+async fn attendee_edits_only_change_that_meeting() {
+    let db = common::database().await;
+    let uid = common::unique_uid("hm");
+    let edited = house_meeting(&db, date(2021, 10, 4), &uid, AttendanceStatus::Absent).await;
+    let other = house_meeting(&db, date(2021, 10, 4), &uid, AttendanceStatus::Absent).await;
+
+    let excused = edit_attendee(&db, edited, &uid, AttendanceStatus::Excused, Some("sick")).await;
+    let edited_status = roster_status(&db, edited, &uid).await.unwrap();
+    let other_status = roster_status(&db, other, &uid).await.unwrap();
+    delete_house_meeting(&db, edited).await;
+    delete_house_meeting(&db, other).await;
+
+    assert!(excused.is_ok());
+    assert_eq!(edited_status.attendance_status, AttendanceStatus::Excused);
+    assert_eq!(edited_status.excuse.as_deref(), Some("sick"));
+    assert_eq!(other_status.attendance_status, AttendanceStatus::Absent);
+    assert!(other_status.excuse.is_none());
+}
+
+#[actix_web::test]
+#[ignore = "needs DATABASE_URL"]
+async fn attendee_edits_need_the_attendee_on_the_roster() {
+    let db = common::database().await;
+    let uid = common::unique_uid("hm");
+    let id = house_meeting(&db, date(2021, 10, 4), &uid, AttendanceStatus::Absent).await;
+
+    let edit = edit_attendee(
+        &db,
+        id,
+        &common::unique_uid("hm"),
+        AttendanceStatus::Attended,
+        None,
+    )
+    .await;
+    let roster = house::get_house_meeting(&db, id).await.unwrap();
+    delete_house_meeting(&db, id).await;
+
+    assert!(matches!(edit, Err(AppError::NotFound(_))));
+    assert_eq!(roster.members.len(), 1);
+}
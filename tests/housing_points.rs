@@ -0,0 +1,134 @@
+use chrono::{NaiveDate, NaiveDateTime};
+use conditional_backend::housing::{compute, rank, semesters_onfloor, HousingPointInputs};
+use conditional_backend::schema::api::HousingPointWeights;
+use conditional_backend::schema::db::CoopSemester;
+
+fn date(y: i32, m: u32, d: u32) -> NaiveDate {
+    NaiveDate::from_ymd_opt(y, m, d).unwrap()
+}
+
+fn datetime(y: i32, m: u32, d: u32) -> NaiveDateTime {
+    date(y, m, d).and_hms_opt(12, 0, 0).unwrap()
+}
+
+fn inputs(uid: &str, onfloor_granted: Option<NaiveDateTime>) -> HousingPointInputs {
+    HousingPointInputs {
+        uid: uid.to_string(),
+        onfloor_granted,
+        coop_semesters: 0,
+        passed_evals: 0,
+        seminars: 0,
+        directorships: 0,
+        missed_hms: 0,
+    }
+}
+
+#[test]
+fn semesters_onfloor_counts_inclusively() {
+    assert_eq!(semesters_onfloor(None, date(2024, 10, 1)), 0);
+    assert_eq!(
+        semesters_onfloor(Some(datetime(2024, 9, 1)), date(2024, 10, 1)),
+        1
+    );
+    assert_eq!(
+        semesters_onfloor(Some(datetime(2024, 9, 1)), date(2025, 2, 1)),
+        2
+    );
+    assert_eq!(
+        semesters_onfloor(Some(datetime(2023, 1, 15)), date(2024, 8, 20)),
+        4
+    );
+    assert_eq!(
+        semesters_onfloor(Some(datetime(2025, 9, 1)), date(2024, 10, 1)),
+        0
+    );
+}
+
+#[test]
+fn semesters_split_at_june() {
+    assert_eq!(
+        CoopSemester::containing(&date(2024, 5, 31)),
+        CoopSemester::Spring
+    );
+    assert_eq!(
+        CoopSemester::containing(&date(2024, 6, 1)),
+        CoopSemester::Fall
+    );
+    // Co-ops and housing points agree on where the semester changes
+    assert_eq!(
+        semesters_onfloor(Some(datetime(2024, 5, 31)), date(2024, 6, 1)),
+        2
+    );
+    assert_eq!(
+        semesters_onfloor(Some(datetime(2024, 6, 1)), date(2024, 7, 31)),
+        1
+    );
+}
+
+#[test]
+fn compute_sums_weighted_components() {
+    let weights = HousingPointWeights::default();
+    let member = HousingPointInputs {
+        coop_semesters: 1,
+        passed_evals: 2,
+        missed_hms: 1,
+        ..inputs("mom", Some(datetime(2023, 9, 1)))
+    };
+    let points = compute(&member, &weights, date(2024, 10, 1));
+    // 3 semesters on floor * 2 - 1 co-op * 2 + 2 evals * 1 - 1 missed meeting
+    assert_eq!(points.total, 5);
+    assert_eq!(points.components.len(), 6);
+    assert_eq!(
+        points.components.iter().map(|c| c.points).sum::<i64>(),
+        points.total
+    );
+    let onfloor = &points.components[0];
+    assert_eq!(onfloor.source, "semesters_onfloor");
+    assert_eq!((onfloor.count, onfloor.weight, onfloor.points), (3, 2, 6));
+    assert_eq!(points.rank, None);
+}
+
+#[test]
+fn compute_uses_given_weights() {
+    let weights = HousingPointWeights {
+        seminar: 3,
+        ..HousingPointWeights::default()
+    };
+    let member = HousingPointInputs {
+        seminars: 2,
+        ..inputs("dad", None)
+    };
+    assert_eq!(compute(&member, &weights, date(2024, 10, 1)).total, 6);
+}
+
+#[test]
+fn rank_orders_by_total_then_time_on_floor() {
+    let weights = HousingPointWeights::default();
+    let today = date(2024, 10, 1);
+    let members = [
+        inputs("new", Some(datetime(2024, 9, 1))),
+        inputs("off", None),
+        inputs("old", Some(datetime(2023, 9, 1))),
+        HousingPointInputs {
+            passed_evals: 4,
+            ..inputs("tied", Some(datetime(2024, 9, 2)))
+        },
+    ];
+    let ranked = rank(
+        members
+            .iter()
+            .map(|m| compute(m, &weights, today))
+            .collect(),
+    );
+    let order: Vec<(&str, Option<usize>)> =
+        ranked.iter().map(|p| (p.uid.as_str(), p.rank)).collect();
+    assert_eq!(
+        order,
+        [
+            ("old", Some(1)),
+            ("tied", Some(2)),
+            ("new", Some(3)),
+            ("off", Some(4))
+        ]
+    );
+}
@@ -0,0 +1,164 @@
+use chrono::{NaiveDate, NaiveDateTime};
+use conditional_backend::identity::{reconcile, rit_username_key, FreshmanAccountIdentity};
+use conditional_backend::ldap::user::LdapUser;
+use conditional_backend::packet::PacketReport;
+use conditional_backend::schema::api::{
+    IdentityIssueKind, IdentityReport, IncompletePacket, Packet,
+};
+
+fn year_start() -> NaiveDateTime {
+    NaiveDate::from_ymd_opt(2024, 6, 1)
+        .unwrap()
+        .and_hms_opt(0, 0, 0)
+        .unwrap()
+}
+
+fn account(fid: i32, rit_username: Option<&str>) -> FreshmanAccountIdentity {
+    FreshmanAccountIdentity {
+        fid,
+        name: format!("Freshman {fid}"),
+        rit_username: rit_username.map(String::from),
+    }
+}
+
+fn member(uid: &str, rit_dn: &str) -> LdapUser {
+    serde_json::from_value(serde_json::json!({
+        "dn": format!("uid={uid},cn=users,cn=accounts,dc=csh,dc=rit,dc=edu"),
+        "cn": format!("Member {uid}"),
+        "uid": uid,
+        "rit_username": rit_dn,
+        "groups": ["member", "intromembers"],
+        "krb_principal_name": format!("{uid}@CSH.RIT.EDU"),
+        "mail": [],
+    }))
+    .unwrap()
+}
+
+fn packet(username: &str, start: Option<NaiveDateTime>) -> Packet {
+    Packet {
+        username: username.to_string(),
+        name: format!("Packet {username}"),
+        start,
+        upper_signatures: 0,
+        max_upper: 0,
+        misc_signatures: 0,
+    }
+}
+
+fn kinds(report: &IdentityReport, rit_username: &str) -> Vec<IdentityIssueKind> {
+    report
+        .issues
+        .iter()
+        .filter(|i| i.rit_username.as_deref() == Some(rit_username))
+        .map(|i| i.kind)
+        .collect()
+}
+
+#[test]
+fn rit_username_keys() {
+    assert_eq!(rit_username_key(" ABC1234 ").as_deref(), Some("abc1234"));
+    assert_eq!(
+        rit_username_key("uid=abc1234,ou=People,dc=rit,dc=edu").as_deref(),
+        Some("abc1234")
+    );
+    assert_eq!(rit_username_key("   "), None);
+    assert_eq!(rit_username_key("uid=,ou=People"), None);
+}
+
+#[test]
+fn links_all_three_sources() {
+    let report = reconcile(
+        &[account(1, Some("abc1234"))],
+        &[member("kid", "uid=abc1234,ou=People,dc=rit,dc=edu")],
+        &PacketReport {
+            packets: vec![packet("abc1234", Some(year_start()))],
+            incomplete: vec![],
+        },
+        &year_start(),
+    );
+    assert_eq!(report.identities.len(), 1);
+    let identity = &report.identities[0];
+    assert_eq!(identity.fids, [1]);
+    assert_eq!(identity.uids, ["kid"]);
+    assert!(identity.has_packet);
+    assert_eq!(identity.name.as_deref(), Some("Packet abc1234"));
+    assert_eq!(
+        kinds(&report, "abc1234"),
+        [IdentityIssueKind::UnconvertedAccount]
+    );
+}
+
+#[test]
+fn whitespace_and_case_are_matched_and_reported() {
+    let report = reconcile(
+        &[account(1, Some("ABC1234 "))],
+        &[],
+        &PacketReport {
+            packets: vec![packet("abc1234", None)],
+            incomplete: vec![],
+        },
+        &year_start(),
+    );
+    assert_eq!(report.identities.len(), 1);
+    assert_eq!(report.identities[0].fids, [1]);
+    assert_eq!(kinds(&report, "abc1234"), [IdentityIssueKind::InexactMatch]);
+}
+
+#[test]
+fn reports_orphans_duplicates_and_missing() {
+    let old_packet = NaiveDate::from_ymd_opt(2022, 8, 20)
+        .unwrap()
+        .and_hms_opt(0, 0, 0)
+        .unwrap();
+    let report = reconcile(
+        &[
+            account(1, Some("dup1234")),
+            account(2, Some("dup1234")),
+            account(3, None),
+            account(4, Some("nopacket")),
+        ],
+        &[member("blank", "")],
+        &PacketReport {
+            packets: vec![
+                packet("dup1234", Some(year_start())),
+                packet("orphan", Some(year_start())),
+                packet("alumni", Some(old_packet)),
+            ],
+            incomplete: vec![IncompletePacket {
+                packet_id: 9,
+                username: Some(String::from("noname")),
+                missing: vec![String::from("name")],
+            }],
+        },
+        &year_start(),
+    );
+    assert_eq!(
+        kinds(&report, "dup1234"),
+        [IdentityIssueKind::DuplicateAccount]
+    );
+    assert_eq!(
+        kinds(&report, "orphan"),
+        [IdentityIssueKind::OrphanedPacket]
+    );
+    assert_eq!(
+        kinds(&report, "nopacket"),
+        [IdentityIssueKind::MissingPacket]
+    );
+    assert_eq!(
+        kinds(&report, "noname"),
+        [IdentityIssueKind::IncompletePacket]
+    );
+    assert!(kinds(&report, "alumni").is_empty());
+    assert!(report.identities.iter().all(|i| i.rit_username != "alumni"));
+
+    let missing: Vec<(Vec<i32>, Vec<String>)> = report
+        .issues
+        .iter()
+        .filter(|i| i.kind == IdentityIssueKind::MissingRitUsername)
+        .map(|i| (i.fids.clone(), i.uids.clone()))
+        .collect();
+    assert_eq!(
+        missing,
+        [(vec![3], vec![]), (vec![], vec![String::from("blank")])]
+    );
+}
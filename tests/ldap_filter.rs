@@ -0,0 +1,60 @@
+use conditional_backend::ldap::filter::Filter;
+
+#[test]
+fn plain_values_are_unchanged() {
+    assert_eq!(Filter::equal("uid", "mom").to_string(), "(uid=mom)");
+    assert_eq!(Filter::contains("cn", "Joe").to_string(), "(cn=*Joe*)");
+    assert_eq!(Filter::present("ibutton").to_string(), "(ibutton=*)");
+}
+
+#[test]
+fn composite_filters() {
+    let filter = Filter::and(vec![
+        Filter::equal(
+            "memberOf",
+            "cn=active,cn=groups,cn=accounts,dc=csh,dc=rit,dc=edu",
+        ),
+        !Filter::equal(
+            "memberOf",
+            "cn=intromembers,cn=groups,cn=accounts,dc=csh,dc=rit,dc=edu",
+        ),
+    ]);
+    assert_eq!(
+        filter.to_string(),
+        "(&(memberOf=cn=active,cn=groups,cn=accounts,dc=csh,dc=rit,dc=edu)(!(memberOf=cn=\
+         intromembers,cn=groups,cn=accounts,dc=csh,dc=rit,dc=edu)))"
+    );
+    let filter = Filter::or(vec![
+        Filter::contains("uid", "mom"),
+        Filter::contains("cn", "mom"),
+    ]);
+    assert_eq!(filter.to_string(), "(|(uid=*mom*)(cn=*mom*))");
+}
+
+#[test]
+fn filter_injection_is_escaped() {
+    let filter = Filter::or(vec![
+        Filter::contains("uid", "*)(uid=*"),
+        Filter::contains("cn", "*)(uid=*"),
+    ]);
+    assert_eq!(
+        filter.to_string(),
+        "(|(uid=*\\2a\\29\\28uid=\\2a*)(cn=*\\2a\\29\\28uid=\\2a*))"
+    );
+    assert_eq!(
+        Filter::equal("uid", "admin)(|(uid=*").to_string(),
+        "(uid=admin\\29\\28|\\28uid=\\2a)"
+    );
+}
+
+#[test]
+fn backslashes_and_nul_are_escaped() {
+    assert_eq!(Filter::equal("uid", "\\00").to_string(), "(uid=\\5c00)");
+    assert_eq!(Filter::equal("uid", "mom\0").to_string(), "(uid=mom\\00)");
+    assert_eq!(Filter::contains("cn", "\\2a").to_string(), "(cn=*\\5c2a*)");
+}
+
+#[test]
+fn unicode_is_passed_through() {
+    assert_eq!(Filter::equal("cn", "Zoë").to_string(), "(cn=Zoë)");
+}
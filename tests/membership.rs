@@ -0,0 +1,89 @@
+use conditional_backend::ldap::{
+    directory::{Directory, MemoryDirectory},
+    group::Group,
+    membership::{self, GroupAction, GroupChange},
+    search::SearchAttrs,
+};
+
+fn fixture() -> MemoryDirectory {
+    MemoryDirectory::from_file(concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/fixtures/directory.json"
+    ))
+    .unwrap()
+}
+
+async fn groups(directory: &MemoryDirectory, uid: &str) -> Vec<String> {
+    directory
+        .get_user(uid, &SearchAttrs::minimal())
+        .await
+        .unwrap()
+        .remove(0)
+        .groups
+}
+
+#[test]
+fn passing_intro_evals_leaves_intromembers() {
+    let changes = membership::passed_intro_evals("kid");
+    assert_eq!(changes.len(), 3);
+    assert!(changes.contains(&GroupChange {
+        uid: String::from("kid"),
+        group: Group::IntroMembers,
+        action: GroupAction::Remove,
+    }));
+    assert!(changes.iter().all(|c| c.uid == "kid"));
+}
+
+#[actix_web::test]
+async fn apply_moves_intro_member_to_member_groups() {
+    let directory = fixture();
+    let report = membership::apply(&directory, membership::passed_intro_evals("kid")).await;
+    assert_eq!(report.changes.len(), 3);
+    assert!(report.failed.is_empty());
+
+    let groups = groups(&directory, "kid").await;
+    assert!(!groups.contains(&String::from("intromembers")));
+    assert!(groups.contains(&String::from("member")));
+    assert!(groups.contains(&String::from("active")));
+    assert_eq!(
+        directory
+            .get_intro_members(&SearchAttrs::minimal())
+            .await
+            .unwrap()
+            .len(),
+        0
+    );
+}
+
+#[actix_web::test]
+async fn apply_is_idempotent() {
+    let directory = fixture();
+    membership::apply(&directory, membership::passed_intro_evals("kid")).await;
+    let report = membership::apply(&directory, membership::passed_intro_evals("kid")).await;
+    assert!(report.failed.is_empty());
+    let groups = groups(&directory, "kid").await;
+    assert_eq!(groups.iter().filter(|g| *g == "active").count(), 1);
+}
+
+#[actix_web::test]
+async fn apply_reports_failures_and_continues() {
+    let directory = fixture();
+    let mut changes = membership::passed_intro_evals("nobody");
+    changes.extend(membership::passed_intro_evals("kid"));
+    let report = membership::apply(&directory, changes).await;
+    assert_eq!(report.failed.len(), 3);
+    assert_eq!(report.changes.len(), 3);
+    assert!(report.failed.iter().all(|f| f.change.uid == "nobody"));
+}
+
+#[test]
+fn groups_serialize_as_cn() {
+    assert_eq!(
+        serde_json::to_string(&Group::IntroMembers).unwrap(),
+        "\"intromembers\""
+    );
+    assert_eq!(
+        serde_json::to_string(&Group::CurrentStudent).unwrap(),
+        "\"current_student\""
+    );
+}
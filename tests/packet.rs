@@ -0,0 +1,186 @@
+use chrono::Utc;
+use conditional_backend::packet::{
+    completion, get_all_packets, get_packet_signatures, PacketReport, PacketRow,
+};
+use conditional_backend::schema::api::Packet;
+use conditional_backend::schema::db::{PacketRounding, PacketSettings};
+use sqlx::{query, Pool, Postgres};
+
+mod common;
+
+fn row(id: i32, username: Option<&str>, name: Option<&str>) -> PacketRow {
+    PacketRow {
+        id,
+        username: username.map(String::from),
+        name: name.map(String::from),
+        start: None,
+        upper_signatures: 3,
+        max_upper: 5,
+        misc_signatures: 14,
+    }
+}
+
+#[test]
+fn complete_packet_is_converted() {
+    let settings = PacketSettings::default();
+    let packet = Packet::try_from(row(1, Some("abc1234  "), Some("Kid Child"))).unwrap();
+    assert_eq!(packet.username, "abc1234");
+    assert_eq!(packet.counted_misc(&settings), 10);
+    assert_eq!(packet.signatures(&settings), 13);
+    assert_eq!(packet.max_signatures(&settings), 15);
+}
+
+#[test]
+fn misc_cap_comes_from_settings() {
+    let settings = PacketSettings {
+        misc_cap: 20,
+        ..PacketSettings::default()
+    };
+    let packet = Packet::try_from(row(1, Some("abc1234"), Some("Kid Child"))).unwrap();
+    assert_eq!(packet.counted_misc(&settings), 14);
+    assert_eq!(packet.signatures(&settings), 17);
+    assert_eq!(packet.max_signatures(&settings), 25);
+    assert_eq!(packet.completion(&settings), 68.0);
+}
+
+#[test]
+fn completion_is_precise_and_guards_zero() {
+    assert_eq!(completion(2, 3), 200.0 / 3.0);
+    assert_eq!(completion(0, 0), 0.0);
+
+    let packet = Packet {
+        username: String::from("abc1234"),
+        name: String::from("Kid Child"),
+        start: None,
+        upper_signatures: 0,
+        max_upper: 0,
+        misc_signatures: 4,
+    };
+    let settings = PacketSettings {
+        misc_cap: 0,
+        ..PacketSettings::default()
+    };
+    assert_eq!(packet.max_signatures(&settings), 0);
+    assert_eq!(packet.completion(&settings), 0.0);
+}
+
+#[test]
+fn rounding_rules() {
+    assert_eq!(PacketRounding::Floor.round(89.6), 89);
+    assert_eq!(PacketRounding::Nearest.round(89.6), 90);
+    assert_eq!(PacketRounding::Nearest.round(89.4), 89);
+    assert_eq!(PacketRounding::Ceiling.round(89.1), 90);
+    assert_eq!(PacketRounding::Ceiling.round(100.0), 100);
+}
+
+#[test]
+fn incomplete_packet_reports_missing_fields() {
+    let incomplete = Packet::try_from(row(2, Some("def5678"), None)).unwrap_err();
+    assert_eq!(incomplete.packet_id, 2);
+    assert_eq!(incomplete.username.as_deref(), Some("def5678"));
+    assert_eq!(incomplete.missing, ["name"]);
+
+    let incomplete = Packet::try_from(row(3, Some("  "), Some(""))).unwrap_err();
+    assert_eq!(incomplete.username, None);
+    assert_eq!(incomplete.missing, ["username", "name"]);
+}
+
+#[test]
+fn report_skips_incomplete_packets() {
+    let report = PacketReport::from(vec![
+        row(1, Some("abc1234"), Some("Kid Child")),
+        row(2, None, Some("Nobody")),
+        row(3, Some("ghi9012"), Some("Another Kid")),
+    ]);
+    let usernames: Vec<&str> = report.packets.iter().map(|p| p.username.as_str()).collect();
+    assert_eq!(usernames, ["abc1234", "ghi9012"]);
+    assert_eq!(report.incomplete.len(), 1);
+    assert_eq!(report.incomplete[0].packet_id, 2);
+}
+
+async fn execute(db: &Pool<Postgres>, sql: &str, username: &str) {
+    query(sql).bind(username).execute(db).await.unwrap();
+}
+
+/// The packet queries are checked against packet-db's query data when they're
+/// built, and here against a real packet database
+#[actix_web::test]
+#[ignore = "needs PACKET_DATABASE_URL"]
+async fn packet_queries_match_the_packet_schema() {
+    let db = common::packet_database().await;
+    let username = format!("t{:09}", Utc::now().timestamp_subsec_nanos());
+    execute(
+        &db,
+        "INSERT INTO freshman (rit_username, name, onfloor) VALUES ($1, 'Test Kid', false)",
+        &username,
+    )
+    .await;
+    execute(
+        &db,
+        "INSERT INTO packet (freshman_username, start) VALUES ($1, now())",
+        &username,
+    )
+    .await;
+    execute(
+        &db,
+        "INSERT INTO signature_upper (packet_id, member, signed)
+            SELECT id, m.member, m.signed FROM packet,
+                (VALUES ('mom', true), ('dad', false)) AS m(member, signed)
+            WHERE freshman_username = $1",
+        &username,
+    )
+    .await;
+    execute(
+        &db,
+        "INSERT INTO signature_misc (packet_id, member)
+            SELECT id, 'misc' || g FROM packet, generate_series(1, 3) g
+            WHERE freshman_username = $1",
+        &username,
+    )
+    .await;
+
+    let report = get_all_packets(&db).await;
+    let signatures = get_packet_signatures(&username, &PacketSettings::default(), &db).await;
+
+    for table in ["signature_upper", "signature_misc"] {
+        execute(
+            &db,
+            &format!(
+                "DELETE FROM {table} WHERE packet_id IN
+                    (SELECT id FROM packet WHERE freshman_username = $1)"
+            ),
+            &username,
+        )
+        .await;
+    }
+    execute(
+        &db,
+        "DELETE FROM packet WHERE freshman_username = $1",
+        &username,
+    )
+    .await;
+    execute(
+        &db,
+        "DELETE FROM freshman WHERE rit_username = $1",
+        &username,
+    )
+    .await;
+
+    let packet = report
+        .unwrap()
+        .packets
+        .into_iter()
+        .find(|p| p.username == username)
+        .unwrap();
+    assert_eq!(packet.name, "Test Kid");
+    assert_eq!(packet.upper_signatures, 1);
+    assert_eq!(packet.max_upper, 2);
+    assert_eq!(packet.misc_signatures, 3);
+
+    let signatures = signatures.unwrap().unwrap().unwrap();
+    assert_eq!(signatures.upperclassmen.signed, ["mom"]);
+    assert_eq!(signatures.upperclassmen.unsigned, ["dad"]);
+    assert_eq!(signatures.misc.members, ["misc1", "misc2", "misc3"]);
+    assert_eq!(signatures.signatures, 4);
+    assert_eq!(signatures.max_signatures, 12);
+}
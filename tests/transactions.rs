@@ -0,0 +1,137 @@
+use actix_web::test::{call_service, init_service, read_body_json, TestRequest};
+use actix_web::{http::StatusCode, web, App};
+use chrono::NaiveDate;
+use conditional_backend::api::attendance::seminar::submit_seminar_attendance;
+use conditional_backend::app::AppState;
+use conditional_backend::error::AppError;
+use conditional_backend::ldap::directory::MemoryDirectory;
+use conditional_backend::schema::api::ErrorBody;
+use conditional_backend::transaction::UnitOfWork;
+use futures::lock::Mutex;
+use sqlx::{Pool, Postgres};
+use std::collections::HashMap;
+use std::env;
+use std::sync::Arc;
+
+mod common;
+
+async fn seminars_named(db: &Pool<Postgres>, name: &str) -> i64 {
+    sqlx::query_scalar("SELECT COUNT(*) FROM technical_seminars WHERE name = $1")
+        .bind(name)
+        .fetch_one(db)
+        .await
+        .unwrap()
+}
+
+async fn insert_seminar(conn: &mut sqlx::PgConnection, name: &str) -> Result<i32, AppError> {
+    Ok(sqlx::query_scalar(
+        "INSERT INTO technical_seminars (name, timestamp, active, approved)
+            VALUES ($1, NOW(), true, false) RETURNING id",
+    )
+    .bind(name)
+    .fetch_one(conn)
+    .await?)
+}
+
+#[actix_web::test]
+#[ignore = "needs DATABASE_URL"]
+async fn failed_work_leaves_no_rows() {
+    let db = common::database().await;
+    let name = common::unique_name("failed_work_leaves_no_rows");
+
+    let result = UnitOfWork::run(&db, |conn| {
+        let name = name.clone();
+        Box::pin(async move {
+            let id = insert_seminar(conn, &name).await?;
+            // No freshman has a negative id
+            sqlx::query(
+                "INSERT INTO freshman_seminar_attendance (fid, seminar_id) VALUES (-1, $1)",
+            )
+            .bind(id)
+            .execute(conn)
+            .await?;
+            Ok(id)
+        })
+    })
+    .await;
+
+    assert_eq!(result.unwrap_err().code(), "invalid_reference");
+    assert_eq!(seminars_named(&db, &name).await, 0);
+}
+
+#[actix_web::test]
+#[ignore = "needs DATABASE_URL"]
+async fn successful_work_is_committed() {
+    let db = common::database().await;
+    let name = common::unique_name("successful_work_is_committed");
+
+    let id = UnitOfWork::run(&db, |conn| {
+        let name = name.clone();
+        Box::pin(async move { insert_seminar(conn, &name).await })
+    })
+    .await
+    .unwrap();
+
+    assert_eq!(seminars_named(&db, &name).await, 1);
+    sqlx::query("DELETE FROM technical_seminars WHERE id = $1")
+        .bind(id)
+        .execute(&db)
+        .await
+        .unwrap();
+}
+
+#[actix_web::test]
+#[ignore = "needs DATABASE_URL"]
+async fn dropped_work_is_rolled_back() {
+    let db = common::database().await;
+    let name = common::unique_name("dropped_work_is_rolled_back");
+
+    let mut uow = UnitOfWork::begin(&db).await.unwrap();
+    insert_seminar(&mut uow, &name).await.unwrap();
+    drop(uow);
+
+    assert_eq!(seminars_named(&db, &name).await, 0);
+}
+
+#[actix_web::test]
+#[ignore = "needs DATABASE_URL"]
+async fn failing_handler_leaves_no_rows() {
+    let db = common::database().await;
+    env::set_var("SECURITY_ENABLED", "false");
+    let name = common::unique_name("failing_handler_leaves_no_rows");
+
+    let state = web::Data::new(AppState {
+        db: db.clone(),
+        packet_db: db.clone(),
+        year_start: NaiveDate::from_ymd_opt(2023, 6, 1)
+            .unwrap()
+            .and_hms_opt(0, 0, 0)
+            .unwrap(),
+        ldap: Arc::new(MemoryDirectory::new(Vec::new())),
+        jwt_cache: Arc::new(Mutex::new(HashMap::new())),
+    });
+    let app = init_service(
+        App::new()
+            .app_data(state)
+            .service(web::scope("/attendance").service(submit_seminar_attendance)),
+    )
+    .await;
+
+    // The seminar is inserted before its attendance, which fails
+    let req = TestRequest::post()
+        .uri("/attendance/seminar")
+        .set_json(serde_json::json!({
+            "name": name,
+            "timestamp": "2024-10-01T18:00:00",
+            "members": ["alice"],
+            "frosh": [-1],
+            "approved": false,
+        }))
+        .to_request();
+    let res = call_service(&app, req).await;
+    assert_eq!(res.status(), StatusCode::UNPROCESSABLE_ENTITY);
+    let body: ErrorBody = read_body_json(res).await;
+    assert_eq!(body.code, "invalid_reference");
+
+    assert_eq!(seminars_named(&db, &name).await, 0);
+}